@@ -6,21 +6,48 @@
 
     println!("cargo:rerun-if-changed=build.rs");
 
+    // 动态加载模式下只需要头文件，不链接驱动库
+    let dynamic = env::var_os("CARGO_FEATURE_DYNAMIC").is_some();
+
     let nvidia = Cfg::new("nvidia");
     let iluvatar = Cfg::new("iluvatar");
+    let cuda_12_3 = Cfg::new("cuda_12_3");
+    let cuda_12_8 = Cfg::new("cuda_12_8");
+
+    // 模拟驱动与 NVIDIA 驱动的接口一致，不需要 Toolkit
+    if env::var_os("CARGO_FEATURE_MOCK").is_some() {
+        nvidia.define();
+        cuda_12_3.define();
+        cuda_12_8.define();
+        return;
+    }
     let toolkit = if let Some(corex) = find_corex() {
-        include_corex(&corex);
+        if !dynamic {
+            include_corex(&corex)
+        }
         iluvatar.define();
         corex
     } else if let Some(cuda_root) = find_cuda_root() {
-        include_cuda();
+        if !dynamic {
+            include_cuda()
+        }
         nvidia.define();
+        // 条件节点需要 12.3 的头文件，IF/ELSE 和 SWITCH 条件节点需要 12.8 的头文件
+        let version = cuda_version(&cuda_root);
+        if version.is_some_and(|v| v >= 12030) {
+            cuda_12_3.define()
+        }
+        if version.is_some_and(|v| v >= 12080) {
+            cuda_12_8.define()
+        }
         cuda_root
     } else {
         return;
     };
 
-    println!("cargo:rustc-link-lib=dylib=nvrtc");
+    if !dynamic {
+        println!("cargo:rustc-link-lib=dylib=nvrtc")
+    }
 
     // Tell cargo to invalidate the built crate whenever the wrapper changes.
     println!("cargo:rerun-if-changed=wrapper.h");
@@ -54,7 +81,119 @@
 
     // Write the bindings to the $OUT_DIR/bindings.rs file.
     let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
-    bindings
-        .write_to_file(out_path.join("bindings.rs"))
+    if dynamic {
+        #[cfg(feature = "dynamic")]
+        std::fs::write(
+            out_path.join("bindings.rs"),
+            dynamic_loading(&bindings.to_string()),
+        )
         .expect("Couldn't write bindings!");
+    } else {
+        bindings
+            .write_to_file(out_path.join("bindings.rs"))
+            .expect("Couldn't write bindings!");
+    }
+}
+
+/// 从 Toolkit 的 `cuda.h` 读取 `CUDA_VERSION`，例如 12.8 为 12080。
+fn cuda_version(cuda_root: &std::path::Path) -> Option<u32> {
+    std::fs::read_to_string(cuda_root.join("include").join("cuda.h"))
+        .ok()?
+        .lines()
+        .find_map(|line| {
+            let version = line.trim().strip_prefix("#define CUDA_VERSION")?;
+            version.trim().parse().ok()
+        })
+}
+
+/// 将 bindgen 生成的 `extern "C"` 函数声明替换为同名的包装函数，
+/// 包装函数通过运行时加载的函数表调用驱动和 NVRTC。
+#[cfg(feature = "dynamic")]
+fn dynamic_loading(bindings: &str) -> String {
+    use quote::{format_ident, quote};
+    use syn::{FnArg, ForeignItem, Item, Pat};
+
+    let file = syn::parse_file(bindings).expect("Unable to parse bindings");
+
+    let mut items = Vec::new();
+    let mut driver = Vec::new();
+    let mut nvrtc = Vec::new();
+    for item in file.items {
+        let Item::ForeignMod(foreign) = item else {
+            items.push(item);
+            continue;
+        };
+        for item in foreign.items {
+            let ForeignItem::Fn(f) = item else {
+                panic!("Unexpected foreign item in bindings")
+            };
+            if f.sig.ident.to_string().starts_with("nvrtc") {
+                nvrtc.push(f.sig)
+            } else {
+                driver.push(f.sig)
+            }
+        }
+    }
+
+    let table = |api: &str, getter: &str, lib: &str, sigs: &[syn::Signature]| {
+        let api = format_ident!("{api}");
+        let getter = format_ident!("{getter}");
+        let names = sigs.iter().map(|sig| &sig.ident).collect::<Vec<_>>();
+        let symbols = names
+            .iter()
+            .map(|name| syn::LitByteStr::new(format!("{name}\0").as_bytes(), name.span()));
+        let types = sigs.iter().map(|sig| {
+            let inputs = &sig.inputs;
+            let output = &sig.output;
+            quote!(Option<unsafe extern "C" fn(#inputs) #output>)
+        });
+        let wrappers = sigs.iter().map(|sig| {
+            let name = &sig.ident;
+            let inputs = &sig.inputs;
+            let output = &sig.output;
+            let args = inputs.iter().map(|arg| match arg {
+                FnArg::Typed(arg) => match &*arg.pat {
+                    Pat::Ident(ident) => &ident.ident,
+                    _ => panic!("Unexpected argument pattern in {name}"),
+                },
+                FnArg::Receiver(_) => unreachable!(),
+            });
+            let msg = format!("`{name}` is not found in {lib}");
+            quote! {
+                #[inline]
+                #[allow(clippy::missing_safety_doc, clippy::too_many_arguments)]
+                pub unsafe fn #name(#inputs) #output {
+                    unsafe { (crate::library::#getter().#name.expect(#msg))(#(#args),*) }
+                }
+            }
+        });
+        quote! {
+            #[allow(clippy::type_complexity)]
+            pub struct #api {
+                #( pub #names: #types, )*
+            }
+
+            impl #api {
+                /// # Safety
+                ///
+                /// The `lib` must be the library declaring these symbols.
+                pub unsafe fn load(lib: &::libloading::Library) -> Self {
+                    Self {
+                        #( #names: unsafe { lib.get(#symbols) }.ok().map(|f| *f), )*
+                    }
+                }
+            }
+
+            #( #wrappers )*
+        }
+    };
+    let driver = table("DriverApi", "driver", "the driver library", &driver);
+    let nvrtc = table("NvrtcApi", "nvrtc", "the NVRTC library", &nvrtc);
+
+    quote! {
+        #( #items )*
+        #driver
+        #nvrtc
+    }
+    .to_string()
 }
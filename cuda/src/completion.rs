@@ -0,0 +1,142 @@
+use crate::{
+    CallSite, Error, Event, Stream,
+    bindings::{CUresult, CUstream},
+};
+use context_spore::AsRaw;
+use std::{
+    ffi::c_void,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+/// 流上的任务完成时就绪的 future。
+///
+/// 由流回调唤醒，等待时不占用线程。
+/// 流上的任务失败时，回调仍会执行，future 以流上的错误就绪。
+pub struct Completion(Arc<Mutex<State>>);
+
+#[derive(Default)]
+struct State {
+    /// 回调中不能调用驱动接口，只记录状态码，在 [`Completion::poll`] 中转换为错误。
+    status: Option<CUresult>,
+    waker: Option<Waker>,
+}
+
+impl Future for Completion {
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.0.lock().unwrap();
+        if let Some(status) = state.status {
+            Poll::Ready(Error::check(
+                status,
+                CallSite {
+                    expr: "cuStreamAddCallback",
+                    file: file!(),
+                    line: line!(),
+                },
+            ))
+        } else {
+            match &mut state.waker {
+                Some(waker) => waker.clone_from(cx.waker()),
+                None => state.waker = Some(cx.waker().clone()),
+            }
+            Poll::Pending
+        }
+    }
+}
+
+impl Stream<'_> {
+    /// 流上已提交的任务全部完成时就绪。
+    #[inline]
+    #[track_caller]
+    pub fn completion(&self) -> Completion {
+        self.try_completion().unwrap()
+    }
+
+    pub fn try_completion(&self) -> Result<Completion, Error> {
+        extern "C" fn callback(_stream: CUstream, status: CUresult, user_data: *mut c_void) {
+            let state = unsafe { Arc::from_raw(user_data as *const Mutex<State>) };
+            let waker = {
+                let mut state = state.lock().unwrap();
+                state.status = Some(status);
+                state.waker.take()
+            };
+            if let Some(waker) = waker {
+                waker.wake()
+            }
+        }
+
+        let state = Arc::new(Mutex::new(State::default()));
+        let user_data = Arc::into_raw(state.clone()).cast_mut();
+        try_driver!(cuStreamAddCallback(
+            self.as_raw(),
+            Some(callback),
+            user_data.cast(),
+            0
+        ))
+        // 回调未注册，回收其持有的引用
+        .inspect_err(|_| drop(unsafe { Arc::from_raw(user_data) }))?;
+        Ok(Completion(state))
+    }
+}
+
+impl Event<'_> {
+    /// 令 `stream` 等待事件，事件和 `stream` 上已提交的任务都完成时就绪。
+    #[inline]
+    #[track_caller]
+    pub fn completion(&self, stream: &Stream) -> Completion {
+        self.try_completion(stream).unwrap()
+    }
+
+    pub fn try_completion(&self, stream: &Stream) -> Result<Completion, Error> {
+        stream.try_wait_for(self)?;
+        stream.try_completion()
+    }
+}
+
+#[test]
+fn test_behavior() {
+    use std::task::Wake;
+    use std::thread::{self, Thread};
+
+    struct Unpark(Thread);
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark()
+        }
+    }
+
+    fn block_on<T>(f: impl Future<Output = T> + Send + 'static) -> T {
+        let waker = Waker::from(Arc::new(Unpark(thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut f = std::pin::pin!(f);
+        loop {
+            match f.as_mut().poll(&mut cx) {
+                Poll::Ready(ans) => break ans,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    crate::Device::new(0).context().apply(|ctx| {
+        let mut mem = ctx.malloc::<u32>(1 << 10);
+        let stream = ctx.stream();
+        stream.memset_d32(&mut mem, 1);
+        block_on(stream.completion()).unwrap();
+
+        let event = stream.memset_d32(&mut mem, 2).record();
+        let waiter = ctx.stream_builder().non_blocking(true).build();
+        block_on(event.completion(&waiter)).unwrap();
+        assert!(event.is_complete());
+
+        let mut host = vec![0u32; 1 << 10];
+        crate::memcpy_d2h(&mut host, &mem);
+        assert!(host.iter().all(|&x| x == 2))
+    })
+}
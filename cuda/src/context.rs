@@ -1,5 +1,5 @@
 ﻿use crate::{
-    Device, MemSize,
+    Device, Error, MemSize,
     bindings::{CUcontext, CUdevice},
 };
 use context_spore::{AsRaw, RawContainer};
@@ -17,31 +17,41 @@ pub struct Context {
 
 impl Device {
     #[inline]
+    #[track_caller]
     pub fn context(&self) -> Context {
+        self.try_context().unwrap()
+    }
+
+    pub fn try_context(&self) -> Result<Context, Error> {
         const { assert!(size_of::<Context>() == size_of::<[usize; 2]>()) }
         const { assert!(align_of::<Context>() == align_of::<usize>()) }
 
         let dev = unsafe { self.as_raw() };
         let mut ctx = null_mut();
-        driver!(cuCtxCreate_v2(&mut ctx, 0, dev));
+        try_driver!(cuCtxCreate_v2(&mut ctx, 0, dev))?;
         driver!(cuCtxPopCurrent_v2(null_mut()));
-        Context {
+        Ok(Context {
             ctx,
             dev,
             primary: false,
-        }
+        })
     }
 
     #[inline]
+    #[track_caller]
     pub fn retain_primary(&self) -> Context {
+        self.try_retain_primary().unwrap()
+    }
+
+    pub fn try_retain_primary(&self) -> Result<Context, Error> {
         let dev = unsafe { self.as_raw() };
         let mut ctx = null_mut();
-        driver!(cuDevicePrimaryCtxRetain(&mut ctx, dev));
-        Context {
+        try_driver!(cuDevicePrimaryCtxRetain(&mut ctx, dev))?;
+        Ok(Context {
             ctx,
             dev,
             primary: true,
-        }
+        })
     }
 }
 
@@ -81,13 +91,21 @@ impl Context {
     }
 
     #[inline]
+    #[track_caller]
     pub fn apply<T>(&self, f: impl FnOnce(&CurrentCtx) -> T) -> T {
-        driver!(cuCtxPushCurrent_v2(self.ctx));
+        self.try_apply(f).unwrap()
+    }
+
+    /// 将上下文压栈为当前上下文，执行 `f` 后出栈。压栈失败时不执行 `f`。
+    pub fn try_apply<T>(&self, f: impl FnOnce(&CurrentCtx) -> T) -> Result<T, Error> {
+        try_driver!(cuCtxPushCurrent_v2(self.ctx))?;
         let ans = f(&CurrentCtx(self.ctx));
         let mut top = null_mut();
-        driver!(cuCtxPopCurrent_v2(&mut top));
-        assert_eq!(top, self.ctx);
-        ans
+        try_driver!(cuCtxPopCurrent_v2(&mut top))?;
+        if top != self.ctx {
+            return Err(invalid_value!("f leaves the context stack balanced"));
+        }
+        Ok(ans)
     }
 }
 
@@ -107,25 +125,42 @@ impl AsRaw for CurrentCtx {
 
 impl CurrentCtx {
     #[inline]
+    #[track_caller]
     pub fn dev(&self) -> Device {
+        self.try_dev().unwrap()
+    }
+
+    pub fn try_dev(&self) -> Result<Device, Error> {
         let mut dev = 0;
-        driver!(cuCtxGetDevice(&mut dev));
-        Device::new(dev)
+        try_driver!(cuCtxGetDevice(&mut dev))?;
+        Ok(Device::new(dev))
     }
 
     /// 上下文同步。
     #[inline]
+    #[track_caller]
     pub fn synchronize(&self) {
-        driver!(cuCtxSynchronize())
+        self.try_synchronize().unwrap()
+    }
+
+    /// 上下文同步，返回同步过程中发现的异步错误。
+    #[inline]
+    pub fn try_synchronize(&self) -> Result<(), Error> {
+        try_driver!(cuCtxSynchronize())
     }
 
-    /// 获取驱动反馈的当前上下文可用存储空间和总存储空间。
     #[inline]
+    #[track_caller]
     pub fn mem_info(&self) -> (MemSize, MemSize) {
+        self.try_mem_info().unwrap()
+    }
+
+    /// 获取驱动反馈的当前上下文可用存储空间和总存储空间。
+    pub fn try_mem_info(&self) -> Result<(MemSize, MemSize), Error> {
         let mut free = 0;
         let mut total = 0;
-        driver!(cuMemGetInfo_v2(&mut free, &mut total));
-        (free.into(), total.into())
+        try_driver!(cuMemGetInfo_v2(&mut free, &mut total))?;
+        Ok((free.into(), total.into()))
     }
 
     /// 如果存在当前上下文，在当前上下文上执行依赖上下文的操作。
@@ -175,18 +210,23 @@ impl CurrentCtx {
 
 impl CurrentCtx {
     /// 将一段 host 存储空间注册为锁页内存，以允许从这个上下文直接访问。
+    #[track_caller]
     pub fn lock_page<T>(&self, slice: &[T]) {
-        let ptrs = slice.as_ptr_range();
-        driver!(cuMemHostRegister_v2(
-            ptrs.start as _,
-            ptrs.end as usize - ptrs.start as usize,
-            0,
-        ));
+        self.try_lock_page(slice).unwrap()
+    }
+
+    pub fn try_lock_page<T>(&self, slice: &[T]) -> Result<(), Error> {
+        self.host_mem_builder().try_lock_page(slice)
     }
 
     /// 将一段 host 存储空间从锁页内存注销。
+    #[track_caller]
     pub fn unlock_page<T>(&self, slice: &[T]) {
-        driver!(cuMemHostUnregister(slice.as_ptr() as _));
+        self.try_unlock_page(slice).unwrap()
+    }
+
+    pub fn try_unlock_page<T>(&self, slice: &[T]) -> Result<(), Error> {
+        try_driver!(cuMemHostUnregister(slice.as_ptr() as _))
     }
 }
 
@@ -0,0 +1,294 @@
+use crate::{
+    CurrentCtx, DevByte, DevMem, Error, VirByte, bindings::CUdeviceptr, try_memcpy_d2d,
+    try_memcpy_d2h, try_memcpy_h2d,
+};
+use context_spore::AsRaw;
+use std::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut, Index, IndexMut},
+    ptr::{NonNull, slice_from_raw_parts, slice_from_raw_parts_mut},
+    slice::{SliceIndex, from_raw_parts, from_raw_parts_mut},
+};
+
+/// 以 `T` 为元素的设备存储区域，长度和下标都以元素计。
+///
+/// 与 `[DevByte]` 一样只表示设备地址，不能在主机上访问元素。
+#[repr(transparent)]
+pub struct DevSlice<T>([T]);
+
+/// 以 `T` 为元素的设备存储空间。
+#[repr(transparent)]
+pub struct DevBuf<'ctx, T>(DevMem<'ctx>, PhantomData<T>);
+
+impl<'ctx> DevMem<'ctx> {
+    /// 将存储空间视为 `T` 的数组。存储空间的长度必须是 `T` 的整数倍。
+    pub fn into_buf<T: Copy>(self) -> DevBuf<'ctx, T> {
+        DevSlice::<T>::from_bytes(&self);
+        DevBuf(self, PhantomData)
+    }
+}
+
+impl<'ctx, T> DevBuf<'ctx, T> {
+    #[inline]
+    pub fn into_mem(self) -> DevMem<'ctx> {
+        self.0
+    }
+}
+
+impl CurrentCtx {
+    #[inline]
+    #[track_caller]
+    pub fn malloc_buf<T: Copy>(&self, len: usize) -> DevBuf<'_, T> {
+        self.try_malloc_buf(len).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn from_host_buf<T: Copy>(&self, slice: &[T]) -> DevBuf<'_, T> {
+        self.try_from_host_buf(slice).unwrap()
+    }
+
+    pub fn try_malloc_buf<T: Copy>(&self, len: usize) -> Result<DevBuf<'_, T>, Error> {
+        self.try_malloc::<T>(len).map(DevMem::into_buf)
+    }
+
+    pub fn try_from_host_buf<T: Copy>(&self, slice: &[T]) -> Result<DevBuf<'_, T>, Error> {
+        self.try_from_host(slice).map(DevMem::into_buf)
+    }
+}
+
+impl<T> Deref for DevBuf<'_, T> {
+    type Target = DevSlice<T>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe { DevSlice::from_raw_parts(self.0.as_ptr() as _, self.0.len() / size_of::<T>()) }
+    }
+}
+
+impl<T> DerefMut for DevBuf<'_, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe {
+            DevSlice::from_raw_parts_mut(self.0.as_mut_ptr() as _, self.0.len() / size_of::<T>())
+        }
+    }
+}
+
+impl<T> DevSlice<T> {
+    /// # Safety
+    ///
+    /// `[ptr, ptr + len * size_of::<T>())` 是 `'a` 期间有效的设备存储区域。
+    unsafe fn from_raw_parts<'a>(ptr: CUdeviceptr, len: usize) -> &'a Self {
+        let ptr = if len == 0 {
+            NonNull::dangling().as_ptr()
+        } else {
+            ptr as *const T
+        };
+        unsafe { &*(slice_from_raw_parts(ptr, len) as *const Self) }
+    }
+
+    /// # Safety
+    ///
+    /// `[ptr, ptr + len * size_of::<T>())` 是 `'a` 期间有效且未被借用的设备存储区域。
+    unsafe fn from_raw_parts_mut<'a>(ptr: CUdeviceptr, len: usize) -> &'a mut Self {
+        let ptr = if len == 0 {
+            NonNull::dangling().as_ptr()
+        } else {
+            ptr as *mut T
+        };
+        unsafe { &mut *(slice_from_raw_parts_mut(ptr, len) as *mut Self) }
+    }
+
+    #[inline]
+    fn from_slice(slice: &[T]) -> &Self {
+        unsafe { &*(slice as *const [T] as *const Self) }
+    }
+
+    #[inline]
+    fn from_slice_mut(slice: &mut [T]) -> &mut Self {
+        unsafe { &mut *(slice as *mut [T] as *mut Self) }
+    }
+
+    /// 检查字节区域能否视为 `T` 的数组，返回元素数量。
+    fn check_bytes(ptr: usize, len: usize) -> usize {
+        assert_ne!(size_of::<T>(), 0);
+        assert_eq!(len % size_of::<T>(), 0);
+        assert!(len == 0 || ptr.is_multiple_of(align_of::<T>()));
+        len / size_of::<T>()
+    }
+
+    pub fn from_bytes(bytes: &[DevByte]) -> &Self {
+        let len = Self::check_bytes(bytes.as_ptr() as _, bytes.len());
+        unsafe { Self::from_raw_parts(bytes.as_ptr() as _, len) }
+    }
+
+    pub fn from_bytes_mut(bytes: &mut [DevByte]) -> &mut Self {
+        let len = Self::check_bytes(bytes.as_ptr() as _, bytes.len());
+        unsafe { Self::from_raw_parts_mut(bytes.as_mut_ptr() as _, len) }
+    }
+
+    /// 将虚存储区域视为 `T` 的数组。区域中的虚地址应当已经映射。
+    pub fn from_vir(bytes: &[VirByte]) -> &Self {
+        let len = Self::check_bytes(bytes.as_ptr() as _, bytes.len());
+        unsafe { Self::from_raw_parts(bytes.as_ptr() as _, len) }
+    }
+
+    /// 将虚存储区域视为 `T` 的数组。区域中的虚地址应当已经映射。
+    pub fn from_vir_mut(bytes: &mut [VirByte]) -> &mut Self {
+        let len = Self::check_bytes(bytes.as_ptr() as _, bytes.len());
+        unsafe { Self::from_raw_parts_mut(bytes.as_mut_ptr() as _, len) }
+    }
+
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 设备指针，可作为 kernel 参数。
+    #[inline]
+    pub const fn as_ptr(&self) -> *const T {
+        self.0.as_ptr()
+    }
+
+    #[inline]
+    pub const fn as_mut_ptr(&mut self) -> *mut T {
+        self.0.as_mut_ptr()
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[DevByte] {
+        unsafe { from_raw_parts(self.0.as_ptr().cast(), size_of_val(&self.0)) }
+    }
+
+    #[inline]
+    pub fn as_bytes_mut(&mut self) -> &mut [DevByte] {
+        unsafe { from_raw_parts_mut(self.0.as_mut_ptr().cast(), size_of_val(&self.0)) }
+    }
+
+    #[inline]
+    pub fn split_at(&self, mid: usize) -> (&Self, &Self) {
+        let (a, b) = self.0.split_at(mid);
+        (Self::from_slice(a), Self::from_slice(b))
+    }
+
+    #[inline]
+    pub fn split_at_mut(&mut self, mid: usize) -> (&mut Self, &mut Self) {
+        let (a, b) = self.0.split_at_mut(mid);
+        (Self::from_slice_mut(a), Self::from_slice_mut(b))
+    }
+
+    #[inline]
+    pub fn chunks(&self, chunk_size: usize) -> impl Iterator<Item = &Self> {
+        self.0.chunks(chunk_size).map(Self::from_slice)
+    }
+
+    #[inline]
+    pub fn chunks_mut(&mut self, chunk_size: usize) -> impl Iterator<Item = &mut Self> {
+        self.0.chunks_mut(chunk_size).map(Self::from_slice_mut)
+    }
+}
+
+impl<T: Copy> DevSlice<T> {
+    #[inline]
+    #[track_caller]
+    pub fn copy_from_host(&mut self, src: &[T]) {
+        self.try_copy_from_host(src).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn copy_to_host(&self, dst: &mut [T]) {
+        self.try_copy_to_host(dst).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn copy_from(&mut self, src: &Self) {
+        self.try_copy_from(src).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn to_vec(&self) -> Vec<T> {
+        self.try_to_vec().unwrap()
+    }
+
+    pub fn try_copy_from_host(&mut self, src: &[T]) -> Result<(), Error> {
+        assert_eq!(self.len(), src.len());
+        try_memcpy_h2d(self.as_bytes_mut(), src)
+    }
+
+    pub fn try_copy_to_host(&self, dst: &mut [T]) -> Result<(), Error> {
+        assert_eq!(self.len(), dst.len());
+        try_memcpy_d2h(dst, self.as_bytes())
+    }
+
+    pub fn try_copy_from(&mut self, src: &Self) -> Result<(), Error> {
+        assert_eq!(self.len(), src.len());
+        try_memcpy_d2d(self.as_bytes_mut(), src.as_bytes())
+    }
+
+    pub fn try_to_vec(&self) -> Result<Vec<T>, Error> {
+        let mut ans = Vec::with_capacity(self.len());
+        let spare = &mut ans.spare_capacity_mut()[..self.len()];
+        try_memcpy_d2h(spare, self.as_bytes())?;
+        unsafe { ans.set_len(self.len()) };
+        Ok(ans)
+    }
+}
+
+impl<T, I: SliceIndex<[T], Output = [T]>> Index<I> for DevSlice<T> {
+    type Output = Self;
+    #[inline]
+    fn index(&self, index: I) -> &Self::Output {
+        Self::from_slice(&self.0[index])
+    }
+}
+
+impl<T, I: SliceIndex<[T], Output = [T]>> IndexMut<I> for DevSlice<T> {
+    #[inline]
+    fn index_mut(&mut self, index: I) -> &mut Self::Output {
+        Self::from_slice_mut(&mut self.0[index])
+    }
+}
+
+impl<T> AsRaw for DevSlice<T> {
+    type Raw = CUdeviceptr;
+    #[inline]
+    unsafe fn as_raw(&self) -> Self::Raw {
+        self.0.as_ptr() as _
+    }
+}
+
+#[test]
+fn test_behavior() {
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    crate::Device::new(0).context().apply(|ctx| {
+        let host = (0..1024).collect::<Vec<u32>>();
+        let mut buf = ctx.from_host_buf(&host);
+        assert_eq!(buf.len(), host.len());
+        assert_eq!(buf.as_bytes().len(), size_of_val(&*host));
+
+        let (a, b) = buf.split_at_mut(256);
+        a.copy_from(&b[..256]);
+        for (i, chunk) in buf.chunks(256).enumerate() {
+            // 第一块已经被第二块覆盖
+            let offset = (i.max(1) * 256) as u32;
+            assert_eq!(chunk.to_vec(), (offset..offset + 256).collect::<Vec<_>>())
+        }
+
+        let mut out = vec![0; 16];
+        buf[1008..].copy_to_host(&mut out);
+        assert_eq!(out, host[1008..]);
+
+        let mem = buf.into_mem();
+        assert_eq!(DevSlice::<u64>::from_bytes(&mem).len(), 512)
+    })
+}
@@ -1,4 +1,4 @@
-use crate::{Blob, CurrentCtx, Stream, bindings::CUdeviceptr};
+use crate::{Blob, CurrentCtx, Error, Stream, bindings::CUdeviceptr};
 use context_spore::{AsRaw, impl_spore};
 use std::{
     alloc::Layout,
@@ -11,118 +11,260 @@ use std::{
 pub struct DevByte(u8);
 
 #[inline]
+#[track_caller]
 pub fn memcpy_d2h<T: Copy>(dst: &mut [T], src: &[DevByte]) {
-    let len = size_of_val(dst);
-    let dst = dst.as_mut_ptr().cast();
-    assert_eq!(len, size_of_val(src));
-    driver!(cuMemcpyDtoH_v2(dst, src.as_ptr() as _, len))
+    try_memcpy_d2h(dst, src).unwrap()
 }
 
 #[inline]
+#[track_caller]
 pub fn memcpy_h2d<T: Copy>(dst: &mut [DevByte], src: &[T]) {
-    let len = size_of_val(src);
-    let src = src.as_ptr().cast();
-    assert_eq!(len, size_of_val(dst));
-    driver!(cuMemcpyHtoD_v2(dst.as_ptr() as _, src, len))
+    try_memcpy_h2d(dst, src).unwrap()
 }
 
 #[inline]
+#[track_caller]
 pub fn memcpy_d2d(dst: &mut [DevByte], src: &[DevByte]) {
+    try_memcpy_d2d(dst, src).unwrap()
+}
+
+pub fn try_memcpy_d2h<T: Copy>(dst: &mut [T], src: &[DevByte]) -> Result<(), Error> {
+    let len = size_of_val(dst);
+    let dst = dst.as_mut_ptr().cast();
+    if len != size_of_val(src) {
+        return Err(invalid_value!("dst and src have the same length"));
+    }
+    try_driver!(cuMemcpyDtoH_v2(dst, src.as_ptr() as _, len))
+}
+
+pub fn try_memcpy_h2d<T: Copy>(dst: &mut [DevByte], src: &[T]) -> Result<(), Error> {
     let len = size_of_val(src);
-    assert_eq!(len, size_of_val(dst));
-    driver!(cuMemcpyDtoD_v2(dst.as_ptr() as _, src.as_ptr() as _, len))
+    let src = src.as_ptr().cast();
+    if len != size_of_val(dst) {
+        return Err(invalid_value!("dst and src have the same length"));
+    }
+    try_driver!(cuMemcpyHtoD_v2(dst.as_ptr() as _, src, len))
+}
+
+pub fn try_memcpy_d2d(dst: &mut [DevByte], src: &[DevByte]) -> Result<(), Error> {
+    let len = size_of_val(src);
+    if len != size_of_val(dst) {
+        return Err(invalid_value!("dst and src have the same length"));
+    }
+    try_driver!(cuMemcpyDtoD_v2(dst.as_ptr() as _, src.as_ptr() as _, len))
 }
 
 impl Stream<'_> {
     #[inline]
+    #[track_caller]
     pub fn memcpy_h2d<T: Copy>(&self, dst: &mut [DevByte], src: &[T]) -> &Self {
+        self.try_memcpy_h2d(dst, src).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn memcpy_d2d(&self, dst: &mut [DevByte], src: &[DevByte]) -> &Self {
+        self.try_memcpy_d2d(dst, src).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn memcpy_d2h<T: Copy>(&self, dst: &mut [T], src: &[DevByte]) -> &Self {
+        self.try_memcpy_d2h(dst, src).unwrap()
+    }
+
+    pub fn try_memcpy_h2d<T: Copy>(&self, dst: &mut [DevByte], src: &[T]) -> Result<&Self, Error> {
         let len = size_of_val(src);
-        assert_eq!(len, size_of_val(dst));
-        driver!(cuMemcpyHtoDAsync_v2(
+        if len != size_of_val(dst) {
+            return Err(invalid_value!("dst and src have the same length"));
+        }
+        try_driver!(cuMemcpyHtoDAsync_v2(
             dst.as_mut_ptr() as _,
             src.as_ptr().cast(),
             len,
             self.as_raw()
-        ));
-        self
+        ))?;
+        Ok(self)
     }
 
-    #[inline]
-    pub fn memcpy_d2d(&self, dst: &mut [DevByte], src: &[DevByte]) -> &Self {
+    pub fn try_memcpy_d2d(&self, dst: &mut [DevByte], src: &[DevByte]) -> Result<&Self, Error> {
         let len = size_of_val(src);
-        assert_eq!(len, size_of_val(dst));
-        driver!(cuMemcpyDtoDAsync_v2(
+        if len != size_of_val(dst) {
+            return Err(invalid_value!("dst and src have the same length"));
+        }
+        try_driver!(cuMemcpyDtoDAsync_v2(
             dst.as_mut_ptr() as _,
             src.as_ptr() as _,
             len,
             self.as_raw()
-        ));
-        self
+        ))?;
+        Ok(self)
     }
 
-    #[inline]
-    pub fn memcpy_d2h<T: Copy>(&self, dst: &mut [T], src: &[DevByte]) -> &Self {
+    pub fn try_memcpy_d2h<T: Copy>(&self, dst: &mut [T], src: &[DevByte]) -> Result<&Self, Error> {
         let len = size_of_val(src);
-        assert_eq!(len, size_of_val(dst));
-        driver!(cuMemcpyDtoHAsync_v2(
+        if len != size_of_val(dst) {
+            return Err(invalid_value!("dst and src have the same length"));
+        }
+        try_driver!(cuMemcpyDtoHAsync_v2(
             dst.as_mut_ptr().cast(),
             src.as_ptr() as _,
             len,
             self.as_raw()
-        ));
-        self
+        ))?;
+        Ok(self)
     }
 }
 
 impl_spore!(DevMem and DevMemSpore by (CurrentCtx, Blob<CUdeviceptr>));
 
 impl CurrentCtx {
+    #[inline]
+    #[track_caller]
     pub fn malloc<T: Copy>(&self, len: usize) -> DevMem<'_> {
-        let len = Layout::array::<T>(len).unwrap().size();
+        self.try_malloc::<T>(len).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn from_host<T: Copy>(&self, slice: &[T]) -> DevMem<'_> {
+        self.try_from_host(slice).unwrap()
+    }
+
+    pub fn try_malloc<T: Copy>(&self, len: usize) -> Result<DevMem<'_>, Error> {
+        let len = Layout::array::<T>(len)
+            .map_err(|_| invalid_value!("len * size_of::<T>() fits in isize"))?
+            .size();
         let mut ptr = 0;
         if len != 0 {
-            driver!(cuMemAlloc_v2(&mut ptr, len))
+            try_driver!(cuMemAlloc_v2(&mut ptr, len))?
         }
-        DevMem(unsafe { self.wrap_raw(Blob { ptr, len }) }, PhantomData)
+        Ok(DevMem(
+            unsafe { self.wrap_raw(Blob { ptr, len }) },
+            PhantomData,
+        ))
     }
 
-    pub fn from_host<T: Copy>(&self, slice: &[T]) -> DevMem<'_> {
-        let mut dev = self.malloc::<T>(slice.len());
-        memcpy_h2d(&mut dev, slice);
-        dev
+    pub fn try_from_host<T: Copy>(&self, slice: &[T]) -> Result<DevMem<'_>, Error> {
+        let mut dev = self.try_malloc::<T>(slice.len())?;
+        try_memcpy_h2d(&mut dev, slice)?;
+        Ok(dev)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn malloc_pitch<T: Copy>(&self, width: usize, height: usize) -> (DevMem<'_>, usize) {
+        self.try_malloc_pitch::<T>(width, height).unwrap()
+    }
+
+    /// 分配 `height` 行、每行 `width` 个元素的二维存储，返回存储和驱动选择的行跨度（字节）。
+    pub fn try_malloc_pitch<T: Copy>(
+        &self,
+        width: usize,
+        height: usize,
+    ) -> Result<(DevMem<'_>, usize), Error> {
+        // 驱动只接受 4、8、16 字节的元素，元素大小只影响对齐
+        let elem = size_of::<T>().next_power_of_two().clamp(4, 16);
+        let row = width
+            .checked_mul(size_of::<T>())
+            .ok_or_else(|| invalid_value!("width * size_of::<T>() fits in usize"))?;
+        let mut ptr = 0;
+        let mut pitch = 0;
+        try_driver!(cuMemAllocPitch_v2(
+            &mut ptr, &mut pitch, row, height, elem as _
+        ))?;
+        let Some(len) = pitch.checked_mul(height) else {
+            try_driver!(cuMemFree_v2(ptr))?;
+            return Err(invalid_value!("pitch * height fits in usize"));
+        };
+        Ok((
+            DevMem(unsafe { self.wrap_raw(Blob { ptr, len }) }, PhantomData),
+            pitch,
+        ))
     }
 }
 
+#[cfg(nvidia)]
+use crate::MemPool;
+
 #[cfg(nvidia)]
 impl<'ctx> Stream<'ctx> {
+    #[inline]
+    #[track_caller]
     pub fn malloc<T: Copy>(&self, len: usize) -> DevMem<'ctx> {
-        let len = Layout::array::<T>(len).unwrap().size();
+        self.try_malloc::<T>(len).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn from_host<T: Copy>(&self, slice: &[T]) -> DevMem<'ctx> {
+        self.try_from_host(slice).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn free(&self, mem: DevMem) -> &Self {
+        self.try_free(mem).unwrap()
+    }
+
+    pub fn try_malloc<T: Copy>(&self, len: usize) -> Result<DevMem<'ctx>, Error> {
+        let len = Layout::array::<T>(len)
+            .map_err(|_| invalid_value!("len * size_of::<T>() fits in isize"))?
+            .size();
         let mut ptr = 0;
-        driver!(cuMemAllocAsync(&mut ptr, len, self.as_raw()));
-        DevMem(
+        try_driver!(cuMemAllocAsync(&mut ptr, len, self.as_raw()))?;
+        Ok(DevMem(
             unsafe { self.ctx().wrap_raw(Blob { ptr, len }) },
             PhantomData,
-        )
+        ))
     }
 
-    pub fn from_host<T: Copy>(&self, slice: &[T]) -> DevMem<'ctx> {
+    /// 从 `pool` 分配存储。
+    #[inline]
+    #[track_caller]
+    pub fn malloc_from<T: Copy>(&self, pool: &MemPool, len: usize) -> DevMem<'ctx> {
+        self.try_malloc_from::<T>(pool, len).unwrap()
+    }
+
+    pub fn try_malloc_from<T: Copy>(
+        &self,
+        pool: &MemPool,
+        len: usize,
+    ) -> Result<DevMem<'ctx>, Error> {
+        let len = Layout::array::<T>(len)
+            .map_err(|_| invalid_value!("len * size_of::<T>() fits in isize"))?
+            .size();
+        let mut ptr = 0;
+        try_driver!(cuMemAllocFromPoolAsync(
+            &mut ptr,
+            len,
+            pool.as_raw(),
+            self.as_raw()
+        ))?;
+        Ok(DevMem(
+            unsafe { self.ctx().wrap_raw(Blob { ptr, len }) },
+            PhantomData,
+        ))
+    }
+
+    pub fn try_from_host<T: Copy>(&self, slice: &[T]) -> Result<DevMem<'ctx>, Error> {
         let stream = unsafe { self.as_raw() };
         let len = size_of_val(slice);
         let src = slice.as_ptr().cast();
         let mut ptr = 0;
-        driver!(cuMemAllocAsync(&mut ptr, len, stream));
-        driver!(cuMemcpyHtoDAsync_v2(ptr, src, len, stream));
-        DevMem(
+        try_driver!(cuMemAllocAsync(&mut ptr, len, stream))?;
+        let mem = DevMem(
             unsafe { self.ctx().wrap_raw(Blob { ptr, len }) },
             PhantomData,
-        )
+        );
+        try_driver!(cuMemcpyHtoDAsync_v2(ptr, src, len, stream))?;
+        Ok(mem)
     }
 
-    #[cfg(nvidia)]
-    pub fn free(&self, mem: DevMem) -> &Self {
-        driver!(cuMemFreeAsync(mem.0.rss.ptr, self.as_raw()));
+    pub fn try_free(&self, mem: DevMem) -> Result<&Self, Error> {
+        try_driver!(cuMemFreeAsync(mem.0.rss.ptr, self.as_raw()))?;
         std::mem::forget(mem);
-        self
+        Ok(self)
     }
 }
 
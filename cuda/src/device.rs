@@ -1,5 +1,5 @@
 ﻿use crate::{
-    Dim3, MemSize, Version,
+    Dim3, Error, MemSize, Version,
     bindings::{
         CUdevice,
         CUdevice_attribute::{self, *},
@@ -21,10 +21,16 @@ impl AsRaw for Device {
 
 impl Device {
     #[inline]
+    #[track_caller]
     pub fn new(index: c_int) -> Self {
+        Self::try_new(index).unwrap()
+    }
+
+    #[inline]
+    pub fn try_new(index: c_int) -> Result<Self, Error> {
         let mut device = 0;
-        driver!(cuDeviceGet(&mut device, index));
-        Self(device)
+        try_driver!(cuDeviceGet(&mut device, index))?;
+        Ok(Self(device))
     }
 
     #[inline]
@@ -134,19 +140,14 @@ impl Device {
     }
 
     #[inline]
-    pub fn info(&self) -> InfoFmt {
+    pub fn info(&self) -> InfoFmt<'_> {
         InfoFmt(self)
     }
 
     #[cfg(nvidia)]
+    #[inline]
     pub fn set_mempool_threshold(&self, threshold: u64) {
-        let mut mempool = std::ptr::null_mut();
-        driver!(cuDeviceGetDefaultMemPool(&mut mempool, self.0));
-        driver!(cuMemPoolSetAttribute(
-            mempool,
-            CUmemPool_attribute::CU_MEMPOOL_ATTR_RELEASE_THRESHOLD,
-            (&raw const threshold) as _,
-        ));
+        self.default_mem_pool().set_release_threshold(threshold)
     }
 
     #[inline]
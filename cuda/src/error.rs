@@ -0,0 +1,145 @@
+use crate::bindings::{CUresult, cuGetErrorName, cuGetErrorString};
+use std::{
+    ffi::{CStr, c_char},
+    fmt,
+    ptr::null,
+};
+
+/// 驱动调用失败时的错误信息。
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Error {
+    code: CUresult,
+    name: &'static str,
+    desc: &'static str,
+    site: CallSite,
+}
+
+/// 驱动调用所在的位置。
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CallSite {
+    pub expr: &'static str,
+    pub file: &'static str,
+    pub line: u32,
+}
+
+impl Error {
+    /// 检查驱动调用的返回值，失败时收集错误信息。
+    pub fn check(code: CUresult, site: CallSite) -> Result<(), Self> {
+        if code == CUresult::CUDA_SUCCESS {
+            return Ok(());
+        }
+        fn text(ptr: *const c_char) -> &'static str {
+            if ptr.is_null() {
+                ""
+            } else {
+                unsafe { CStr::from_ptr(ptr) }.to_str().unwrap_or("")
+            }
+        }
+        let mut name = null();
+        let mut desc = null();
+        let _ = unsafe { cuGetErrorName(code, &mut name) };
+        let _ = unsafe { cuGetErrorString(code, &mut desc) };
+        Err(Self {
+            code,
+            name: text(name),
+            desc: text(desc),
+            site,
+        })
+    }
+
+    /// 调用驱动前发现参数非法，以 `CUDA_ERROR_INVALID_VALUE` 报告。
+    pub(crate) fn invalid_value(site: CallSite) -> Self {
+        Self::check(CUresult::CUDA_ERROR_INVALID_VALUE, site).unwrap_err()
+    }
+
+    #[inline]
+    pub const fn code(&self) -> CUresult {
+        self.code
+    }
+
+    /// 驱动给出的错误名字，来自 `cuGetErrorName`。
+    #[inline]
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// 驱动给出的错误描述，来自 `cuGetErrorString`。
+    #[inline]
+    pub const fn description(&self) -> &'static str {
+        self.desc
+    }
+
+    #[inline]
+    pub const fn site(&self) -> &CallSite {
+        &self.site
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            code,
+            name,
+            desc,
+            site,
+        } = self;
+        if name.is_empty() {
+            write!(f, "{code:?}")?
+        } else {
+            write!(f, "{name}")?
+        }
+        if !desc.is_empty() {
+            write!(f, " ({desc})")?
+        }
+        write!(f, " at {}:{}: {}", site.file, site.line, site.expr)
+    }
+}
+
+/// 与 [`Display`](fmt::Display) 相同，使 `unwrap` 报告的错误和 [`driver!`] 一致。
+impl fmt::Debug for Error {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[test]
+fn test_check() {
+    use crate::bindings::CUresult::*;
+
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    let site = CallSite {
+        expr: "cuMemAlloc_v2(&mut ptr, len)",
+        file: file!(),
+        line: line!(),
+    };
+    assert_eq!(Error::check(CUDA_SUCCESS, site), Ok(()));
+
+    let e = Error::check(CUDA_ERROR_OUT_OF_MEMORY, site).unwrap_err();
+    assert_eq!(e.code(), CUDA_ERROR_OUT_OF_MEMORY);
+    assert_eq!(e.name(), "CUDA_ERROR_OUT_OF_MEMORY");
+    assert_eq!(e.site(), &site);
+    println!("{e}")
+}
+
+#[test]
+fn test_oom() {
+    use crate::bindings::CUresult::*;
+
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    crate::Device::new(0).context().apply(|ctx| {
+        let (_, total) = ctx.mem_info();
+        // 申请超出总容量的空间，返回错误而不是崩溃
+        let e = ctx.try_malloc::<u8>(total.0 * 2).err().unwrap();
+        assert_eq!(e.code(), CUDA_ERROR_OUT_OF_MEMORY);
+        println!("{e}");
+        // 上下文仍然可用
+        let _mem = ctx.malloc::<u8>(1 << 20);
+    })
+}
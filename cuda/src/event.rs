@@ -1,18 +1,97 @@
-use crate::{CurrentCtx, Stream, bindings::CUevent};
+use crate::{
+    CurrentCtx, Error, Stream,
+    bindings::{CUevent, CUevent_flags, CUipcEventHandle},
+};
 use context_spore::{AsRaw, impl_spore};
-use std::{marker::PhantomData, ptr::null_mut, time::Duration};
+use std::{ffi::c_uint, marker::PhantomData, mem::MaybeUninit, ptr::null_mut, time::Duration};
 
 impl_spore!(Event and EventSpore by (CurrentCtx, CUevent));
 
+impl CurrentCtx {
+    #[inline]
+    pub fn event(&self) -> Event<'_> {
+        self.event_builder().build()
+    }
+
+    #[inline]
+    pub fn event_builder(&self) -> EventBuilder<'_> {
+        EventBuilder {
+            ctx: self,
+            flags: CUevent_flags::CU_EVENT_DEFAULT as _,
+        }
+    }
+}
+
+/// 按指定的标志创建事件。
+pub struct EventBuilder<'ctx> {
+    ctx: &'ctx CurrentCtx,
+    flags: c_uint,
+}
+
+impl<'ctx> EventBuilder<'ctx> {
+    fn set(mut self, flag: CUevent_flags, value: bool) -> Self {
+        if value {
+            self.flags |= flag as c_uint
+        } else {
+            self.flags &= !(flag as c_uint)
+        }
+        self
+    }
+
+    /// 不记录时刻，不能用于计时，开销更小。
+    #[inline]
+    pub fn disable_timing(self, value: bool) -> Self {
+        self.set(CUevent_flags::CU_EVENT_DISABLE_TIMING, value)
+    }
+
+    /// 同步事件的线程阻塞等待而不是轮询。
+    #[inline]
+    pub fn blocking_sync(self, value: bool) -> Self {
+        self.set(CUevent_flags::CU_EVENT_BLOCKING_SYNC, value)
+    }
+
+    /// 事件可以导出到其他进程，必须同时禁用计时。
+    #[inline]
+    pub fn interprocess(self, value: bool) -> Self {
+        self.set(CUevent_flags::CU_EVENT_INTERPROCESS, value)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn build(self) -> Event<'ctx> {
+        self.try_build().unwrap()
+    }
+
+    pub fn try_build(self) -> Result<Event<'ctx>, Error> {
+        let mut event = null_mut();
+        try_driver!(cuEventCreate(&mut event, self.flags))?;
+        Ok(Event(unsafe { self.ctx.wrap_raw(event) }, PhantomData))
+    }
+}
+
 impl<'ctx> Stream<'ctx> {
+    #[inline]
+    #[track_caller]
     pub fn record(&self) -> Event<'ctx> {
-        let mut event = null_mut();
-        driver!(cuEventCreate(
-            &mut event,
-            CUstream_flags::CU_STREAM_DEFAULT as _
-        ));
-        driver!(cuEventRecord(event, self.as_raw()));
-        Event(unsafe { self.ctx().wrap_raw(event) }, PhantomData)
+        self.try_record().unwrap()
+    }
+
+    pub fn try_record(&self) -> Result<Event<'ctx>, Error> {
+        let event = self.ctx().event_builder().try_build()?;
+        self.try_record_event(&event)?;
+        Ok(event)
+    }
+
+    /// 在流上重新记录已有的事件。
+    #[inline]
+    #[track_caller]
+    pub fn record_event(&self, event: &Event) -> &Self {
+        self.try_record_event(event).unwrap()
+    }
+
+    pub fn try_record_event(&self, event: &Event) -> Result<&Self, Error> {
+        try_driver!(cuEventRecord(event.0.rss, self.as_raw()))?;
+        Ok(self)
     }
 }
 
@@ -33,8 +112,14 @@ impl AsRaw for Event<'_> {
 
 impl Stream<'_> {
     #[inline]
+    #[track_caller]
     pub fn wait_for(&self, event: &Event) {
-        driver!(cuStreamWaitEvent(self.as_raw(), event.0.rss, 0));
+        self.try_wait_for(event).unwrap()
+    }
+
+    #[inline]
+    pub fn try_wait_for(&self, event: &Event) -> Result<(), Error> {
+        try_driver!(cuStreamWaitEvent(self.as_raw(), event.0.rss, 0))
     }
 
     pub fn bench(&self, mut f: impl FnMut(usize, &Self), times: usize, warm_up: usize) -> Duration {
@@ -53,26 +138,121 @@ impl Stream<'_> {
     }
 }
 
+/// 跨进程共享事件的句柄。
+#[derive(Clone, Copy)]
+#[repr(transparent)]
+pub struct IpcEventHandle(CUipcEventHandle);
+
+impl IpcEventHandle {
+    pub const SIZE: usize = size_of::<CUipcEventHandle>();
+
+    #[inline]
+    pub fn from_bytes(bytes: [u8; Self::SIZE]) -> Self {
+        Self(unsafe { std::mem::transmute::<[u8; Self::SIZE], CUipcEventHandle>(bytes) })
+    }
+
+    #[inline]
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        unsafe { std::mem::transmute::<CUipcEventHandle, [u8; Self::SIZE]>(self.0) }
+    }
+}
+
+impl CurrentCtx {
+    /// 打开其他进程导出的事件。
+    #[inline]
+    #[track_caller]
+    pub fn open_ipc_event(&self, handle: &IpcEventHandle) -> Event<'_> {
+        self.try_open_ipc_event(handle).unwrap()
+    }
+
+    pub fn try_open_ipc_event(&self, handle: &IpcEventHandle) -> Result<Event<'_>, Error> {
+        let mut event = null_mut();
+        try_driver!(cuIpcOpenEventHandle(&mut event, handle.0))?;
+        Ok(Event(unsafe { self.wrap_raw(event) }, PhantomData))
+    }
+}
+
 impl Event<'_> {
+    /// 导出事件，事件必须以 [`EventBuilder::interprocess`] 创建。
     #[inline]
+    #[track_caller]
+    pub fn ipc_handle(&self) -> IpcEventHandle {
+        self.try_ipc_handle().unwrap()
+    }
+
+    pub fn try_ipc_handle(&self) -> Result<IpcEventHandle, Error> {
+        let mut handle = MaybeUninit::uninit();
+        try_driver!(cuIpcGetEventHandle(handle.as_mut_ptr(), self.0.rss))?;
+        Ok(IpcEventHandle(unsafe { handle.assume_init() }))
+    }
+
+    #[inline]
+    #[track_caller]
     pub fn is_complete(&self) -> bool {
-        use crate::bindings::{cuEventQuery, cudaError_enum as E};
-        match unsafe { cuEventQuery(self.0.rss) } {
-            E::CUDA_SUCCESS => true,
-            E::CUDA_ERROR_NOT_READY => false,
-            err => panic!("Unexpected error: {err:?}"),
+        self.try_is_complete().unwrap()
+    }
+
+    /// 查询事件是否完成，`CUDA_ERROR_NOT_READY` 视作未完成而不是错误。
+    pub fn try_is_complete(&self) -> Result<bool, Error> {
+        use crate::bindings::cudaError_enum as E;
+        match try_driver!(cuEventQuery(self.0.rss)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.code() == E::CUDA_ERROR_NOT_READY => Ok(false),
+            Err(e) => Err(e),
         }
     }
 
     #[inline]
+    #[track_caller]
     pub fn synchronize(&self) {
-        driver!(cuEventSynchronize(self.0.rss))
+        self.try_synchronize().unwrap()
+    }
+
+    #[inline]
+    pub fn try_synchronize(&self) -> Result<(), Error> {
+        try_driver!(cuEventSynchronize(self.0.rss))
     }
 
     #[inline]
+    #[track_caller]
     pub fn elapse_from(&self, start: &Self) -> Duration {
+        self.try_elapse_from(start).unwrap()
+    }
+
+    pub fn try_elapse_from(&self, start: &Self) -> Result<Duration, Error> {
         let mut ms = 0.;
-        driver!(cuEventElapsedTime(&mut ms, start.0.rss, self.0.rss));
-        Duration::from_secs_f32(ms * 1e-3)
+        try_driver!(cuEventElapsedTime(&mut ms, start.0.rss, self.0.rss))?;
+        Ok(Duration::from_secs_f32(ms * 1e-3))
     }
 }
+
+#[test]
+fn test_behavior() {
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    crate::Device::new(0).context().apply(|ctx| {
+        let stream = ctx.stream();
+        let start = stream.record();
+        let end = ctx.event();
+        stream.record_event(&end);
+        end.synchronize();
+        assert!(end.is_complete());
+        assert!(end.try_elapse_from(&start).is_ok());
+
+        // 禁用计时的事件只能用于同步
+        let event = ctx.event_builder().disable_timing(true).build();
+        ctx.stream().record_event(&event).wait_for(&event);
+        event.synchronize();
+        assert!(event.try_elapse_from(&start).is_err());
+
+        let event = ctx
+            .event_builder()
+            .disable_timing(true)
+            .interprocess(true)
+            .build();
+        let handle = event.ipc_handle();
+        let bytes = handle.to_bytes();
+        assert_eq!(IpcEventHandle::from_bytes(bytes).to_bytes(), bytes);
+    })
+}
@@ -0,0 +1,306 @@
+﻿use super::{ConditionalNode, Graph, GraphNode, collect_dependencies};
+use crate::{
+    CurrentCtx, Error,
+    bindings::{
+        CU_GRAPH_COND_ASSIGN_DEFAULT, CUgraphConditionalHandle, CUgraphConditionalNodeType,
+        CUgraphNodeParams, CUgraphNodeType,
+    },
+};
+use context_spore::AsRaw;
+use std::{marker::PhantomData, ptr::null_mut, slice::from_raw_parts};
+
+/// 条件节点的条件值句柄。
+///
+/// 句柄可以直接作为 kernel 参数，设备端参数类型为 `cudaGraphConditionalHandle`，
+/// 在 kernel 中调用 `cudaGraphSetConditional` 修改条件值，例如每执行一步递减计数，计数归零时结束循环：
+///
+/// ```cuda
+/// extern "C" __global__ void step(cudaGraphConditionalHandle handle, unsigned int *remain) {
+///     // ...
+///     if (threadIdx.x == 0) cudaGraphSetConditional(handle, --*remain != 0);
+/// }
+/// ```
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ConditionalHandle(CUgraphConditionalHandle);
+
+impl AsRaw for ConditionalHandle {
+    type Raw = CUgraphConditionalHandle;
+    #[inline]
+    unsafe fn as_raw(&self) -> Self::Raw {
+        self.0
+    }
+}
+
+/// 条件节点的类型，决定子图的数量和执行方式。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConditionalKind {
+    /// 条件值非零时执行子图。
+    If,
+    /// 条件值非零时执行第 0 个子图，否则执行第 1 个子图。需要 CUDA 12.8。
+    #[cfg(cuda_12_8)]
+    IfElse,
+    /// 条件值非零时重复执行子图，每次执行前检查条件值。
+    While,
+    /// 执行序号等于条件值的子图，条件值越界时不执行。需要 CUDA 12.8。
+    #[cfg(cuda_12_8)]
+    Switch(usize),
+}
+
+impl Graph {
+    #[inline]
+    #[track_caller]
+    pub fn conditional_handle(&self, ctx: &CurrentCtx, default: Option<u32>) -> ConditionalHandle {
+        self.try_conditional_handle(ctx, default).unwrap()
+    }
+
+    /// 在图上创建条件值句柄。
+    ///
+    /// `default` 不为空时每次启动图都将条件值设为 `default`，否则条件值需要在图中的 kernel 里设置。
+    pub fn try_conditional_handle(
+        &self,
+        ctx: &CurrentCtx,
+        default: Option<u32>,
+    ) -> Result<ConditionalHandle, Error> {
+        let (value, flags) = match default {
+            Some(value) => (value, CU_GRAPH_COND_ASSIGN_DEFAULT),
+            None => (0, 0),
+        };
+        let mut handle = 0;
+        try_driver!(cuGraphConditionalHandleCreate(
+            &mut handle,
+            self.as_raw(),
+            ctx.as_raw(),
+            value,
+            flags
+        ))?;
+        Ok(ConditionalHandle(handle))
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn add_conditional<'a>(
+        &self,
+        ctx: &CurrentCtx,
+        handle: ConditionalHandle,
+        kind: ConditionalKind,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> (ConditionalNode<'_>, &[Graph]) {
+        self.try_add_conditional(ctx, handle, kind, deps).unwrap()
+    }
+
+    /// 添加由 `handle` 控制的条件节点，返回节点和驱动创建的子图，向子图中添加节点构成条件执行的部分。
+    ///
+    /// `handle` 必须在这个图上创建。子图随节点释放，只能包含设备上的操作。
+    pub fn try_add_conditional<'a>(
+        &self,
+        ctx: &CurrentCtx,
+        handle: ConditionalHandle,
+        kind: ConditionalKind,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<(ConditionalNode<'_>, &[Graph]), Error> {
+        use CUgraphConditionalNodeType::*;
+        let (type_, size) = match kind {
+            ConditionalKind::If => (CU_GRAPH_COND_TYPE_IF, 1),
+            #[cfg(cuda_12_8)]
+            ConditionalKind::IfElse => (CU_GRAPH_COND_TYPE_IF, 2),
+            ConditionalKind::While => (CU_GRAPH_COND_TYPE_WHILE, 1),
+            #[cfg(cuda_12_8)]
+            ConditionalKind::Switch(n) => (
+                CU_GRAPH_COND_TYPE_SWITCH,
+                u32::try_from(n).map_err(|_| invalid_value!("switch size fits in u32"))?,
+            ),
+        };
+        let deps = collect_dependencies(deps);
+
+        let mut params: CUgraphNodeParams = unsafe { std::mem::zeroed() };
+        params.type_ = CUgraphNodeType::CU_GRAPH_NODE_TYPE_CONDITIONAL;
+        let cond = unsafe { &mut params.__bindgen_anon_1.conditional };
+        cond.handle = handle.0;
+        cond.type_ = type_;
+        cond.size = size;
+        cond.ctx = unsafe { ctx.as_raw() };
+
+        let mut node = null_mut();
+        try_driver!(cuGraphAddNode(
+            &mut node,
+            self.as_raw(),
+            deps.as_ptr(),
+            deps.len(),
+            &mut params
+        ))?;
+        // 子图数组由驱动持有，与节点的生命周期相同；`Graph` 是句柄的透明包装，借用时不会销毁子图
+        let bodies = unsafe {
+            let graphs = params.__bindgen_anon_1.conditional.phGraph_out;
+            from_raw_parts(graphs.cast::<Graph>(), size as _)
+        };
+        Ok((ConditionalNode(node, PhantomData), bodies))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ConditionalKind;
+    use crate::{Device, Graph, GraphNode, memcpy_d2h};
+
+    #[test]
+    fn test_if() {
+        if let Err(crate::NoDevice) = crate::init() {
+            return;
+        }
+        Device::new(0).context().apply(|ctx| {
+            let mut mem = ctx.malloc::<u32>(4);
+            let graph = Graph::new();
+            let init = graph.add_memset(&mut mem, 0u32, &[]);
+
+            let handle = graph.conditional_handle(ctx, Some(1));
+            let (node, [then]) =
+                graph.add_conditional(ctx, handle, ConditionalKind::If, &[init.into()])
+            else {
+                panic!()
+            };
+            then.add_memset(&mut mem[..8], 1u32, &[]);
+            // 条件值为零时不执行子图
+            let handle = graph.conditional_handle(ctx, Some(0));
+            let (_, [then]) =
+                graph.add_conditional(ctx, handle, ConditionalKind::If, &[node.into()])
+            else {
+                panic!()
+            };
+            then.add_memset(&mut mem[8..], 2u32, &[]);
+            assert!(matches!(
+                &*graph.nodes(),
+                [_, GraphNode::Conditional(_), GraphNode::Conditional(_)]
+            ));
+
+            ctx.stream()
+                .launch_graph(&ctx.instantiate(&graph))
+                .synchronize();
+            let mut host = [0u32; 4];
+            memcpy_d2h(&mut host, &mem);
+            assert_eq!(host, [1, 1, 0, 0])
+        })
+    }
+
+    #[cfg(cuda_12_8)]
+    #[test]
+    fn test_if_else() {
+        if let Err(crate::NoDevice) = crate::init() {
+            return;
+        }
+        Device::new(0).context().apply(|ctx| {
+            let mut mem = ctx.malloc::<u32>(4);
+            let graph = Graph::new();
+            let init = graph.add_memset(&mut mem, 0u32, &[]);
+
+            let handle = graph.conditional_handle(ctx, Some(1));
+            let (node, [then]) =
+                graph.add_conditional(ctx, handle, ConditionalKind::If, &[init.into()])
+            else {
+                panic!()
+            };
+            then.add_memset(&mut mem[..8], 1u32, &[]);
+            // 条件值为零的分支选择 else 子图
+            let handle = graph.conditional_handle(ctx, Some(0));
+            let (_, [_, else_]) =
+                graph.add_conditional(ctx, handle, ConditionalKind::IfElse, &[node.into()])
+            else {
+                panic!()
+            };
+            else_.add_memset(&mut mem[8..], 2u32, &[]);
+            assert!(matches!(&*graph.nodes(), [_, GraphNode::Conditional(_), _]));
+
+            ctx.stream()
+                .launch_graph(&ctx.instantiate(&graph))
+                .synchronize();
+            let mut host = [0u32; 4];
+            memcpy_d2h(&mut host, &mem);
+            assert_eq!(host, [1, 1, 2, 2])
+        })
+    }
+
+    #[test]
+    fn test_while() {
+        if let Err(crate::NoDevice) = crate::init() {
+            return;
+        }
+        Device::new(0).context().apply(|ctx| {
+            let mut mem = ctx.malloc::<u32>(1);
+            let graph = Graph::new();
+            let init = graph.add_memset(&mut mem, 0u32, &[]);
+
+            // 条件值为零的循环不执行
+            let handle = graph.conditional_handle(ctx, Some(0));
+            let (_, [body]) =
+                graph.add_conditional(ctx, handle, ConditionalKind::While, &[init.into()])
+            else {
+                panic!()
+            };
+            body.add_memset(&mut mem, 1u32, &[]);
+
+            ctx.stream()
+                .launch_graph(&ctx.instantiate(&graph))
+                .synchronize();
+            let mut host = [1u32];
+            memcpy_d2h(&mut host, &mem);
+            assert_eq!(host, [0])
+        })
+    }
+
+    #[cfg(cuda_12_8)]
+    #[test]
+    fn test_switch() {
+        if let Err(crate::NoDevice) = crate::init() {
+            return;
+        }
+        Device::new(0).context().apply(|ctx| {
+            let mut mem = ctx.malloc::<u32>(3);
+            let graph = Graph::new();
+            let node = graph.add_memset(&mut mem, 0u32, &[]);
+
+            let handle = graph.conditional_handle(ctx, Some(2));
+            let (_, bodies) =
+                graph.add_conditional(ctx, handle, ConditionalKind::Switch(3), &[node.into()]);
+            for (i, body) in bodies.iter().enumerate() {
+                body.add_memset(&mut mem[4..], i as u32 + 1, &[]);
+            }
+
+            ctx.stream()
+                .launch_graph(&ctx.instantiate(&graph))
+                .synchronize();
+            let mut host = [0u32; 3];
+            memcpy_d2h(&mut host, &mem);
+            assert_eq!(host, [0, 3, 3])
+        })
+    }
+
+    #[test]
+    fn test_invalid() {
+        if let Err(crate::NoDevice) = crate::init() {
+            return;
+        }
+        Device::new(0).context().apply(|ctx| {
+            let graph = Graph::new();
+            // 句柄必须在同一个图上创建
+            let other = Graph::new().conditional_handle(ctx, None);
+            assert!(
+                graph
+                    .try_add_conditional(ctx, other, ConditionalKind::If, &[])
+                    .is_err()
+            );
+            // 分支的数量不能为零，也不能超出 u32
+            #[cfg(cuda_12_8)]
+            {
+                let handle = graph.conditional_handle(ctx, None);
+                for n in [0, usize::MAX] {
+                    assert!(
+                        graph
+                            .try_add_conditional(ctx, handle, ConditionalKind::Switch(n), &[])
+                            .is_err()
+                    )
+                }
+            }
+            assert!(graph.nodes().is_empty())
+        })
+    }
+}
@@ -0,0 +1,361 @@
+﻿use super::{
+    Graph, GraphExec, GraphNode, HostFnNode, KernelNode, MemcpyNode, MemsetNode,
+    kernel::kernel_params,
+};
+use crate::{
+    Dim3, Error, KernelFn,
+    bindings::{
+        CUDA_MEMCPY3D, CUDA_MEMSET_NODE_PARAMS, CUgraphExecUpdateResult,
+        CUgraphExecUpdateResultInfo, CUgraphNode, CUhostFn, CUresult,
+    },
+};
+use context_spore::AsRaw;
+use std::{ffi::c_void, fmt, ptr::null_mut};
+
+/// 驱动拒绝更新执行图的原因。
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum UpdateFailure {
+    /// 拓扑结构改变。
+    TopologyChanged,
+    /// 节点类型改变。
+    NodeTypeChanged,
+    /// kernel 节点的函数改变。
+    FunctionChanged,
+    /// 节点参数的改变不能更新，例如拷贝的存储类型改变。
+    ParametersChanged,
+    /// 节点不支持更新，例如图分配和释放节点。
+    NotSupported,
+    /// kernel 节点的函数改变到不支持的函数。
+    UnsupportedFunctionChange,
+    /// 节点属性改变。
+    AttributesChanged,
+    /// 其他原因。
+    Other,
+}
+
+impl From<CUgraphExecUpdateResult> for UpdateFailure {
+    fn from(value: CUgraphExecUpdateResult) -> Self {
+        use CUgraphExecUpdateResult::*;
+        match value {
+            CU_GRAPH_EXEC_UPDATE_ERROR_TOPOLOGY_CHANGED => Self::TopologyChanged,
+            CU_GRAPH_EXEC_UPDATE_ERROR_NODE_TYPE_CHANGED => Self::NodeTypeChanged,
+            CU_GRAPH_EXEC_UPDATE_ERROR_FUNCTION_CHANGED => Self::FunctionChanged,
+            CU_GRAPH_EXEC_UPDATE_ERROR_PARAMETERS_CHANGED => Self::ParametersChanged,
+            CU_GRAPH_EXEC_UPDATE_ERROR_NOT_SUPPORTED => Self::NotSupported,
+            CU_GRAPH_EXEC_UPDATE_ERROR_UNSUPPORTED_FUNCTION_CHANGE => {
+                Self::UnsupportedFunctionChange
+            }
+            CU_GRAPH_EXEC_UPDATE_ERROR_ATTRIBUTES_CHANGED => Self::AttributesChanged,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// 更新执行图失败。
+#[derive(Debug)]
+pub enum UpdateError<'g> {
+    /// 驱动拒绝更新，`node` 是新图中导致失败的节点。
+    Rejected {
+        reason: UpdateFailure,
+        node: Option<GraphNode<'g>>,
+    },
+    /// 驱动调用失败。
+    Driver(Error),
+}
+
+impl fmt::Display for UpdateError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rejected { reason, node } => {
+                write!(f, "graph exec update rejected: {reason:?}")?;
+                if let Some(node) = node {
+                    write!(f, " at node {:?}", unsafe { node.as_raw() })?
+                }
+                Ok(())
+            }
+            Self::Driver(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for UpdateError<'_> {}
+
+impl GraphExec<'_> {
+    #[inline]
+    #[track_caller]
+    pub fn update(&mut self, graph: &Graph) {
+        self.try_update(graph).unwrap()
+    }
+
+    /// 用拓扑结构相同的 `graph` 中的参数更新执行图，不需要重新实例化。
+    ///
+    /// 失败时执行图不变。更新后仍以实例化时的节点设置参数。
+    pub fn try_update<'g>(&mut self, graph: &'g Graph) -> Result<(), UpdateError<'g>> {
+        let mut info = CUgraphExecUpdateResultInfo {
+            result: CUgraphExecUpdateResult::CU_GRAPH_EXEC_UPDATE_SUCCESS,
+            errorNode: null_mut(),
+            errorFromNode: null_mut(),
+        };
+        match try_driver!(cuGraphExecUpdate_v2(
+            self.as_raw(),
+            graph.as_raw(),
+            &mut info
+        )) {
+            Ok(()) => Ok(()),
+            Err(e) if e.code() == CUresult::CUDA_ERROR_GRAPH_EXEC_UPDATE_FAILURE => {
+                Err(UpdateError::Rejected {
+                    reason: info.result.into(),
+                    node: (!info.errorNode.is_null())
+                        .then(|| GraphNode::try_new(info.errorNode).ok())
+                        .flatten(),
+                })
+            }
+            Err(e) => Err(UpdateError::Driver(e)),
+        }
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn set_kernel_params(
+        &mut self,
+        node: &KernelNode,
+        f: &KernelFn,
+        attrs: (impl Into<Dim3>, impl Into<Dim3>, usize),
+        params: &[*const c_void],
+    ) {
+        self.try_set_kernel_params(node, f, attrs, params).unwrap()
+    }
+
+    /// 修改执行图中 kernel 节点的参数，参数含义同 [`Graph::add_kernel_call`]。
+    pub fn try_set_kernel_params(
+        &mut self,
+        node: &KernelNode,
+        f: &KernelFn,
+        attrs: (impl Into<Dim3>, impl Into<Dim3>, usize),
+        params: &[*const c_void],
+    ) -> Result<(), Error> {
+        let params = kernel_params(f, attrs, params);
+        try_driver!(cuGraphExecKernelNodeSetParams_v2(
+            self.as_raw(),
+            node.as_raw(),
+            &params
+        ))
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn set_memcpy_params(&mut self, node: &MemcpyNode, params: &CUDA_MEMCPY3D) {
+        self.try_set_memcpy_params(node, params).unwrap()
+    }
+
+    /// 修改执行图中拷贝节点的参数，不能改变存储的类型。
+    pub fn try_set_memcpy_params(
+        &mut self,
+        node: &MemcpyNode,
+        params: &CUDA_MEMCPY3D,
+    ) -> Result<(), Error> {
+        try_driver!(cuGraphExecMemcpyNodeSetParams(
+            self.as_raw(),
+            node.as_raw(),
+            params,
+            null_mut()
+        ))
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn set_memset_params(&mut self, node: &MemsetNode, params: &CUDA_MEMSET_NODE_PARAMS) {
+        self.try_set_memset_params(node, params).unwrap()
+    }
+
+    pub fn try_set_memset_params(
+        &mut self,
+        node: &MemsetNode,
+        params: &CUDA_MEMSET_NODE_PARAMS,
+    ) -> Result<(), Error> {
+        try_driver!(cuGraphExecMemsetNodeSetParams(
+            self.as_raw(),
+            node.as_raw(),
+            params,
+            null_mut()
+        ))
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn set_host_params(
+        &mut self,
+        node: &HostFnNode,
+        host_fn: CUhostFn,
+        user_data: *mut c_void,
+    ) {
+        self.try_set_host_params(node, host_fn, user_data).unwrap()
+    }
+
+    pub fn try_set_host_params(
+        &mut self,
+        node: &HostFnNode,
+        host_fn: CUhostFn,
+        user_data: *mut c_void,
+    ) -> Result<(), Error> {
+        let params = crate::bindings::CUDA_HOST_NODE_PARAMS {
+            fn_: host_fn,
+            userData: user_data,
+        };
+        try_driver!(cuGraphExecHostNodeSetParams(
+            self.as_raw(),
+            node.as_raw(),
+            &params
+        ))
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn set_node_enabled(&mut self, node: &impl AsRaw<Raw = CUgraphNode>, enabled: bool) {
+        self.try_set_node_enabled(node, enabled).unwrap()
+    }
+
+    /// 启用或禁用节点，禁用的节点执行时什么也不做。只有 kernel、拷贝和填充节点可以禁用。
+    pub fn try_set_node_enabled(
+        &mut self,
+        node: &impl AsRaw<Raw = CUgraphNode>,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        try_driver!(cuGraphNodeSetEnabled(
+            self.as_raw(),
+            node.as_raw(),
+            enabled as _
+        ))
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn node_enabled(&self, node: &impl AsRaw<Raw = CUgraphNode>) -> bool {
+        self.try_node_enabled(node).unwrap()
+    }
+
+    pub fn try_node_enabled(&self, node: &impl AsRaw<Raw = CUgraphNode>) -> Result<bool, Error> {
+        let mut enabled = 0;
+        try_driver!(cuGraphNodeGetEnabled(
+            self.as_raw(),
+            node.as_raw(),
+            &mut enabled
+        ))?;
+        Ok(enabled != 0)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{UpdateError, UpdateFailure};
+    use crate::{
+        AsRaw, Device, Graph, GraphNode, bindings::CUDA_MEMSET_NODE_PARAMS, memcpy_d2h, memcpy_h2d,
+    };
+    use std::{
+        ffi::c_void,
+        sync::atomic::{AtomicUsize, Ordering::SeqCst},
+    };
+
+    #[test]
+    fn test_update() {
+        if let Err(crate::NoDevice) = crate::init() {
+            return;
+        }
+        Device::new(0).context().apply(|ctx| {
+            let host = (0..256u32).collect::<Vec<_>>();
+            let mut a = ctx.malloc::<u32>(host.len());
+            let mut b = ctx.malloc::<u32>(host.len());
+            let mut ans = vec![0u32; host.len()];
+
+            let graph = Graph::new();
+            let h2d = graph.add_memcpy_h2d(&mut a, &*host, &[]);
+            graph.add_memset(&mut b[..16], 1u8, &[h2d.into()]);
+            let mut exec = ctx.instantiate(&graph);
+
+            // 拓扑相同的图，拷贝到另一块存储
+            let graph_ = Graph::new();
+            let h2d = graph_.add_memcpy_h2d(&mut b, &*host, &[]);
+            graph_.add_memset(&mut a[..16], 1u8, &[h2d.into()]);
+            exec.update(&graph_);
+            let stream = ctx.stream();
+            stream.launch_graph(&exec).synchronize();
+            memcpy_d2h(&mut ans, &b);
+            assert_eq!(ans, host);
+
+            // 拓扑改变
+            let graph_ = Graph::new();
+            graph_.add_memcpy_h2d(&mut b, &*host, &[]);
+            assert!(matches!(
+                exec.try_update(&graph_),
+                Err(UpdateError::Rejected {
+                    reason: UpdateFailure::TopologyChanged,
+                    ..
+                })
+            ));
+
+            // 拷贝的存储类型改变，报告导致失败的节点
+            let graph_ = Graph::new();
+            let d2d = graph_.add_memcpy_d2d(&mut b, &a, &[]);
+            let raw = unsafe { d2d.as_raw() };
+            graph_.add_memset(&mut a[..16], 1u8, &[d2d.into()]);
+            match exec.try_update(&graph_) {
+                Err(UpdateError::Rejected {
+                    reason: UpdateFailure::ParametersChanged,
+                    node: Some(node @ GraphNode::Memcpy(_)),
+                }) => assert_eq!(unsafe { node.as_raw() }, raw),
+                _ => panic!(),
+            }
+        })
+    }
+
+    #[test]
+    fn test_set_params() {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        extern "C" fn add(n: *mut c_void) {
+            COUNT.fetch_add(n as usize, SeqCst);
+        }
+
+        if let Err(crate::NoDevice) = crate::init() {
+            return;
+        }
+        Device::new(0).context().apply(|ctx| {
+            let mut mem = ctx.from_host(&[0u8; 64]);
+            let graph = Graph::new();
+            let memset = graph.add_memset(&mut mem, 1u8, &[]);
+            let host = graph.add_host_node(Some(add), 1 as _, &[]);
+            let mut exec = ctx.instantiate(&graph);
+            let stream = ctx.stream();
+
+            // 只修改执行图，图中的节点不变
+            exec.set_memset_params(
+                &memset,
+                &CUDA_MEMSET_NODE_PARAMS {
+                    dst: mem.as_ptr() as _,
+                    pitch: 0,
+                    value: 2,
+                    elementSize: 1,
+                    width: 32,
+                    height: 1,
+                },
+            );
+            exec.set_host_params(&host, Some(add), 10 as _);
+            stream.launch_graph(&exec).synchronize();
+            let mut ans = [0u8; 64];
+            memcpy_d2h(&mut ans, &mem);
+            assert_eq!(ans[..32], [2; 32]);
+            assert_eq!(ans[32..], [0; 32]);
+            assert_eq!(COUNT.load(SeqCst), 10);
+
+            // 禁用的节点不执行
+            assert!(exec.node_enabled(&memset));
+            exec.set_node_enabled(&memset, false);
+            assert!(!exec.node_enabled(&memset));
+            memcpy_h2d(&mut mem, &[0u8; 64]);
+            stream.launch_graph(&exec).synchronize();
+            memcpy_d2h(&mut ans, &mem);
+            assert_eq!(ans, [0; 64]);
+            // 主机节点不能禁用
+            assert!(exec.try_set_node_enabled(&host, false).is_err())
+        })
+    }
+}
@@ -1,34 +1,54 @@
 ﻿use super::{Graph, GraphNode, MemFreeNode, collect_dependencies};
-use crate::VirByte;
+use crate::{Error, VirByte};
 use context_spore::AsRaw;
 use std::{marker::PhantomData, ptr::null_mut};
 
 impl Graph {
+    #[inline]
+    #[track_caller]
     pub fn free<'a>(
         &self,
         ptr: *const VirByte,
         deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
-    ) -> MemFreeNode {
+    ) -> MemFreeNode<'_> {
+        self.try_free(ptr, deps).unwrap()
+    }
+
+    pub fn try_free<'a>(
+        &self,
+        ptr: *const VirByte,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<MemFreeNode<'_>, Error> {
         let deps = collect_dependencies(deps);
 
         let mut node = null_mut();
-        driver!(cuGraphAddMemFreeNode(
+        try_driver!(cuGraphAddMemFreeNode(
             &mut node,
             self.as_raw(),
             deps.as_ptr(),
             deps.len(),
             ptr as _,
-        ));
-        MemFreeNode(node, PhantomData)
+        ))?;
+        Ok(MemFreeNode(node, PhantomData))
     }
 
+    #[inline]
+    #[track_caller]
     pub fn add_free_node<'a>(
         &self,
         node: &MemFreeNode,
         deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
-    ) -> MemFreeNode {
+    ) -> MemFreeNode<'_> {
+        self.try_add_free_node(node, deps).unwrap()
+    }
+
+    pub fn try_add_free_node<'a>(
+        &self,
+        node: &MemFreeNode,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<MemFreeNode<'_>, Error> {
         let mut ptr = 0;
-        driver!(cuGraphMemFreeNodeGetParams(node.as_raw(), &mut ptr));
-        self.free(ptr as _, deps)
+        try_driver!(cuGraphMemFreeNodeGetParams(node.as_raw(), &mut ptr))?;
+        self.try_free(ptr as _, deps)
     }
 }
@@ -1,30 +1,137 @@
 ﻿use super::{Graph, GraphNode, HostFnNode, collect_dependencies};
-use crate::bindings::{CUDA_HOST_NODE_PARAMS, CUhostFn};
+use crate::{
+    Error, Stream,
+    bindings::{CUDA_HOST_NODE_PARAMS, CUgraph, CUhostFn, CUstreamCaptureStatus},
+};
 use context_spore::AsRaw;
-use std::{ffi::c_void, marker::PhantomData, ptr::null_mut};
+use std::{ffi::c_void, marker::PhantomData, ptr::null_mut, sync::Mutex};
+
+/// 将 `data` 的所有权转移给图。
+///
+/// 图和从图实例化的执行图都销毁后，驱动释放 `data`。
+pub(super) fn move_into_graph<T: Send + 'static>(graph: CUgraph, data: T) -> Result<*mut T, Error> {
+    extern "C" fn destroy<T>(data: *mut c_void) {
+        drop(unsafe { Box::from_raw(data.cast::<T>()) })
+    }
+
+    let data = Box::into_raw(Box::new(data));
+    let mut obj = null_mut();
+    if let Err(e) = try_driver!(cuUserObjectCreate(
+        &mut obj,
+        data.cast(),
+        Some(destroy::<T>),
+        1,
+        CUuserObject_flags::CU_USER_OBJECT_NO_DESTRUCTOR_SYNC as _,
+    )) {
+        drop(unsafe { Box::from_raw(data) });
+        return Err(e);
+    }
+    // 引用转移给图，失败时由释放引用的操作释放数据
+    if let Err(e) = try_driver!(cuGraphRetainUserObject(
+        graph,
+        obj,
+        1,
+        CUuserObjectRetain_flags::CU_GRAPH_USER_OBJECT_MOVE as _,
+    )) {
+        driver!(cuUserObjectRelease(obj, 1));
+        return Err(e);
+    }
+    Ok(data)
+}
+
+impl Stream<'_> {
+    /// 在流上调用 `f`。
+    ///
+    /// 流正在捕获时，`f` 由捕获的图持有，在图第一次执行时调用，图销毁时释放。
+    #[inline]
+    #[track_caller]
+    pub fn launch_host_fn(&self, f: impl FnOnce() + Send + 'static) -> &Self {
+        self.try_launch_host_fn(f).unwrap()
+    }
+
+    pub fn try_launch_host_fn<F: FnOnce() + Send + 'static>(&self, f: F) -> Result<&Self, Error> {
+        extern "C" fn call_once<F: FnOnce()>(data: *mut c_void) {
+            let f = unsafe { Box::from_raw(data.cast::<F>()) };
+            f()
+        }
+        extern "C" fn call_first<F: FnOnce()>(data: *mut c_void) {
+            let f = unsafe { &*data.cast::<Mutex<Option<F>>>() };
+            if let Some(f) = f.lock().unwrap().take() {
+                f()
+            }
+        }
+
+        let stream = unsafe { self.as_raw() };
+        let mut status = CUstreamCaptureStatus::CU_STREAM_CAPTURE_STATUS_NONE;
+        let mut graph = null_mut();
+        try_driver!(cuStreamGetCaptureInfo_v2(
+            stream,
+            &mut status,
+            null_mut(),
+            &mut graph,
+            null_mut(),
+            null_mut(),
+        ))?;
+        if status == CUstreamCaptureStatus::CU_STREAM_CAPTURE_STATUS_ACTIVE {
+            // 捕获的图可能执行多次或不执行，闭包随图释放
+            let data = move_into_graph(graph, Mutex::new(Some(f)))?;
+            try_driver!(cuLaunchHostFunc(stream, Some(call_first::<F>), data.cast()))?
+        } else {
+            // 闭包在回调中释放，提交失败时在此释放
+            let data = Box::into_raw(Box::new(f));
+            if let Err(e) = try_driver!(cuLaunchHostFunc(stream, Some(call_once::<F>), data.cast()))
+            {
+                drop(unsafe { Box::from_raw(data) });
+                return Err(e);
+            }
+        }
+        Ok(self)
+    }
+}
 
 impl Graph {
-    pub fn add_host_node_with_rust_fn<'a>(
+    #[inline]
+    #[track_caller]
+    pub fn add_host_node_with_rust_fn<'a, F: Fn() + Send + Sync + 'static>(
         &self,
-        host_fn: impl Fn() + Send + Sync + 'static,
+        host_fn: F,
         deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
-    ) -> HostFnNode {
-        extern "C" fn c_host_fn(user_data: *mut c_void) {
-            let host_fn = unsafe { Box::from_raw(user_data as *mut Box<dyn Fn()>) };
-            host_fn();
+    ) -> HostFnNode<'_> {
+        self.try_add_host_node_with_rust_fn(host_fn, deps).unwrap()
+    }
+
+    /// 添加调用 `host_fn` 的节点。`host_fn` 随图释放，图可以执行任意次。
+    pub fn try_add_host_node_with_rust_fn<'a, F: Fn() + Send + Sync + 'static>(
+        &self,
+        host_fn: F,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<HostFnNode<'_>, Error> {
+        extern "C" fn c_host_fn<F: Fn()>(user_data: *mut c_void) {
+            let f = unsafe { &*user_data.cast::<F>() };
+            f()
         }
-        let boxed_closure: Box<dyn Fn()> = Box::new(host_fn);
 
-        let user_data = Box::into_raw(Box::new(boxed_closure));
-        self.add_host_node(Some(c_host_fn), user_data as *mut c_void, deps)
+        let user_data = move_into_graph(unsafe { self.as_raw() }, host_fn)?;
+        self.try_add_host_node(Some(c_host_fn::<F>), user_data.cast(), deps)
     }
 
+    #[inline]
+    #[track_caller]
     pub fn add_host_node<'a>(
         &self,
         host_fn: CUhostFn,
         user_data: *mut c_void,
         deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
-    ) -> HostFnNode {
+    ) -> HostFnNode<'_> {
+        self.try_add_host_node(host_fn, user_data, deps).unwrap()
+    }
+
+    pub fn try_add_host_node<'a>(
+        &self,
+        host_fn: CUhostFn,
+        user_data: *mut c_void,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<HostFnNode<'_>, Error> {
         let deps = collect_dependencies(deps);
 
         let cuda_host_node_params = CUDA_HOST_NODE_PARAMS {
@@ -32,14 +139,14 @@ impl Graph {
             userData: user_data,
         };
         let mut node = null_mut();
-        driver!(cuGraphAddHostNode(
+        try_driver!(cuGraphAddHostNode(
             &mut node,
             self.as_raw(),
             deps.as_ptr(),
             deps.len(),
             &cuda_host_node_params,
-        ));
-        HostFnNode(node, PhantomData)
+        ))?;
+        Ok(HostFnNode(node, PhantomData))
     }
 }
 
@@ -49,6 +156,7 @@ mod test {
     use std::ptr::{null, null_mut};
 
     #[test]
+    #[cfg_attr(feature = "mock", ignore = "the mock driver cannot compile kernels")]
     fn test_launch_host_fn() {
         const CODE: &str = r#"extern "C" __global__ void print(int n) { printf("Hello, world(%d)! from GPU\n", n); }"#;
 
@@ -90,6 +198,7 @@ mod test {
     }
 
     #[test]
+    #[cfg_attr(feature = "mock", ignore = "the mock driver cannot compile kernels")]
     fn test_host_graph_dot() {
         const CODE: &str = r#"extern "C" __global__ void print(int n) { printf("Hello, world(%d)! from GPU\n", n); }"#;
 
@@ -112,7 +221,7 @@ mod test {
             driver!(cuLaunchHostFunc(
                 stream.as_raw(),
                 Some(host_fn as unsafe extern "C" fn(*mut core::ffi::c_void)),
-                1 as *mut core::ffi::c_void
+                std::ptr::without_provenance_mut(1)
             ));
 
             stream.launch(&kernel, (1, 1, 0), &params![1].to_ptrs());
@@ -120,7 +229,7 @@ mod test {
             driver!(cuLaunchHostFunc(
                 stream.as_raw(),
                 Some(host_fn as unsafe extern "C" fn(*mut core::ffi::c_void)),
-                2 as *mut core::ffi::c_void
+                std::ptr::without_provenance_mut(2)
             ));
 
             stream.launch(&kernel, (1, 1, 0), &params![2].to_ptrs());
@@ -132,6 +241,7 @@ mod test {
     }
 
     #[test]
+    #[cfg_attr(feature = "mock", ignore = "the mock driver cannot compile kernels")]
     fn test_host_graph_node() {
         const CODE: &str = r#"extern "C" __global__ void print(int n) { printf("Hello, world(%d)! from GPU\n", n); }"#;
 
@@ -154,7 +264,7 @@ mod test {
 
             let cuda_host_node_params = CUDA_HOST_NODE_PARAMS {
                 fn_: Some(host_fn),
-                userData: one as *mut core::ffi::c_void,
+                userData: std::ptr::without_provenance_mut(one),
             };
             let mut node = null_mut();
             driver!(cuGraphAddHostNode(
@@ -203,4 +313,71 @@ mod test {
             stream.launch_graph(&exec);
         });
     }
+
+    #[test]
+    fn test_rust_fn_relaunch() {
+        use std::sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering::SeqCst},
+        };
+
+        if let Err(crate::NoDevice) = crate::init() {
+            return;
+        }
+        crate::Device::new(0).context().apply(|ctx| {
+            let count = Arc::new(AtomicUsize::new(0));
+            let graph = Graph::new();
+            let count_ = count.clone();
+            graph.add_host_node_with_rust_fn(
+                move || {
+                    count_.fetch_add(1, SeqCst);
+                },
+                &[],
+            );
+            let exec = ctx.instantiate(&graph);
+            // 执行图仍然持有闭包
+            drop(graph);
+
+            let stream = ctx.stream();
+            for _ in 0..3 {
+                stream.launch_graph(&exec);
+            }
+            stream.synchronize();
+            assert_eq!(count.load(SeqCst), 3)
+        });
+    }
+
+    #[test]
+    fn test_stream_host_fn() {
+        use std::sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering::SeqCst},
+        };
+
+        if let Err(crate::NoDevice) = crate::init() {
+            return;
+        }
+        crate::Device::new(0).context().apply(|ctx| {
+            let count = Arc::new(AtomicUsize::new(0));
+            let stream = ctx.stream();
+            let count_ = count.clone();
+            stream
+                .launch_host_fn(move || {
+                    count_.fetch_add(1, SeqCst);
+                })
+                .synchronize();
+            assert_eq!(count.load(SeqCst), 1);
+
+            // 捕获的闭包只在图第一次执行时调用
+            let stream = stream.capture();
+            let count_ = count.clone();
+            stream.launch_host_fn(move || {
+                count_.fetch_add(1, SeqCst);
+            });
+            let exec = ctx.instantiate(&stream.end());
+            let stream = ctx.stream();
+            stream.launch_graph(&exec).launch_graph(&exec).synchronize();
+            assert_eq!(count.load(SeqCst), 2)
+        });
+    }
 }
@@ -0,0 +1,267 @@
+﻿use super::{Graph, GraphExec, GraphNode};
+use crate::{
+    CurrentCtx, Error, Stream,
+    bindings::{
+        CUDA_GRAPH_INSTANTIATE_PARAMS, CUgraphInstantiate_flags::*, CUgraphInstantiateResult,
+    },
+};
+use context_spore::AsRaw;
+use std::{fmt, marker::PhantomData, ptr::null_mut};
+
+impl CurrentCtx {
+    #[inline]
+    pub fn instantiate_builder(&self) -> InstantiateBuilder<'_> {
+        InstantiateBuilder {
+            ctx: self,
+            auto_free_on_launch: true,
+            upload: None,
+            device_launch: false,
+            use_node_priority: false,
+        }
+    }
+}
+
+/// 按指定的选项实例化图。
+pub struct InstantiateBuilder<'a> {
+    ctx: &'a CurrentCtx,
+    auto_free_on_launch: bool,
+    upload: Option<&'a Stream<'a>>,
+    device_launch: bool,
+    use_node_priority: bool,
+}
+
+impl<'a> InstantiateBuilder<'a> {
+    /// 每次执行前自动释放上次执行时图分配且未释放的存储，默认启用。
+    #[inline]
+    pub fn auto_free_on_launch(mut self, value: bool) -> Self {
+        self.auto_free_on_launch = value;
+        self
+    }
+
+    /// 实例化后立即在 `stream` 上传执行图，避免第一次执行的额外延迟。
+    #[inline]
+    pub fn upload(mut self, stream: &'a Stream<'a>) -> Self {
+        self.upload = Some(stream);
+        self
+    }
+
+    /// 允许从设备上启动执行图，图中只能包含设备上的操作。
+    ///
+    /// 不能与 [`auto_free_on_launch`](Self::auto_free_on_launch) 同时启用，
+    /// 需要先关闭默认启用的 `auto_free_on_launch`，否则实例化时返回错误。
+    #[inline]
+    pub fn device_launch(mut self, value: bool) -> Self {
+        self.device_launch = value;
+        self
+    }
+
+    /// 执行时使用 kernel 节点各自的优先级，而不是流的优先级。
+    #[inline]
+    pub fn use_node_priority(mut self, value: bool) -> Self {
+        self.use_node_priority = value;
+        self
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn instantiate(self, graph: &Graph) -> GraphExec<'a> {
+        self.try_instantiate(graph).unwrap()
+    }
+
+    pub fn try_instantiate<'g>(
+        self,
+        graph: &'g Graph,
+    ) -> Result<GraphExec<'a>, InstantiateError<'g>> {
+        if self.device_launch && self.auto_free_on_launch {
+            return Err(InstantiateError::Driver(invalid_value!(
+                "device_launch is not combined with auto_free_on_launch"
+            )));
+        }
+        let mut flags = 0;
+        for (value, flag) in [
+            (
+                self.auto_free_on_launch,
+                CUDA_GRAPH_INSTANTIATE_FLAG_AUTO_FREE_ON_LAUNCH,
+            ),
+            (self.upload.is_some(), CUDA_GRAPH_INSTANTIATE_FLAG_UPLOAD),
+            (
+                self.device_launch,
+                CUDA_GRAPH_INSTANTIATE_FLAG_DEVICE_LAUNCH,
+            ),
+            (
+                self.use_node_priority,
+                CUDA_GRAPH_INSTANTIATE_FLAG_USE_NODE_PRIORITY,
+            ),
+        ] {
+            if value {
+                flags |= flag as u64
+            }
+        }
+        let mut params = CUDA_GRAPH_INSTANTIATE_PARAMS {
+            flags: flags as _,
+            hUploadStream: self.upload.map_or(null_mut(), |s| unsafe { s.as_raw() }),
+            hErrNode_out: null_mut(),
+            result_out: CUgraphInstantiateResult::CUDA_GRAPH_INSTANTIATE_SUCCESS,
+        };
+        let mut exec = null_mut();
+        match try_driver!(cuGraphInstantiateWithParams(
+            &mut exec,
+            graph.as_raw(),
+            &mut params
+        )) {
+            Ok(()) => Ok(GraphExec(unsafe { self.ctx.wrap_raw(exec) }, PhantomData)),
+            Err(e)
+                if params.result_out
+                    == CUgraphInstantiateResult::CUDA_GRAPH_INSTANTIATE_SUCCESS =>
+            {
+                Err(InstantiateError::Driver(e))
+            }
+            Err(_) => Err(InstantiateError::Rejected {
+                reason: params.result_out.into(),
+                node: (!params.hErrNode_out.is_null())
+                    .then(|| GraphNode::try_new(params.hErrNode_out).ok())
+                    .flatten(),
+            }),
+        }
+    }
+}
+
+/// 图不能实例化的原因。
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum InstantiateFailure {
+    /// 图的结构不合法，例如存在环。
+    InvalidStructure,
+    /// 节点的操作不支持指定的选项，例如设备启动的图包含主机节点。
+    NodeOperationNotSupported,
+    /// 节点属于不同的上下文，而选项要求只有一个上下文。
+    MultipleCtxsNotSupported,
+    /// 其他原因。
+    Other,
+}
+
+impl From<CUgraphInstantiateResult> for InstantiateFailure {
+    fn from(value: CUgraphInstantiateResult) -> Self {
+        use CUgraphInstantiateResult::*;
+        match value {
+            CUDA_GRAPH_INSTANTIATE_INVALID_STRUCTURE => Self::InvalidStructure,
+            CUDA_GRAPH_INSTANTIATE_NODE_OPERATION_NOT_SUPPORTED => Self::NodeOperationNotSupported,
+            CUDA_GRAPH_INSTANTIATE_MULTIPLE_CTXS_NOT_SUPPORTED => Self::MultipleCtxsNotSupported,
+            _ => Self::Other,
+        }
+    }
+}
+
+/// 实例化图失败。
+#[derive(Debug)]
+pub enum InstantiateError<'g> {
+    /// 驱动拒绝实例化，`node` 是导致失败的节点。
+    Rejected {
+        reason: InstantiateFailure,
+        node: Option<GraphNode<'g>>,
+    },
+    /// 驱动调用失败。
+    Driver(Error),
+}
+
+impl fmt::Display for InstantiateError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rejected { reason, node } => {
+                write!(f, "graph instantiation rejected: {reason:?}")?;
+                if let Some(node) = node {
+                    write!(f, " at node {:?}", unsafe { node.as_raw() })?
+                }
+                Ok(())
+            }
+            Self::Driver(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for InstantiateError<'_> {}
+
+impl GraphExec<'_> {
+    #[inline]
+    #[track_caller]
+    pub fn upload(&self, stream: &Stream) {
+        self.try_upload(stream).unwrap()
+    }
+
+    /// 在 `stream` 上预先上传执行图，之后在任意流上执行都不需要再上传。
+    pub fn try_upload(&self, stream: &Stream) -> Result<(), Error> {
+        try_driver!(cuGraphUpload(self.as_raw(), stream.as_raw()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{InstantiateError, InstantiateFailure};
+    use crate::{AsRaw, Device, Graph, GraphNode, bindings::CUresult, memcpy_d2h};
+
+    #[test]
+    fn test_builder() {
+        if let Err(crate::NoDevice) = crate::init() {
+            return;
+        }
+        Device::new(0).context().apply(|ctx| {
+            let host = (0..256u32).collect::<Vec<_>>();
+            let mut mem = ctx.malloc::<u32>(host.len());
+            let graph = Graph::new();
+            graph.add_memcpy_h2d(&mut mem, &*host, &[]);
+
+            // 实例化时上传，再在另一个流上执行
+            let stream = ctx.stream();
+            let exec = ctx
+                .instantiate_builder()
+                .auto_free_on_launch(false)
+                .use_node_priority(true)
+                .upload(&stream)
+                .instantiate(&graph);
+            exec.upload(&stream);
+            ctx.stream().launch_graph(&exec).synchronize();
+            let mut ans = vec![0u32; host.len()];
+            memcpy_d2h(&mut ans, &mem);
+            assert_eq!(ans, host)
+        })
+    }
+
+    #[test]
+    fn test_error_node() {
+        if let Err(crate::NoDevice) = crate::init() {
+            return;
+        }
+        Device::new(0).context().apply(|ctx| {
+            let mut mem = ctx.malloc::<u8>(64);
+            let graph = Graph::new();
+            let memset = graph.add_memset(&mut mem, 0u8, &[]);
+            let host = graph.add_host_node_with_rust_fn(|| {}, &[memset.into()]);
+            let raw = unsafe { host.as_raw() };
+
+            // 设备启动的图不能包含主机节点
+            match ctx
+                .instantiate_builder()
+                .auto_free_on_launch(false)
+                .device_launch(true)
+                .try_instantiate(&graph)
+            {
+                Err(InstantiateError::Rejected {
+                    reason: InstantiateFailure::NodeOperationNotSupported,
+                    node: Some(node @ GraphNode::HostFn(_)),
+                }) => assert_eq!(unsafe { node.as_raw() }, raw),
+                _ => panic!(),
+            }
+
+            // 设备启动不能与默认的 auto_free_on_launch 同时启用
+            match ctx
+                .instantiate_builder()
+                .device_launch(true)
+                .try_instantiate(&graph)
+            {
+                Err(InstantiateError::Driver(e)) => {
+                    assert_eq!(e.code(), CUresult::CUDA_ERROR_INVALID_VALUE)
+                }
+                _ => panic!(),
+            }
+        })
+    }
+}
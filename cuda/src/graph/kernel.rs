@@ -1,63 +1,102 @@
 ﻿use super::{Graph, GraphNode, KernelNode, collect_dependencies};
-use crate::{Dim3, KernelFn, bindings::CUDA_KERNEL_NODE_PARAMS};
+use crate::{Dim3, Error, KernelFn, bindings::CUDA_KERNEL_NODE_PARAMS};
 use context_spore::AsRaw;
 use std::{ffi::c_void, marker::PhantomData, ptr::null_mut};
 
+/// 构造 kernel 节点的参数。
+pub(super) fn kernel_params(
+    f: &KernelFn,
+    attrs: (impl Into<Dim3>, impl Into<Dim3>, usize),
+    params: &[*const c_void],
+) -> CUDA_KERNEL_NODE_PARAMS {
+    let (grid, block, shared_mem) = attrs;
+    let grid = grid.into();
+    let block = block.into();
+    CUDA_KERNEL_NODE_PARAMS {
+        func: unsafe { f.as_raw() },
+        gridDimX: grid.x,
+        gridDimY: grid.y,
+        gridDimZ: grid.z,
+        blockDimX: block.x,
+        blockDimY: block.y,
+        blockDimZ: block.z,
+        sharedMemBytes: shared_mem as _,
+        kernelParams: params.as_ptr() as _,
+        extra: null_mut(),
+        kern: null_mut(),
+        ctx: null_mut(),
+    }
+}
+
 impl Graph {
+    #[inline]
+    #[track_caller]
     pub fn add_kernel_call<'a>(
         &self,
         f: &KernelFn,
         attrs: (impl Into<Dim3>, impl Into<Dim3>, usize),
         params: &[*const c_void],
         deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
-    ) -> KernelNode {
-        let (grid, block, shared_mem) = attrs;
-        let grid = grid.into();
-        let block = block.into();
-        let params = CUDA_KERNEL_NODE_PARAMS {
-            func: unsafe { f.as_raw() },
-            gridDimX: grid.x,
-            gridDimY: grid.y,
-            gridDimZ: grid.z,
-            blockDimX: block.x,
-            blockDimY: block.y,
-            blockDimZ: block.z,
-            sharedMemBytes: shared_mem as _,
-            kernelParams: params.as_ptr() as _,
-            extra: null_mut(),
-            kern: null_mut(),
-            ctx: null_mut(),
-        };
-
-        self.add_kernel_node_with_params(&params, deps)
+    ) -> KernelNode<'_> {
+        self.try_add_kernel_call(f, attrs, params, deps).unwrap()
+    }
+
+    pub fn try_add_kernel_call<'a>(
+        &self,
+        f: &KernelFn,
+        attrs: (impl Into<Dim3>, impl Into<Dim3>, usize),
+        params: &[*const c_void],
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<KernelNode<'_>, Error> {
+        self.try_add_kernel_node_with_params(&kernel_params(f, attrs, params), deps)
     }
 
+    #[inline]
+    #[track_caller]
     pub fn add_kernel_node<'a>(
         &self,
         node: &KernelNode,
         deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
-    ) -> KernelNode {
+    ) -> KernelNode<'_> {
+        self.try_add_kernel_node(node, deps).unwrap()
+    }
+
+    pub fn try_add_kernel_node<'a>(
+        &self,
+        node: &KernelNode,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<KernelNode<'_>, Error> {
         let mut params = unsafe { std::mem::zeroed() };
-        driver!(cuGraphKernelNodeGetParams_v2(node.as_raw(), &mut params));
+        try_driver!(cuGraphKernelNodeGetParams_v2(node.as_raw(), &mut params))?;
 
-        self.add_kernel_node_with_params(&params, deps)
+        self.try_add_kernel_node_with_params(&params, deps)
     }
 
+    #[inline]
+    #[track_caller]
     pub fn add_kernel_node_with_params<'a>(
         &self,
         params: &CUDA_KERNEL_NODE_PARAMS,
         deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
-    ) -> KernelNode {
+    ) -> KernelNode<'_> {
+        self.try_add_kernel_node_with_params(params, deps).unwrap()
+    }
+
+    pub fn try_add_kernel_node_with_params<'a>(
+        &self,
+        params: &CUDA_KERNEL_NODE_PARAMS,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<KernelNode<'_>, Error> {
         let deps = collect_dependencies(deps);
 
         let mut node = null_mut();
-        driver!(cuGraphAddKernelNode_v2(
+        try_driver!(cuGraphAddKernelNode_v2(
             &mut node,
             self.as_raw(),
             deps.as_ptr(),
             deps.len(),
             params,
-        ));
-        KernelNode(node, PhantomData)
+        ))?;
+        Ok(KernelNode(node, PhantomData))
     }
 }
@@ -1,25 +1,35 @@
 ﻿use super::{Graph, GraphNode, MemAllocNode, collect_dependencies};
-use crate::bindings::CUDA_MEM_ALLOC_NODE_PARAMS;
+use crate::{Error, bindings::CUDA_MEM_ALLOC_NODE_PARAMS};
 use context_spore::AsRaw;
 use std::{marker::PhantomData, ptr::null_mut};
 
 impl Graph {
+    #[inline]
+    #[track_caller]
     pub fn add_alloc_node_with_params<'a>(
         &self,
         params: &mut CUDA_MEM_ALLOC_NODE_PARAMS,
         deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
-    ) -> MemAllocNode {
+    ) -> MemAllocNode<'_> {
+        self.try_add_alloc_node_with_params(params, deps).unwrap()
+    }
+
+    pub fn try_add_alloc_node_with_params<'a>(
+        &self,
+        params: &mut CUDA_MEM_ALLOC_NODE_PARAMS,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<MemAllocNode<'_>, Error> {
         let deps = collect_dependencies(deps);
 
         let mut node = null_mut();
-        driver!(cuGraphAddMemAllocNode(
+        try_driver!(cuGraphAddMemAllocNode(
             &mut node,
             self.as_raw(),
             deps.as_ptr(),
             deps.len(),
             params,
-        ));
-        MemAllocNode(node, PhantomData)
+        ))?;
+        Ok(MemAllocNode(node, PhantomData))
     }
 }
 
@@ -1,13 +1,15 @@
-﻿use super::{Graph, GraphNode, MemcpyNode, collect_dependencies};
+﻿use super::{Graph, GraphNode, MemcpyNode, collect_dependencies, host_fn::move_into_graph};
 use crate::{
-    DevByte,
-    bindings::{CUDA_MEMCPY3D, CUmemorytype},
+    Context, DevByte, Error, StridedView, StridedViewMut,
+    bindings::{CUDA_MEMCPY3D, CUcontext, CUmemorytype},
+    strided::memcpy_3d_params,
 };
 use context_spore::AsRaw;
 use std::{
     marker::PhantomData,
     mem::MaybeUninit,
     ptr::{null, null_mut},
+    sync::{Arc, Mutex, PoisonError},
 };
 
 const CFG: CUDA_MEMCPY3D = CUDA_MEMCPY3D {
@@ -39,14 +41,27 @@ const CFG: CUDA_MEMCPY3D = CUDA_MEMCPY3D {
 };
 
 impl Graph {
+    #[inline]
+    #[track_caller]
     pub fn add_memcpy_d2d<'a>(
         &self,
         dst: &mut [DevByte],
         src: &[DevByte],
         deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
-    ) -> MemcpyNode {
-        assert_eq!(size_of_val(dst), size_of_val(src));
-        self.add_memcpy_node_with_params(
+    ) -> MemcpyNode<'_> {
+        self.try_add_memcpy_d2d(dst, src, deps).unwrap()
+    }
+
+    pub fn try_add_memcpy_d2d<'a>(
+        &self,
+        dst: &mut [DevByte],
+        src: &[DevByte],
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<MemcpyNode<'_>, Error> {
+        if size_of_val(dst) != size_of_val(src) {
+            return Err(invalid_value!("dst and src have the same length"));
+        }
+        self.try_add_memcpy_node_with_params(
             &CUDA_MEMCPY3D {
                 srcMemoryType: CUmemorytype::CU_MEMORYTYPE_DEVICE,
                 srcDevice: src.as_ptr() as _,
@@ -59,37 +74,237 @@ impl Graph {
         )
     }
 
+    #[inline]
+    #[track_caller]
+    pub fn add_memcpy_h2d<'a, T: Copy + Send + 'static>(
+        &self,
+        dst: &mut [DevByte],
+        src: impl Into<Box<[T]>>,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> MemcpyNode<'_> {
+        self.try_add_memcpy_h2d(dst, src, deps).unwrap()
+    }
+
+    /// 添加从主机拷贝到设备的节点。
+    ///
+    /// `src` 的所有权转移给图，图和从图实例化的执行图都销毁后释放。传入切片时复制一份。
+    pub fn try_add_memcpy_h2d<'a, T: Copy + Send + 'static>(
+        &self,
+        dst: &mut [DevByte],
+        src: impl Into<Box<[T]>>,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<MemcpyNode<'_>, Error> {
+        let src = src.into();
+        if size_of_val(dst) != size_of_val(&*src) {
+            return Err(invalid_value!("dst and src have the same length"));
+        }
+        let src = move_into_graph(unsafe { self.as_raw() }, src)?;
+        self.try_add_memcpy_node_with_params(
+            &CUDA_MEMCPY3D {
+                srcMemoryType: CUmemorytype::CU_MEMORYTYPE_HOST,
+                srcHost: unsafe { (*src).as_ptr() }.cast(),
+                dstMemoryType: CUmemorytype::CU_MEMORYTYPE_DEVICE,
+                dstDevice: dst.as_mut_ptr() as _,
+                WidthInBytes: size_of_val(dst),
+                ..CFG
+            },
+            deps,
+        )
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn add_memcpy_d2h<'a, T: Copy + Send + 'static>(
+        &self,
+        dst: Arc<Mutex<[T]>>,
+        src: &[DevByte],
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> MemcpyNode<'_> {
+        self.try_add_memcpy_d2h(dst, src, deps).unwrap()
+    }
+
+    /// 添加从设备拷贝到主机的节点。
+    ///
+    /// 图持有 `dst` 的一份引用，图和从图实例化的执行图都销毁后释放。
+    /// 执行图完成后锁定 `dst` 读取结果，执行期间不应访问 `dst`。
+    pub fn try_add_memcpy_d2h<'a, T: Copy + Send + 'static>(
+        &self,
+        dst: Arc<Mutex<[T]>>,
+        src: &[DevByte],
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<MemcpyNode<'_>, Error> {
+        // 切片在 `Arc` 中，地址在图持有引用期间不变
+        let (ptr, len) = {
+            let mut slice = dst.lock().unwrap_or_else(PoisonError::into_inner);
+            (slice.as_mut_ptr(), size_of_val(&*slice))
+        };
+        if len != size_of_val(src) {
+            return Err(invalid_value!("dst and src have the same length"));
+        }
+        move_into_graph(unsafe { self.as_raw() }, dst)?;
+        self.try_add_memcpy_node_with_params(
+            &CUDA_MEMCPY3D {
+                srcMemoryType: CUmemorytype::CU_MEMORYTYPE_DEVICE,
+                srcDevice: src.as_ptr() as _,
+                dstMemoryType: CUmemorytype::CU_MEMORYTYPE_HOST,
+                dstHost: ptr.cast(),
+                WidthInBytes: size_of_val(src),
+                ..CFG
+            },
+            deps,
+        )
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn add_memcpy_peer<'a>(
+        &self,
+        dst: &mut [DevByte],
+        dst_ctx: &Context,
+        src: &[DevByte],
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> MemcpyNode<'_> {
+        self.try_add_memcpy_peer(dst, dst_ctx, src, deps).unwrap()
+    }
+
+    /// 添加在 `dst_ctx` 上执行的拷贝节点，`src` 可以在其他上下文中分配。
+    pub fn try_add_memcpy_peer<'a>(
+        &self,
+        dst: &mut [DevByte],
+        dst_ctx: &Context,
+        src: &[DevByte],
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<MemcpyNode<'_>, Error> {
+        if size_of_val(dst) != size_of_val(src) {
+            return Err(invalid_value!("dst and src have the same length"));
+        }
+        self.add_memcpy_in_ctx(
+            &CUDA_MEMCPY3D {
+                srcMemoryType: CUmemorytype::CU_MEMORYTYPE_DEVICE,
+                srcDevice: src.as_ptr() as _,
+                dstMemoryType: CUmemorytype::CU_MEMORYTYPE_DEVICE,
+                dstDevice: dst.as_mut_ptr() as _,
+                WidthInBytes: size_of_val(dst),
+                ..CFG
+            },
+            unsafe { dst_ctx.as_raw() },
+            deps,
+        )
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn add_memcpy_2d<'a>(
+        &self,
+        dst: StridedViewMut,
+        src: StridedView,
+        extent: (usize, usize),
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> MemcpyNode<'_> {
+        self.try_add_memcpy_2d(dst, src, extent, deps).unwrap()
+    }
+
+    /// 添加二维拷贝节点，参数含义同 [`Stream::memcpy_2d`](crate::Stream::memcpy_2d)，视图须在设备上。
+    pub fn try_add_memcpy_2d<'a>(
+        &self,
+        dst: StridedViewMut,
+        src: StridedView,
+        (width, height): (usize, usize),
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<MemcpyNode<'_>, Error> {
+        self.try_add_memcpy_3d(dst, src, (width, height, 1), deps)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn add_memcpy_3d<'a>(
+        &self,
+        dst: StridedViewMut,
+        src: StridedView,
+        extent: (usize, usize, usize),
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> MemcpyNode<'_> {
+        self.try_add_memcpy_3d(dst, src, extent, deps).unwrap()
+    }
+
+    /// 添加三维拷贝节点，参数含义同 [`Stream::memcpy_3d`](crate::Stream::memcpy_3d)。
+    ///
+    /// 节点只记录视图的地址，图的存活期间不能保证主机存储有效，因此视图须在设备上。
+    pub fn try_add_memcpy_3d<'a>(
+        &self,
+        dst: StridedViewMut,
+        src: StridedView,
+        extent: (usize, usize, usize),
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<MemcpyNode<'_>, Error> {
+        let params = memcpy_3d_params(dst, src, extent)?;
+        let device = CUmemorytype::CU_MEMORYTYPE_DEVICE;
+        if params.srcMemoryType != device || params.dstMemoryType != device {
+            return Err(invalid_value!("graph strided views are on device"));
+        }
+        self.try_add_memcpy_node_with_params(&params, deps)
+    }
+
+    #[inline]
+    #[track_caller]
     pub fn add_memcpy_node<'a>(
         &self,
         node: &MemcpyNode,
         deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
-    ) -> MemcpyNode {
+    ) -> MemcpyNode<'_> {
+        self.try_add_memcpy_node(node, deps).unwrap()
+    }
+
+    pub fn try_add_memcpy_node<'a>(
+        &self,
+        node: &MemcpyNode,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<MemcpyNode<'_>, Error> {
         let mut params = MaybeUninit::uninit();
-        driver!(cuGraphMemcpyNodeGetParams(
+        try_driver!(cuGraphMemcpyNodeGetParams(
             node.as_raw(),
             params.as_mut_ptr()
-        ));
+        ))?;
 
-        self.add_memcpy_node_with_params(unsafe { params.assume_init_ref() }, deps)
+        self.try_add_memcpy_node_with_params(unsafe { params.assume_init_ref() }, deps)
     }
 
+    #[inline]
+    #[track_caller]
     pub fn add_memcpy_node_with_params<'a>(
         &self,
         params: &CUDA_MEMCPY3D,
         deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
-    ) -> MemcpyNode {
+    ) -> MemcpyNode<'_> {
+        self.try_add_memcpy_node_with_params(params, deps).unwrap()
+    }
+
+    pub fn try_add_memcpy_node_with_params<'a>(
+        &self,
+        params: &CUDA_MEMCPY3D,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<MemcpyNode<'_>, Error> {
+        self.add_memcpy_in_ctx(params, null_mut(), deps)
+    }
+
+    fn add_memcpy_in_ctx<'a>(
+        &self,
+        params: &CUDA_MEMCPY3D,
+        ctx: CUcontext,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<MemcpyNode<'_>, Error> {
         let deps = collect_dependencies(deps);
 
         let mut node = null_mut();
-        driver!(cuGraphAddMemcpyNode(
+        try_driver!(cuGraphAddMemcpyNode(
             &mut node,
             self.as_raw(),
             deps.as_ptr(),
             deps.len(),
             params,
-            null_mut(),
-        ));
-        MemcpyNode(node, PhantomData)
+            ctx,
+        ))?;
+        Ok(MemcpyNode(node, PhantomData))
     }
 }
 
@@ -178,6 +393,82 @@ mod test {
         test_memcpy_in_graph(&dev, &graph, dst, src, (0..u64::MAX).rev());
     }
 
+    #[test]
+    fn test_typed() {
+        use crate::{StridedView, StridedViewMut, bindings::CUresult};
+        use std::sync::{Arc, Mutex};
+
+        if let Err(crate::NoDevice) = crate::init() {
+            return;
+        }
+
+        Device::new(0).context().apply(|ctx| {
+            // 4 行 3 列的矩阵
+            let host = (0..12u32).collect::<Vec<_>>();
+            let mut mat = ctx.malloc::<u32>(12);
+            let mut col = ctx.malloc::<u32>(4);
+            let ans: Arc<Mutex<[u32]>> = Arc::new(Mutex::new([0; 4]));
+
+            let graph = Graph::new();
+            // 图持有 host 的副本和 ans 的引用
+            let h2d = graph.add_memcpy_h2d(&mut mat, &*host, &[]);
+            // 取出第 1 列
+            let col_2d = graph.add_memcpy_2d(
+                StridedViewMut::device(&mut col, 4),
+                StridedView::device(&mat, 12).offset(4, 0, 0),
+                (4, 4),
+                &[h2d.into()],
+            );
+            graph.add_memcpy_d2h(ans.clone(), &col, &[col_2d.into()]);
+            assert_eq!(graph.nodes().len(), 3);
+
+            // 图中的拷贝不接受主机上的视图
+            let mut host_ = [0u32; 4];
+            let e = graph
+                .try_add_memcpy_2d(
+                    StridedViewMut::host(&mut host_, 4),
+                    StridedView::device(&col, 4),
+                    (4, 4),
+                    &[],
+                )
+                .unwrap_err();
+            assert_eq!(e.code(), CUresult::CUDA_ERROR_INVALID_VALUE);
+
+            let exec = ctx.instantiate(&graph);
+            drop(graph);
+            ctx.stream().launch_graph(&exec).synchronize();
+            assert_eq!(*ans.lock().unwrap(), [1, 4, 7, 10])
+        })
+    }
+
+    #[test]
+    fn test_peer() {
+        use crate::Device;
+
+        if let Err(crate::NoDevice) = crate::init() {
+            return;
+        }
+        if Device::count() < 2 {
+            return;
+        }
+        let (ctx0, ctx1) = (Device::new(0).context(), Device::new(1).context());
+        let host = (0..1024u32).collect::<Vec<_>>();
+        ctx1.apply(|c1| {
+            let src = c1.from_host(&host);
+            ctx0.apply(|c0| {
+                let mut dst = c0.malloc::<u32>(host.len());
+                let graph = Graph::new();
+                graph.add_memcpy_peer(&mut dst, &ctx0, &src, &[]);
+                c0.stream()
+                    .launch_graph(&c0.instantiate(&graph))
+                    .synchronize();
+                let mut host_ = vec![0u32; host.len()];
+                memcpy_d2h(&mut host_, &dst);
+                assert_eq!(host, host_)
+            })
+        })
+    }
+
     fn test_memcpy_in_graph(
         dev: &Device,
         graph: &Graph,
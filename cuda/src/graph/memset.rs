@@ -1,25 +1,116 @@
 ﻿use super::{Graph, GraphNode, MemsetNode};
-use crate::{bindings::CUDA_MEMSET_NODE_PARAMS, graph::collect_dependencies};
+use crate::{
+    DevByte, Error, MemsetElem, bindings::CUDA_MEMSET_NODE_PARAMS, graph::collect_dependencies,
+    memset::elements,
+};
 use context_spore::AsRaw;
 use std::{marker::PhantomData, ptr::null_mut};
 
 impl Graph {
+    #[inline]
+    #[track_caller]
+    pub fn add_memset<'a, T: MemsetElem>(
+        &self,
+        dst: &mut [DevByte],
+        value: T,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> MemsetNode<'_> {
+        self.try_add_memset(dst, value, deps).unwrap()
+    }
+
+    /// 用 `value` 填充 `dst`，填充的粒度由 `value` 的类型决定。
+    pub fn try_add_memset<'a, T: MemsetElem>(
+        &self,
+        dst: &mut [DevByte],
+        value: T,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<MemsetNode<'_>, Error> {
+        self.try_add_memset_node_with_params(
+            &CUDA_MEMSET_NODE_PARAMS {
+                dst: dst.as_mut_ptr() as _,
+                pitch: 0,
+                value: value.to_bits(),
+                elementSize: size_of::<T>() as _,
+                width: elements::<T>(dst)?,
+                height: 1,
+            },
+            deps,
+        )
+    }
+
+    #[inline]
+    #[track_caller]
     pub fn add_memset_node_with_params<'a>(
         &self,
         params: &CUDA_MEMSET_NODE_PARAMS,
         deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
-    ) -> MemsetNode {
+    ) -> MemsetNode<'_> {
+        self.try_add_memset_node_with_params(params, deps).unwrap()
+    }
+
+    pub fn try_add_memset_node_with_params<'a>(
+        &self,
+        params: &CUDA_MEMSET_NODE_PARAMS,
+        deps: impl IntoIterator<Item = &'a GraphNode<'a>>,
+    ) -> Result<MemsetNode<'_>, Error> {
         let deps = collect_dependencies(deps);
 
         let mut node = null_mut();
-        driver!(cuGraphAddMemsetNode(
+        try_driver!(cuGraphAddMemsetNode(
             &mut node,
             self.as_raw(),
             deps.as_ptr(),
             deps.len(),
             params,
             null_mut(),
-        ));
-        MemsetNode(node, PhantomData)
+        ))?;
+        Ok(MemsetNode(node, PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Device, Graph, GraphNode, memcpy_d2h};
+
+    #[test]
+    fn test_behavior() {
+        if let Err(crate::NoDevice) = crate::init() {
+            return;
+        }
+
+        Device::new(0).context().apply(|ctx| {
+            let mut mem = ctx.malloc::<u16>(256);
+
+            let graph = Graph::new();
+            let node = graph.add_memset(&mut mem, 0u8, &[]);
+            graph.add_memset(&mut mem[..128], 0x1234u16, &[GraphNode::Memset(node)]);
+            ctx.stream().launch_graph(&ctx.instantiate(&graph));
+
+            let mut host = [1u16; 256];
+            memcpy_d2h(&mut host, &mem);
+            assert!(host[..64].iter().all(|&x| x == 0x1234));
+            assert!(host[64..].iter().all(|&x| x == 0))
+        })
+    }
+
+    #[test]
+    fn test_capture() {
+        if let Err(crate::NoDevice) = crate::init() {
+            return;
+        }
+
+        Device::new(0).context().apply(|ctx| {
+            let mut mem = ctx.malloc::<u32>(64);
+
+            let stream = ctx.stream().capture();
+            stream.memset_d32(&mut mem, 7);
+            let graph = stream.end();
+            assert!(matches!(&*graph.nodes(), [GraphNode::Memset(_)]));
+
+            ctx.stream().launch_graph(&ctx.instantiate(&graph));
+            let mut host = [0u32; 64];
+            memcpy_d2h(&mut host, &mem);
+            assert!(host.iter().all(|&x| x == 7))
+        })
     }
 }
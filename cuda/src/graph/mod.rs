@@ -1,12 +1,16 @@
-﻿mod free;
+﻿#[cfg(cuda_12_3)]
+mod conditional;
+mod exec;
+mod free;
 mod host_fn;
+mod instantiate;
 mod kernel;
 mod malloc;
 mod memcpy;
 mod memset;
 
 use crate::{
-    CurrentCtx, Stream,
+    CurrentCtx, Error, Stream,
     bindings::{
         CUgraph, CUgraphExec, CUgraphNode,
         CUstreamCaptureMode::CU_STREAM_CAPTURE_MODE_THREAD_LOCAL as LOCAL,
@@ -15,6 +19,11 @@ use crate::{
 use context_spore::{AsRaw, impl_spore};
 use std::{ffi::CString, marker::PhantomData, ops::Deref, path::Path, ptr::null_mut, str::FromStr};
 
+#[cfg(cuda_12_3)]
+pub use conditional::{ConditionalHandle, ConditionalKind};
+pub use exec::{UpdateError, UpdateFailure};
+pub use instantiate::{InstantiateBuilder, InstantiateError, InstantiateFailure};
+
 #[repr(transparent)]
 pub struct Graph(CUgraph);
 
@@ -24,22 +33,41 @@ impl_spore!(GraphExec and GraphExecSpore by (CurrentCtx, CUgraphExec));
 pub struct CaptureStream<'ctx>(Stream<'ctx>);
 
 impl<'ctx> Stream<'ctx> {
+    #[inline]
+    #[track_caller]
     pub fn capture(self) -> CaptureStream<'ctx> {
-        driver!(cuStreamBeginCapture_v2(self.as_raw(), LOCAL));
-        CaptureStream(self)
+        self.try_capture().unwrap()
     }
 
+    #[inline]
+    #[track_caller]
     pub fn launch_graph(&self, graph: &GraphExec) -> &Self {
-        driver!(cuGraphLaunch(graph.0.rss, self.as_raw()));
-        self
+        self.try_launch_graph(graph).unwrap()
+    }
+
+    pub fn try_capture(self) -> Result<CaptureStream<'ctx>, Error> {
+        try_driver!(cuStreamBeginCapture_v2(self.as_raw(), LOCAL))?;
+        Ok(CaptureStream(self))
+    }
+
+    pub fn try_launch_graph(&self, graph: &GraphExec) -> Result<&Self, Error> {
+        try_driver!(cuGraphLaunch(graph.0.rss, self.as_raw()))?;
+        Ok(self)
     }
 }
 
 impl CaptureStream<'_> {
+    #[inline]
+    #[track_caller]
     pub fn end(self) -> Graph {
+        self.try_end().unwrap()
+    }
+
+    /// 结束捕获。捕获期间的非法操作会使捕获失效，在此处报告。
+    pub fn try_end(self) -> Result<Graph, Error> {
         let mut graph = null_mut();
-        driver!(cuStreamEndCapture(self.0.as_raw(), &mut graph));
-        Graph(graph)
+        try_driver!(cuStreamEndCapture(self.0.as_raw(), &mut graph))?;
+        Ok(Graph(graph))
     }
 }
 
@@ -52,10 +80,16 @@ impl<'ctx> Deref for CaptureStream<'ctx> {
 }
 
 impl Graph {
+    #[inline]
+    #[track_caller]
     pub fn new() -> Self {
+        Self::try_new().unwrap()
+    }
+
+    pub fn try_new() -> Result<Self, Error> {
         let mut graph = null_mut();
-        driver!(cuGraphCreate(&mut graph, 0));
-        Self(graph)
+        try_driver!(cuGraphCreate(&mut graph, 0))?;
+        Ok(Self(graph))
     }
 }
 
@@ -80,30 +114,48 @@ impl AsRaw for Graph {
 }
 
 impl Graph {
+    #[inline]
+    #[track_caller]
     pub fn save_dot(&self, path: impl AsRef<Path>) {
-        let path = CString::from_str(&path.as_ref().display().to_string()).unwrap();
-        driver!(cuGraphDebugDotPrint(self.0, path.as_ptr().cast(), u32::MAX))
+        self.try_save_dot(path).unwrap()
+    }
+
+    pub fn try_save_dot(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = CString::from_str(&path.as_ref().display().to_string())
+            .map_err(|_| invalid_value!("path contains no nul byte"))?;
+        try_driver!(cuGraphDebugDotPrint(self.0, path.as_ptr().cast(), u32::MAX))
     }
 
-    pub fn nodes(&self) -> Vec<GraphNode> {
+    #[inline]
+    #[track_caller]
+    pub fn nodes(&self) -> Vec<GraphNode<'_>> {
+        self.try_nodes().unwrap()
+    }
+
+    pub fn try_nodes(&self) -> Result<Vec<GraphNode<'_>>, Error> {
         let mut num = 0;
-        driver!(cuGraphGetNodes(self.0, null_mut(), &mut num));
+        try_driver!(cuGraphGetNodes(self.0, null_mut(), &mut num))?;
         let mut ans = vec![null_mut(); num];
-        driver!(cuGraphGetNodes(self.0, ans.as_mut_ptr(), &mut num));
+        try_driver!(cuGraphGetNodes(self.0, ans.as_mut_ptr(), &mut num))?;
         assert_eq!(num, ans.len());
-        ans.into_iter().map(GraphNode::new).collect()
+        ans.into_iter().map(GraphNode::try_new).collect()
     }
 }
 
 impl CurrentCtx {
+    #[inline]
+    #[track_caller]
     pub fn instantiate<'ctx>(&'ctx self, graph: &Graph) -> GraphExec<'ctx> {
-        let mut exec = null_mut();
-        driver!(cuGraphInstantiateWithFlags(
-            &mut exec,
-            graph.0,
-            CUgraphInstantiate_flags::CUDA_GRAPH_INSTANTIATE_FLAG_AUTO_FREE_ON_LAUNCH as _
-        ));
-        GraphExec(unsafe { self.wrap_raw(exec) }, PhantomData)
+        self.try_instantiate(graph).unwrap()
+    }
+
+    /// 以默认选项实例化图，见 [`CurrentCtx::instantiate_builder`]。
+    #[inline]
+    pub fn try_instantiate<'ctx, 'g>(
+        &'ctx self,
+        graph: &'g Graph,
+    ) -> Result<GraphExec<'ctx>, InstantiateError<'g>> {
+        self.instantiate_builder().try_instantiate(graph)
     }
 }
 
@@ -121,6 +173,7 @@ impl AsRaw for GraphExec<'_> {
     }
 }
 
+#[derive(Debug)]
 pub enum GraphNode<'g> {
     Kernel(KernelNode<'g>),
     MemAlloc(MemAllocNode<'g>),
@@ -134,12 +187,17 @@ pub enum GraphNode<'g> {
     EventRecord(EventRecordNode<'g>),
     ExtSemasSignal(ExtSemasSignalNode<'g>),
     ExtSemasWait(ExtSemasWaitNode<'g>),
+    /// 条件节点，需要 CUDA 12.3，更早的版本中作为 [`GraphNode::Other`]。
+    Conditional(ConditionalNode<'g>),
+    /// 尚未支持的节点类型。
+    Other(OtherNode<'g>),
 }
 
 macro_rules! typed_node {
     ($( $name:ident )+) => {
         $(
             #[repr(transparent)]
+            #[derive(Debug)]
             pub struct $name<'g>(CUgraphNode, PhantomData<&'g ()>);
 
             impl AsRaw for $name<'_> {
@@ -172,14 +230,22 @@ typed_node! {
     EventRecordNode
     ExtSemasSignalNode
     ExtSemasWaitNode
+    ConditionalNode
+    OtherNode
 }
 
 impl GraphNode<'_> {
+    #[inline]
+    #[track_caller]
     pub(super) fn new(raw: CUgraphNode) -> Self {
+        Self::try_new(raw).unwrap()
+    }
+
+    pub(super) fn try_new(raw: CUgraphNode) -> Result<Self, Error> {
         use crate::bindings::CUgraphNodeType as ty;
 
         let mut type_ = ty::CU_GRAPH_NODE_TYPE_EMPTY;
-        driver!(cuGraphNodeGetType(raw, &mut type_));
+        try_driver!(cuGraphNodeGetType(raw, &mut type_))?;
 
         #[rustfmt::skip]
         let ans = match type_ {
@@ -195,9 +261,11 @@ impl GraphNode<'_> {
             ty::CU_GRAPH_NODE_TYPE_EVENT_RECORD     => Self::EventRecord   (EventRecordNode   (raw, PhantomData)),
             ty::CU_GRAPH_NODE_TYPE_EXT_SEMAS_SIGNAL => Self::ExtSemasSignal(ExtSemasSignalNode(raw, PhantomData)),
             ty::CU_GRAPH_NODE_TYPE_EXT_SEMAS_WAIT   => Self::ExtSemasWait  (ExtSemasWaitNode  (raw, PhantomData)),
-            _ => todo!(),
+            #[cfg(cuda_12_3)]
+            ty::CU_GRAPH_NODE_TYPE_CONDITIONAL      => Self::Conditional   (ConditionalNode   (raw, PhantomData)),
+            _                                       => Self::Other         (OtherNode         (raw, PhantomData)),
         };
-        ans
+        Ok(ans)
     }
 }
 
@@ -226,6 +294,8 @@ impl AsRaw for GraphNode<'_> {
             EventRecord
             ExtSemasSignal
             ExtSemasWait
+            Conditional
+            Other
         }
     }
 }
@@ -266,6 +336,7 @@ mod test {
     }
 
     #[test]
+    #[cfg_attr(feature = "mock", ignore = "the mock driver cannot compile kernels")]
     fn test_save_dot() {
         const CODE: &str = r#"
 extern "C" __global__ void add(float *a, float const *b) {
@@ -338,6 +409,7 @@ extern "C" __global__ void mul(float *a, float const *b) {
     }
 
     #[test]
+    #[cfg_attr(feature = "mock", ignore = "the mock driver cannot compile kernels")]
     fn test_launch() {
         const CODE: &str =
             r#"extern "C" __global__ void print(int n) { printf("Hello, world(%d)!\n", n); }"#;
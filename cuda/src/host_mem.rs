@@ -1,7 +1,14 @@
-﻿use crate::{Blob, CurrentCtx};
+﻿use crate::{
+    Blob, CurrentCtx, Error,
+    bindings::{
+        CU_MEMHOSTALLOC_DEVICEMAP, CU_MEMHOSTALLOC_PORTABLE, CU_MEMHOSTALLOC_WRITECOMBINED,
+        CU_MEMHOSTREGISTER_DEVICEMAP, CU_MEMHOSTREGISTER_PORTABLE, CUdeviceptr,
+    },
+};
 use context_spore::{AsRaw, impl_spore};
 use std::{
     alloc::Layout,
+    ffi::c_uint,
     marker::PhantomData,
     ops::{Deref, DerefMut},
     os::raw::c_void,
@@ -12,11 +19,233 @@ use std::{
 impl_spore!(HostMem and HostMemSpore by (CurrentCtx, Blob<*mut c_void>));
 
 impl CurrentCtx {
-    pub fn malloc_host<T: Copy>(&self, len: usize) -> HostMem {
-        let len = Layout::array::<T>(len).unwrap().size();
+    #[inline]
+    #[track_caller]
+    pub fn malloc_host<T: Copy>(&self, len: usize) -> HostMem<'_> {
+        self.try_malloc_host::<T>(len).unwrap()
+    }
+
+    pub fn try_malloc_host<T: Copy>(&self, len: usize) -> Result<HostMem<'_>, Error> {
+        self.host_mem_builder().try_malloc::<T>(len)
+    }
+
+    #[inline]
+    pub fn host_mem_builder(&self) -> HostMemBuilder<'_> {
+        HostMemBuilder {
+            ctx: self,
+            portable: false,
+            device_map: false,
+            write_combined: false,
+        }
+    }
+}
+
+/// 按指定的标志分配锁页内存或将主机内存注册为锁页内存。
+pub struct HostMemBuilder<'ctx> {
+    ctx: &'ctx CurrentCtx,
+    portable: bool,
+    device_map: bool,
+    write_combined: bool,
+}
+
+impl<'ctx> HostMemBuilder<'ctx> {
+    /// 锁页内存对所有上下文都是锁页的，而不仅是当前上下文。
+    #[inline]
+    pub fn portable(mut self, value: bool) -> Self {
+        self.portable = value;
+        self
+    }
+
+    /// 将锁页内存映射到设备地址空间，kernel 可以通过 [`HostMem::device_ptr`] 直接访问。
+    #[inline]
+    pub fn device_map(mut self, value: bool) -> Self {
+        self.device_map = value;
+        self
+    }
+
+    /// 分配写合并的内存，从主机写入和向设备拷贝更快，从主机读取很慢。不能用于注册。
+    #[inline]
+    pub fn write_combined(mut self, value: bool) -> Self {
+        self.write_combined = value;
+        self
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn malloc<T: Copy>(self, len: usize) -> HostMem<'ctx> {
+        self.try_malloc::<T>(len).unwrap()
+    }
+
+    pub fn try_malloc<T: Copy>(self, len: usize) -> Result<HostMem<'ctx>, Error> {
+        let len = Layout::array::<T>(len)
+            .map_err(|_| invalid_value!("len * size_of::<T>() fits in isize"))?
+            .size();
+        let mut flags = 0;
+        for (value, flag) in [
+            (self.portable, CU_MEMHOSTALLOC_PORTABLE),
+            (self.device_map, CU_MEMHOSTALLOC_DEVICEMAP),
+            (self.write_combined, CU_MEMHOSTALLOC_WRITECOMBINED),
+        ] {
+            if value {
+                flags |= flag as c_uint
+            }
+        }
         let mut ptr = null_mut();
-        driver!(cuMemHostAlloc(&mut ptr, len, 0));
-        HostMem(unsafe { self.wrap_raw(Blob { ptr, len }) }, PhantomData)
+        try_driver!(cuMemHostAlloc(&mut ptr, len, flags))?;
+        Ok(HostMem(
+            unsafe { self.ctx.wrap_raw(Blob { ptr, len }) },
+            PhantomData,
+        ))
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn lock_page<T>(self, slice: &[T]) {
+        self.try_lock_page(slice).unwrap()
+    }
+
+    /// 将一段主机内存注册为锁页内存，以 [`CurrentCtx::unlock_page`] 注销。
+    pub fn try_lock_page<T>(self, slice: &[T]) -> Result<(), Error> {
+        register(slice, self.register_flags()?)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn lock_slice<'a, T>(self, slice: &'a mut [T]) -> LockedSlice<'a, T>
+    where
+        'ctx: 'a,
+    {
+        self.try_lock_slice(slice).unwrap()
+    }
+
+    /// 将一段主机内存注册为锁页内存，返回的守卫释放时注销。
+    pub fn try_lock_slice<'a, T>(self, slice: &'a mut [T]) -> Result<LockedSlice<'a, T>, Error>
+    where
+        'ctx: 'a,
+    {
+        let flags = self.register_flags()?;
+        register(slice, flags)?;
+        Ok(LockedSlice {
+            ctx: self.ctx,
+            slice,
+            flags,
+        })
+    }
+
+    fn register_flags(&self) -> Result<c_uint, Error> {
+        if self.write_combined {
+            return Err(invalid_value!("registered memory cannot be write-combined"));
+        }
+        let mut flags = 0;
+        for (value, flag) in [
+            (self.portable, CU_MEMHOSTREGISTER_PORTABLE),
+            (self.device_map, CU_MEMHOSTREGISTER_DEVICEMAP),
+        ] {
+            if value {
+                flags |= flag as c_uint
+            }
+        }
+        Ok(flags)
+    }
+}
+
+fn register<T>(slice: &[T], flags: c_uint) -> Result<(), Error> {
+    let ptrs = slice.as_ptr_range();
+    try_driver!(cuMemHostRegister_v2(
+        ptrs.start as _,
+        ptrs.end as usize - ptrs.start as usize,
+        flags,
+    ))
+}
+
+/// 注册为锁页内存的主机切片，释放时注销。
+///
+/// 守卫可变借用切片，注册期间切片不会被释放或重新分配。
+/// 可以传给流上的异步拷贝，释放时先同步注册所在的上下文，确保拷贝完成后才注销。
+/// 同步会等待上下文中所有流上的任务，而不只是使用切片的流。
+/// 释放时的驱动错误只记录日志，同步失败时仍然注销。
+///
+/// 守卫不阻止在异步拷贝完成前读写切片，修改切片前需要同步拷贝所在的流。
+pub struct LockedSlice<'a, T> {
+    ctx: &'a CurrentCtx,
+    slice: &'a mut [T],
+    flags: c_uint,
+}
+
+impl<T> LockedSlice<'_, T> {
+    /// 注册时使用的 `CU_MEMHOSTREGISTER_*` 标志。
+    #[inline]
+    pub const fn flags(&self) -> c_uint {
+        self.flags
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn device_ptr(&self) -> CUdeviceptr {
+        self.try_device_ptr().unwrap()
+    }
+
+    /// 切片在设备地址空间中的地址，切片须以 [`HostMemBuilder::device_map`] 注册。
+    pub fn try_device_ptr(&self) -> Result<CUdeviceptr, Error> {
+        let mut ptr = 0;
+        try_driver!(cuMemHostGetDevicePointer_v2(
+            &mut ptr,
+            self.slice.as_ptr().cast_mut().cast(),
+            0
+        ))?;
+        Ok(ptr)
+    }
+}
+
+impl<T> Drop for LockedSlice<'_, T> {
+    fn drop(&mut self) {
+        // 当前上下文可能已经改变，在注册所在的上下文上同步
+        match try_driver!(cuCtxPushCurrent_v2(self.ctx.as_raw())) {
+            Ok(()) => {
+                if let Err(e) = try_driver!(cuCtxSynchronize()) {
+                    log::warn!("{e}")
+                }
+                if let Err(e) = try_driver!(cuCtxPopCurrent_v2(null_mut())) {
+                    log::warn!("{e}")
+                }
+            }
+            Err(e) => log::warn!("{e}"),
+        }
+        if let Err(e) = try_driver!(cuMemHostUnregister(self.slice.as_mut_ptr().cast())) {
+            log::warn!("{e}")
+        }
+    }
+}
+
+impl<T> Deref for LockedSlice<'_, T> {
+    type Target = [T];
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.slice
+    }
+}
+
+impl<T> DerefMut for LockedSlice<'_, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.slice
+    }
+}
+
+impl HostMem<'_> {
+    #[inline]
+    #[track_caller]
+    pub fn device_ptr(&self) -> CUdeviceptr {
+        self.try_device_ptr().unwrap()
+    }
+
+    /// 锁页内存在设备地址空间中的地址，用于 kernel 直接访问主机内存。
+    ///
+    /// 内存须以 [`HostMemBuilder::device_map`] 分配，支持统一地址的设备上与主机地址相同。
+    pub fn try_device_ptr(&self) -> Result<CUdeviceptr, Error> {
+        let mut ptr = 0;
+        try_driver!(cuMemHostGetDevicePointer_v2(&mut ptr, self.0.rss.ptr, 0))?;
+        Ok(ptr)
     }
 }
 
@@ -85,6 +314,86 @@ fn test_behavior() {
 }
 
 #[test]
+fn test_flags() {
+    use crate::{DevByte, memcpy_d2d, memcpy_d2h};
+
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    crate::Device::new(0).context().apply(|ctx| {
+        let mut host = ctx
+            .host_mem_builder()
+            .portable(true)
+            .device_map(true)
+            .malloc::<u8>(256);
+        for (i, x) in host.iter_mut().enumerate() {
+            *x = i as _
+        }
+        // 通过设备地址访问主机内存
+        let mapped = unsafe { from_raw_parts(host.device_ptr() as *const DevByte, host.len()) };
+        let mut dev = ctx.malloc::<u8>(host.len());
+        memcpy_d2d(&mut dev, mapped);
+        let mut host_ = vec![0u8; host.len()];
+        memcpy_d2h(&mut host_, &dev);
+        assert_eq!(&*host, &*host_);
+
+        let _ = ctx
+            .host_mem_builder()
+            .write_combined(true)
+            .malloc::<u8>(256);
+
+        let pageable = vec![0u8; 256];
+        let e = ctx
+            .host_mem_builder()
+            .write_combined(true)
+            .try_lock_page(&pageable)
+            .unwrap_err();
+        assert_eq!(
+            e.code(),
+            crate::bindings::CUresult::CUDA_ERROR_INVALID_VALUE
+        );
+        ctx.host_mem_builder()
+            .portable(true)
+            .device_map(true)
+            .lock_page(&pageable);
+        ctx.unlock_page(&pageable)
+    })
+}
+
+#[test]
+fn test_locked_slice() {
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    crate::Device::new(0).context().apply(|ctx| {
+        let mut host = (0..1024u32).collect::<Vec<_>>();
+        let mut dev = ctx.malloc::<u32>(host.len());
+        {
+            let mut locked = ctx
+                .host_mem_builder()
+                .device_map(true)
+                .lock_slice(&mut host);
+            assert_eq!(locked.flags(), CU_MEMHOSTREGISTER_DEVICEMAP as c_uint);
+            assert_eq!(locked.device_ptr(), locked.as_ptr() as CUdeviceptr);
+
+            let stream = ctx.stream();
+            stream.memcpy_h2d(&mut dev, &locked).synchronize();
+            locked.fill(0);
+            // 守卫释放前同步，拷贝完成后才注销
+            stream.memcpy_d2h(&mut locked, &dev);
+        }
+        assert_eq!(host, (0..1024).collect::<Vec<_>>());
+        // 已经注销，可以再次注册
+        ctx.lock_page(&host);
+        ctx.unlock_page(&host)
+    })
+}
+
+#[test]
+#[cfg_attr(
+    feature = "mock",
+    ignore = "benchmark is meaningless on the mock driver"
+)]
 fn bench() {
     use rand::Rng;
     use std::time::{Duration, Instant};
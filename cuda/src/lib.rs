@@ -4,7 +4,10 @@
 #[macro_use]
 #[allow(unused, non_upper_case_globals, non_camel_case_types, non_snake_case)]
 pub mod bindings {
+    #[cfg(not(feature = "mock"))]
     include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+    #[cfg(feature = "mock")]
+    pub use crate::mock::*;
 
     #[macro_export]
     macro_rules! driver {
@@ -16,7 +19,36 @@ pub mod bindings {
             assert_eq!(err, CUresult::$expected);
         }};
 
-        ($f:expr) => {{ driver!(CUDA_SUCCESS, $f) }};
+        ($f:expr) => {{ $crate::try_driver!($f).unwrap() }};
+    }
+
+    #[macro_export]
+    macro_rules! try_driver {
+        ($f:expr) => {{
+            #[allow(unused_imports)]
+            use $crate::bindings::*;
+            #[allow(unused_unsafe, clippy::macro_metavars_in_unsafe)]
+            let err = unsafe { $f };
+            $crate::Error::check(
+                err,
+                $crate::CallSite {
+                    expr: stringify!($f),
+                    file: file!(),
+                    line: line!(),
+                },
+            )
+        }};
+    }
+
+    /// 构造调用驱动前发现参数非法的错误，`$what` 描述不满足的条件。
+    macro_rules! invalid_value {
+        ($what:expr) => {
+            $crate::Error::invalid_value($crate::CallSite {
+                expr: $what,
+                file: file!(),
+                line: line!(),
+            })
+        };
     }
 
     #[macro_export]
@@ -31,14 +63,33 @@ pub mod bindings {
     }
 }
 
+mod completion;
 mod context;
+mod dev_buf;
 mod dev_mem;
 mod device;
+mod error;
 mod event;
 mod graph;
 mod host_mem;
+#[cfg(all(feature = "dynamic", not(feature = "mock")))]
+mod library;
+mod managed_mem;
+#[cfg(nvidia)]
+mod mem_pool;
+mod memset;
+#[cfg(feature = "mock")]
+#[allow(non_camel_case_types, non_upper_case_globals, non_snake_case)]
+#[allow(clippy::missing_safety_doc, clippy::too_many_arguments)]
+mod mock;
 mod nvrtc;
+mod paged;
+mod peer;
+#[cfg(unix)]
+mod share;
 mod stream;
+mod strided;
+mod vir_vec;
 mod virtual_mem;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
@@ -46,6 +97,11 @@ pub struct NoDevice;
 
 pub fn init() -> Result<(), NoDevice> {
     use bindings::{CUresult::*, cuInit};
+    #[cfg(all(feature = "dynamic", not(feature = "mock")))]
+    if let Err(e) = load_driver() {
+        log::warn!("{e}");
+        return Err(NoDevice);
+    }
     match unsafe { cuInit(0) } {
         CUDA_SUCCESS => Ok(()),
         CUDA_ERROR_NO_DEVICE => Err(NoDevice),
@@ -53,25 +109,48 @@ pub fn init() -> Result<(), NoDevice> {
     }
 }
 
+#[inline]
+#[track_caller]
 pub fn version() -> Version {
+    try_version().unwrap()
+}
+
+pub fn try_version() -> Result<Version, Error> {
     let mut version = 0;
-    driver!(cuDriverGetVersion(&mut version));
-    Version {
+    try_driver!(cuDriverGetVersion(&mut version))?;
+    Ok(Version {
         major: version / 1000,
         minor: version % 1000 / 10,
-    }
+    })
 }
 
+pub use completion::Completion;
 pub use context::{Context, CurrentCtx};
 pub use context_spore::{AsRaw, ContextResource, ContextSpore, RawContainer, impl_spore};
-pub use dev_mem::{DevByte, DevMem, DevMemSpore, memcpy_d2d, memcpy_d2h, memcpy_h2d};
+pub use dev_buf::{DevBuf, DevSlice};
+pub use dev_mem::{
+    DevByte, DevMem, DevMemSpore, memcpy_d2d, memcpy_d2h, memcpy_h2d, try_memcpy_d2d,
+    try_memcpy_d2h, try_memcpy_h2d,
+};
 pub use device::{BlockLimit, Device, SMLimit};
-pub use event::{Event, EventSpore};
+pub use error::{CallSite, Error};
+pub use event::{Event, EventBuilder, EventSpore, IpcEventHandle};
 pub use graph::*;
-pub use host_mem::{HostMem, HostMemSpore};
+pub use host_mem::{HostMem, HostMemBuilder, HostMemSpore, LockedSlice};
+#[cfg(all(feature = "dynamic", not(feature = "mock")))]
+pub use library::{LoadError, load_driver, load_nvrtc, set_library_path};
+pub use managed_mem::{ManagedMem, ManagedMemSpore, MemAdvice, mem_advise, try_mem_advise};
+#[cfg(nvidia)]
+pub use mem_pool::{ImportedMem, MemPool, MemPoolBuilder, MemPoolReuse, PoolPtrHandle};
+pub use memset::MemsetElem;
 pub use nvrtc::{KernelFn, KernelParamPtrs, KernelParams, Module, ModuleSpore, Ptx, Symbol};
-pub use stream::{Stream, StreamSpore};
-pub use virtual_mem::{MemProp, PhyMem, VirByte, VirMem};
+pub use paged::{CopyOnWrite, PageStats, PageTable, PagedMem, SeqId};
+#[cfg(unix)]
+pub use share::{recv_fd, send_fd};
+pub use stream::{Stream, StreamBuilder, StreamSpore};
+pub use strided::{StridedView, StridedViewMut};
+pub use vir_vec::VirVec;
+pub use virtual_mem::{MapError, MemProp, PhyMem, VirByte, VirMem, VirRegion};
 
 use std::{
     cmp::Ordering,
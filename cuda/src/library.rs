@@ -0,0 +1,126 @@
+//! 运行时加载驱动和 NVRTC 动态库。
+//!
+//! 启用 `dynamic` 特性时，驱动和 NVRTC 的符号不在链接时绑定，而是在首次使用时从动态库中解析，
+//! 因此同一个可执行文件可以在没有安装驱动的机器上启动，并由 [`init`](crate::init) 报告错误。
+//!
+//! 动态库按以下顺序查找：
+//!
+//! 1. [`set_library_path`] 设置的目录；
+//! 2. 环境变量 `CUDA_DRIVER_PATH` 中的目录，以平台的路径分隔符分隔；
+//! 3. 系统动态链接器的默认搜索路径。
+
+use crate::bindings::{DriverApi, NvrtcApi};
+use libloading::Library;
+use std::{
+    env, fmt,
+    path::PathBuf,
+    sync::{Mutex, OnceLock},
+};
+
+struct Loaded<T> {
+    api: T,
+    _lib: Library,
+}
+
+static DRIVER: OnceLock<Loaded<DriverApi>> = OnceLock::new();
+static NVRTC: OnceLock<Loaded<NvrtcApi>> = OnceLock::new();
+static SEARCH_PATH: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+#[cfg(unix)]
+const DRIVER_NAMES: &[&str] = &["libcuda.so.1", "libcuda.so"];
+#[cfg(windows)]
+const DRIVER_NAMES: &[&str] = &["nvcuda.dll"];
+
+#[cfg(unix)]
+const NVRTC_NAMES: &[&str] = &["libnvrtc.so", "libnvrtc.so.12", "libnvrtc.so.11.2"];
+#[cfg(windows)]
+const NVRTC_NAMES: &[&str] = &["nvrtc64_120_0.dll", "nvrtc64_112_0.dll"];
+
+/// 设置查找动态库的目录，优先于环境变量和系统路径。
+///
+/// 只影响之后的加载，已加载的动态库不会被替换。
+pub fn set_library_path(dirs: impl IntoIterator<Item = impl Into<PathBuf>>) {
+    *SEARCH_PATH.lock().unwrap() = dirs.into_iter().map(Into::into).collect()
+}
+
+/// 加载驱动动态库。已加载时直接返回。
+pub fn load_driver() -> Result<(), LoadError> {
+    load(&DRIVER, DRIVER_NAMES, |lib| unsafe { DriverApi::load(lib) }).map(|_| ())
+}
+
+/// 加载 NVRTC 动态库。已加载时直接返回。
+pub fn load_nvrtc() -> Result<(), LoadError> {
+    load(&NVRTC, NVRTC_NAMES, |lib| unsafe { NvrtcApi::load(lib) }).map(|_| ())
+}
+
+pub(crate) fn driver() -> &'static DriverApi {
+    load(&DRIVER, DRIVER_NAMES, |lib| unsafe { DriverApi::load(lib) })
+        .unwrap_or_else(|e| panic!("{e}"))
+}
+
+pub(crate) fn nvrtc() -> &'static NvrtcApi {
+    load(&NVRTC, NVRTC_NAMES, |lib| unsafe { NvrtcApi::load(lib) })
+        .unwrap_or_else(|e| panic!("{e}"))
+}
+
+fn load<T>(
+    cell: &'static OnceLock<Loaded<T>>,
+    names: &'static [&'static str],
+    api: impl FnOnce(&Library) -> T,
+) -> Result<&'static T, LoadError> {
+    if let Some(loaded) = cell.get() {
+        return Ok(&loaded.api);
+    }
+
+    let mut dirs = SEARCH_PATH.lock().unwrap().clone();
+    if let Some(paths) = env::var_os("CUDA_DRIVER_PATH") {
+        dirs.extend(env::split_paths(&paths))
+    }
+    let candidates = dirs
+        .iter()
+        .flat_map(|dir| names.iter().map(|name| dir.join(name)))
+        .chain(names.iter().map(PathBuf::from));
+
+    let mut errors = Vec::new();
+    for path in candidates {
+        match unsafe { Library::new(&path) } {
+            Ok(lib) => {
+                let api = api(&lib);
+                // 并发加载时只保留先完成的一份
+                let _ = cell.set(Loaded { api, _lib: lib });
+                return Ok(&cell.get().unwrap().api);
+            }
+            Err(e) => errors.push((path, e)),
+        }
+    }
+    Err(LoadError { names, errors })
+}
+
+/// 未能找到或打开动态库。
+#[derive(Debug)]
+pub struct LoadError {
+    names: &'static [&'static str],
+    errors: Vec<(PathBuf, libloading::Error)>,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed to load {}", self.names.join(" or "))?;
+        for (path, e) in &self.errors {
+            write!(f, "\n  {}: {e}", path.display())?
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+#[test]
+fn test_not_found() {
+    static NOTHING: OnceLock<Loaded<()>> = OnceLock::new();
+    let e = load(&NOTHING, &["libnot-a-cuda-library.so"], |_| ())
+        .err()
+        .unwrap();
+    assert!(!e.errors.is_empty());
+    println!("{e}")
+}
@@ -0,0 +1,205 @@
+use crate::{
+    Blob, CurrentCtx, DevByte, Device, Error, Stream,
+    bindings::{CU_DEVICE_CPU, CUdevice, CUdeviceptr, CUmem_advise},
+};
+use context_spore::{AsRaw, impl_spore};
+use std::{
+    alloc::Layout,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+    slice::{from_raw_parts, from_raw_parts_mut},
+};
+
+impl_spore!(ManagedMem and ManagedMemSpore by (CurrentCtx, Blob<CUdeviceptr>));
+
+impl CurrentCtx {
+    #[inline]
+    #[track_caller]
+    pub fn malloc_managed<T: Copy>(&self, len: usize) -> ManagedMem<'_> {
+        self.try_malloc_managed::<T>(len).unwrap()
+    }
+
+    /// 分配统一内存，主机和所有设备都能以相同的地址访问，可以超过设备的存储容量。
+    pub fn try_malloc_managed<T: Copy>(&self, len: usize) -> Result<ManagedMem<'_>, Error> {
+        let len = Layout::array::<T>(len)
+            .map_err(|_| invalid_value!("len * size_of::<T>() fits in isize"))?
+            .size();
+        let mut ptr = 0;
+        try_driver!(cuMemAllocManaged(
+            &mut ptr,
+            len,
+            CUmemAttach_flags::CU_MEM_ATTACH_GLOBAL as _
+        ))?;
+        Ok(ManagedMem(
+            unsafe { self.wrap_raw(Blob { ptr, len }) },
+            PhantomData,
+        ))
+    }
+}
+
+impl Drop for ManagedMem<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        driver!(cuMemFree_v2(self.0.rss.ptr))
+    }
+}
+
+impl AsRaw for ManagedMem<'_> {
+    type Raw = CUdeviceptr;
+    #[inline]
+    unsafe fn as_raw(&self) -> Self::Raw {
+        self.0.rss.ptr
+    }
+}
+
+impl ManagedMem<'_> {
+    /// 以设备存储的形式访问，用于拷贝和 kernel 参数。
+    #[inline]
+    pub fn as_dev(&self) -> &[DevByte] {
+        unsafe { from_raw_parts(self.0.rss.ptr as _, self.0.rss.len) }
+    }
+
+    #[inline]
+    pub fn as_dev_mut(&mut self) -> &mut [DevByte] {
+        unsafe { from_raw_parts_mut(self.0.rss.ptr as _, self.0.rss.len) }
+    }
+}
+
+/// 在主机上访问统一内存，访问前需要同步使用它的流。
+impl Deref for ManagedMem<'_> {
+    type Target = [u8];
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe { from_raw_parts(self.0.rss.ptr as _, self.0.rss.len) }
+    }
+}
+
+impl DerefMut for ManagedMem<'_> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { from_raw_parts_mut(self.0.rss.ptr as _, self.0.rss.len) }
+    }
+}
+
+impl AsRaw for ManagedMemSpore {
+    type Raw = CUdeviceptr;
+    #[inline]
+    unsafe fn as_raw(&self) -> Self::Raw {
+        self.0.rss.ptr
+    }
+}
+
+impl Deref for ManagedMemSpore {
+    type Target = [u8];
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe { from_raw_parts(self.0.rss.ptr as _, self.0.rss.len) }
+    }
+}
+
+impl DerefMut for ManagedMemSpore {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { from_raw_parts_mut(self.0.rss.ptr as _, self.0.rss.len) }
+    }
+}
+
+/// 统一内存的使用建议，设备为 `None` 时表示主机。
+#[derive(Clone, Copy)]
+pub enum MemAdvice<'a> {
+    /// 数据主要被读取，访问的处理器可以保留只读副本。
+    SetReadMostly,
+    UnsetReadMostly,
+    /// 数据优先存放的位置。
+    SetPreferredLocation(Option<&'a Device>),
+    UnsetPreferredLocation,
+    /// 数据会被设备访问，尽量保持映射以避免缺页。
+    SetAccessedBy(Option<&'a Device>),
+    UnsetAccessedBy(Option<&'a Device>),
+}
+
+fn device_or_cpu(dev: Option<&Device>) -> CUdevice {
+    dev.map_or(CU_DEVICE_CPU as _, |dev| unsafe { dev.as_raw() })
+}
+
+#[inline]
+#[track_caller]
+pub fn mem_advise(mem: &[u8], advice: MemAdvice) {
+    try_mem_advise(mem, advice).unwrap()
+}
+
+/// 建议驱动如何放置统一内存中的一段数据，`mem` 必须位于 [`ManagedMem`] 中。
+pub fn try_mem_advise(mem: &[u8], advice: MemAdvice) -> Result<(), Error> {
+    use CUmem_advise::*;
+    let (advice, dev) = match advice {
+        MemAdvice::SetReadMostly => (CU_MEM_ADVISE_SET_READ_MOSTLY, None),
+        MemAdvice::UnsetReadMostly => (CU_MEM_ADVISE_UNSET_READ_MOSTLY, None),
+        MemAdvice::SetPreferredLocation(dev) => (CU_MEM_ADVISE_SET_PREFERRED_LOCATION, dev),
+        MemAdvice::UnsetPreferredLocation => (CU_MEM_ADVISE_UNSET_PREFERRED_LOCATION, None),
+        MemAdvice::SetAccessedBy(dev) => (CU_MEM_ADVISE_SET_ACCESSED_BY, dev),
+        MemAdvice::UnsetAccessedBy(dev) => (CU_MEM_ADVISE_UNSET_ACCESSED_BY, dev),
+    };
+    try_driver!(cuMemAdvise(
+        mem.as_ptr() as _,
+        mem.len(),
+        advice,
+        device_or_cpu(dev)
+    ))
+}
+
+impl Stream<'_> {
+    #[inline]
+    #[track_caller]
+    pub fn prefetch(&self, mem: &[u8], dst: Option<&Device>) -> &Self {
+        self.try_prefetch(mem, dst).unwrap()
+    }
+
+    /// 将统一内存中的一段数据预取到设备 `dst`，`None` 表示预取到主机。
+    pub fn try_prefetch(&self, mem: &[u8], dst: Option<&Device>) -> Result<&Self, Error> {
+        try_driver!(cuMemPrefetchAsync(
+            mem.as_ptr() as _,
+            mem.len(),
+            device_or_cpu(dst),
+            self.as_raw()
+        ))?;
+        Ok(self)
+    }
+}
+
+#[test]
+fn test_behavior() {
+    use crate::{ContextResource, ContextSpore, memcpy_d2h};
+
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    let dev = Device::new(0);
+    dev.context().apply(|ctx| {
+        let mut mem = ctx.malloc_managed::<u32>(1 << 10);
+        // 在主机上直接写入
+        for (i, x) in mem.chunks_exact_mut(4).enumerate() {
+            x.copy_from_slice(&(i as u32).to_ne_bytes())
+        }
+        mem_advise(&mem, MemAdvice::SetReadMostly);
+        mem_advise(&mem, MemAdvice::SetPreferredLocation(Some(&dev)));
+        mem_advise(&mem[..1024], MemAdvice::SetAccessedBy(None));
+
+        let stream = ctx.stream();
+        stream.prefetch(&mem, Some(&dev));
+        // 以设备存储的形式拷贝
+        let mut dev_mem = ctx.malloc::<u32>(1 << 10);
+        stream.memcpy_d2d(&mut dev_mem, mem.as_dev());
+        stream.prefetch(&mem, None).synchronize();
+
+        let mut host = vec![0u32; 1 << 10];
+        memcpy_d2h(&mut host, &dev_mem);
+        assert_eq!(host, (0..1 << 10).collect::<Vec<_>>());
+
+        // 普通的主机内存不是统一内存
+        assert!(try_mem_advise(&[0u8; 16], MemAdvice::SetReadMostly).is_err());
+
+        let spore = mem.sporulate();
+        assert_eq!(spore[4], 1);
+        drop(spore.sprout(ctx))
+    })
+}
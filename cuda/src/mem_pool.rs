@@ -0,0 +1,595 @@
+use crate::{
+    CurrentCtx, DevByte, DevMem, Device, Error,
+    bindings::{
+        CUdeviceptr, CUmemAccess_flags, CUmemAccessDesc, CUmemAllocationHandleType,
+        CUmemAllocationType, CUmemLocation, CUmemLocationType, CUmemPool_attribute, CUmemPoolProps,
+        CUmemPoolPtrExportData, CUmemoryPool,
+    },
+};
+use context_spore::AsRaw;
+use std::{
+    marker::PhantomData,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+    ptr::null_mut,
+    slice::{from_raw_parts, from_raw_parts_mut},
+};
+
+/// 流序分配的存储池。
+pub struct MemPool {
+    raw: CUmemoryPool,
+    /// 设备的默认存储池由驱动管理，不能销毁。
+    owned: bool,
+}
+
+unsafe impl Send for MemPool {}
+unsafe impl Sync for MemPool {}
+
+impl Device {
+    #[inline]
+    #[track_caller]
+    pub fn default_mem_pool(&self) -> MemPool {
+        self.try_default_mem_pool().unwrap()
+    }
+
+    /// 设备的默认存储池，`Stream::malloc` 从中分配。
+    pub fn try_default_mem_pool(&self) -> Result<MemPool, Error> {
+        let mut raw = null_mut();
+        try_driver!(cuDeviceGetDefaultMemPool(&mut raw, self.as_raw()))?;
+        Ok(MemPool { raw, owned: false })
+    }
+
+    #[inline]
+    pub fn mem_pool_builder(&self) -> MemPoolBuilder {
+        MemPoolBuilder(CUmemPoolProps {
+            allocType: CUmemAllocationType::CU_MEM_ALLOCATION_TYPE_PINNED,
+            handleTypes: CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_NONE,
+            location: location(self),
+            ..unsafe { std::mem::zeroed() }
+        })
+    }
+}
+
+#[inline]
+fn location(dev: &Device) -> CUmemLocation {
+    CUmemLocation {
+        type_: CUmemLocationType::CU_MEM_LOCATION_TYPE_DEVICE,
+        id: unsafe { dev.as_raw() },
+    }
+}
+
+/// 按指定的属性创建存储池。
+pub struct MemPoolBuilder(CUmemPoolProps);
+
+impl MemPoolBuilder {
+    /// 存储池最多保留的字节数，0 表示使用默认值。
+    #[inline]
+    pub fn max_size(mut self, size: usize) -> Self {
+        self.0.maxSize = size;
+        self
+    }
+
+    /// 存储池可以通过 POSIX 文件描述符导出到其他进程。
+    #[cfg(unix)]
+    #[inline]
+    pub fn shareable(mut self, value: bool) -> Self {
+        self.0.handleTypes = if value {
+            CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_POSIX_FILE_DESCRIPTOR
+        } else {
+            CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_NONE
+        };
+        self
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn build(self) -> MemPool {
+        self.try_build().unwrap()
+    }
+
+    pub fn try_build(self) -> Result<MemPool, Error> {
+        let mut raw = null_mut();
+        try_driver!(cuMemPoolCreate(&mut raw, &self.0))?;
+        Ok(MemPool { raw, owned: true })
+    }
+}
+
+/// 存储池复用已释放存储的策略。
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MemPoolReuse {
+    /// 复用释放操作已经被等待的存储。
+    FollowEventDependencies,
+    /// 复用释放操作已经完成的存储。
+    AllowOpportunistic,
+    /// 必要时插入依赖以复用存储。
+    AllowInternalDependencies,
+}
+
+impl Drop for MemPool {
+    #[inline]
+    fn drop(&mut self) {
+        // 池中仍有未释放的存储时，驱动在它们释放后销毁存储池
+        if self.owned {
+            driver!(cuMemPoolDestroy(self.raw))
+        }
+    }
+}
+
+impl AsRaw for MemPool {
+    type Raw = CUmemoryPool;
+    #[inline]
+    unsafe fn as_raw(&self) -> Self::Raw {
+        self.raw
+    }
+}
+
+impl MemPool {
+    fn try_get(&self, attr: CUmemPool_attribute) -> Result<u64, Error> {
+        use CUmemPool_attribute::*;
+        match attr {
+            CU_MEMPOOL_ATTR_REUSE_FOLLOW_EVENT_DEPENDENCIES
+            | CU_MEMPOOL_ATTR_REUSE_ALLOW_OPPORTUNISTIC
+            | CU_MEMPOOL_ATTR_REUSE_ALLOW_INTERNAL_DEPENDENCIES => {
+                let mut value = 0i32;
+                try_driver!(cuMemPoolGetAttribute(
+                    self.raw,
+                    attr,
+                    (&raw mut value).cast()
+                ))?;
+                Ok(value as _)
+            }
+            _ => {
+                let mut value = 0u64;
+                try_driver!(cuMemPoolGetAttribute(
+                    self.raw,
+                    attr,
+                    (&raw mut value).cast()
+                ))?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn try_set(&self, attr: CUmemPool_attribute, value: u64) -> Result<(), Error> {
+        use CUmemPool_attribute::*;
+        match attr {
+            CU_MEMPOOL_ATTR_REUSE_FOLLOW_EVENT_DEPENDENCIES
+            | CU_MEMPOOL_ATTR_REUSE_ALLOW_OPPORTUNISTIC
+            | CU_MEMPOOL_ATTR_REUSE_ALLOW_INTERNAL_DEPENDENCIES => {
+                let mut value = value as i32;
+                try_driver!(cuMemPoolSetAttribute(
+                    self.raw,
+                    attr,
+                    (&raw mut value).cast()
+                ))
+            }
+            _ => {
+                let mut value = value;
+                try_driver!(cuMemPoolSetAttribute(
+                    self.raw,
+                    attr,
+                    (&raw mut value).cast()
+                ))
+            }
+        }
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn release_threshold(&self) -> u64 {
+        self.try_release_threshold().unwrap()
+    }
+
+    /// 同步时存储池保留的字节数，超出部分归还系统。
+    #[inline]
+    pub fn try_release_threshold(&self) -> Result<u64, Error> {
+        self.try_get(CUmemPool_attribute::CU_MEMPOOL_ATTR_RELEASE_THRESHOLD)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn set_release_threshold(&self, threshold: u64) {
+        self.try_set_release_threshold(threshold).unwrap()
+    }
+
+    #[inline]
+    pub fn try_set_release_threshold(&self, threshold: u64) -> Result<(), Error> {
+        self.try_set(
+            CUmemPool_attribute::CU_MEMPOOL_ATTR_RELEASE_THRESHOLD,
+            threshold,
+        )
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn reuse(&self, policy: MemPoolReuse) -> bool {
+        self.try_reuse(policy).unwrap()
+    }
+
+    #[inline]
+    pub fn try_reuse(&self, policy: MemPoolReuse) -> Result<bool, Error> {
+        self.try_get(reuse_attr(policy)).map(|value| value != 0)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn set_reuse(&self, policy: MemPoolReuse, value: bool) {
+        self.try_set_reuse(policy, value).unwrap()
+    }
+
+    #[inline]
+    pub fn try_set_reuse(&self, policy: MemPoolReuse, value: bool) -> Result<(), Error> {
+        self.try_set(reuse_attr(policy), value as _)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn reserved_mem_current(&self) -> u64 {
+        self.try_reserved_mem_current().unwrap()
+    }
+
+    /// 存储池从系统申请的字节数。
+    #[inline]
+    pub fn try_reserved_mem_current(&self) -> Result<u64, Error> {
+        self.try_get(CUmemPool_attribute::CU_MEMPOOL_ATTR_RESERVED_MEM_CURRENT)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn reserved_mem_high(&self) -> u64 {
+        self.try_reserved_mem_high().unwrap()
+    }
+
+    /// 上次重置以来 [`reserved_mem_current`](Self::reserved_mem_current) 的最大值。
+    #[inline]
+    pub fn try_reserved_mem_high(&self) -> Result<u64, Error> {
+        self.try_get(CUmemPool_attribute::CU_MEMPOOL_ATTR_RESERVED_MEM_HIGH)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn used_mem_current(&self) -> u64 {
+        self.try_used_mem_current().unwrap()
+    }
+
+    /// 分配给用户的字节数。
+    #[inline]
+    pub fn try_used_mem_current(&self) -> Result<u64, Error> {
+        self.try_get(CUmemPool_attribute::CU_MEMPOOL_ATTR_USED_MEM_CURRENT)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn used_mem_high(&self) -> u64 {
+        self.try_used_mem_high().unwrap()
+    }
+
+    /// 上次重置以来 [`used_mem_current`](Self::used_mem_current) 的最大值。
+    #[inline]
+    pub fn try_used_mem_high(&self) -> Result<u64, Error> {
+        self.try_get(CUmemPool_attribute::CU_MEMPOOL_ATTR_USED_MEM_HIGH)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn reset_reserved_mem_high(&self) {
+        self.try_reset_reserved_mem_high().unwrap()
+    }
+
+    #[inline]
+    pub fn try_reset_reserved_mem_high(&self) -> Result<(), Error> {
+        self.try_set(CUmemPool_attribute::CU_MEMPOOL_ATTR_RESERVED_MEM_HIGH, 0)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn reset_used_mem_high(&self) {
+        self.try_reset_used_mem_high().unwrap()
+    }
+
+    #[inline]
+    pub fn try_reset_used_mem_high(&self) -> Result<(), Error> {
+        self.try_set(CUmemPool_attribute::CU_MEMPOOL_ATTR_USED_MEM_HIGH, 0)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn trim_to(&self, min_bytes_to_keep: usize) {
+        self.try_trim_to(min_bytes_to_keep).unwrap()
+    }
+
+    /// 将未使用的存储归还系统，直到存储池保留的字节数不超过 `min_bytes_to_keep`。
+    #[inline]
+    pub fn try_trim_to(&self, min_bytes_to_keep: usize) -> Result<(), Error> {
+        try_driver!(cuMemPoolTrimTo(self.raw, min_bytes_to_keep))
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn access(&self, dev: &Device) -> CUmemAccess_flags {
+        self.try_access(dev).unwrap()
+    }
+
+    /// `dev` 访问池中存储的权限。
+    pub fn try_access(&self, dev: &Device) -> Result<CUmemAccess_flags, Error> {
+        let mut flags = CUmemAccess_flags::CU_MEM_ACCESS_FLAGS_PROT_NONE;
+        let mut location = location(dev);
+        try_driver!(cuMemPoolGetAccess(&mut flags, self.raw, &mut location))?;
+        Ok(flags)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn set_access(&self, dev: &Device, flags: CUmemAccess_flags) {
+        self.try_set_access(dev, flags).unwrap()
+    }
+
+    /// 设置 `dev` 访问池中存储的权限，池所在的设备总是可以读写。
+    pub fn try_set_access(&self, dev: &Device, flags: CUmemAccess_flags) -> Result<(), Error> {
+        let desc = CUmemAccessDesc {
+            location: location(dev),
+            flags,
+        };
+        try_driver!(cuMemPoolSetAccess(self.raw, &desc, 1))
+    }
+}
+
+#[cfg(unix)]
+impl MemPool {
+    /// 导出存储池，存储池必须以 [`MemPoolBuilder::shareable`] 创建。
+    #[inline]
+    #[track_caller]
+    pub fn export_fd(&self) -> std::os::fd::OwnedFd {
+        self.try_export_fd().unwrap()
+    }
+
+    pub fn try_export_fd(&self) -> Result<std::os::fd::OwnedFd, Error> {
+        use std::os::fd::FromRawFd;
+        let mut fd: std::ffi::c_int = -1;
+        try_driver!(cuMemPoolExportToShareableHandle(
+            (&raw mut fd).cast(),
+            self.raw,
+            CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_POSIX_FILE_DESCRIPTOR,
+            0,
+        ))?;
+        Ok(unsafe { std::os::fd::OwnedFd::from_raw_fd(fd) })
+    }
+
+    /// 导入其他进程导出的存储池，`fd` 仍由调用者持有。
+    #[inline]
+    #[track_caller]
+    pub fn import_fd(fd: std::os::fd::BorrowedFd) -> Self {
+        Self::try_import_fd(fd).unwrap()
+    }
+
+    pub fn try_import_fd(fd: std::os::fd::BorrowedFd) -> Result<Self, Error> {
+        use std::os::fd::AsRawFd;
+        let mut raw = null_mut();
+        try_driver!(cuMemPoolImportFromShareableHandle(
+            &mut raw,
+            fd.as_raw_fd() as usize as _,
+            CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_POSIX_FILE_DESCRIPTOR,
+            0,
+        ))?;
+        Ok(Self { raw, owned: true })
+    }
+}
+
+/// 可共享的存储池中一块存储的导出数据，包含存储的长度。
+#[derive(Clone, Copy)]
+pub struct PoolPtrHandle {
+    data: CUmemPoolPtrExportData,
+    len: usize,
+}
+
+impl PoolPtrHandle {
+    const DATA: usize = size_of::<CUmemPoolPtrExportData>();
+    pub const SIZE: usize = Self::DATA + size_of::<usize>();
+
+    pub fn from_bytes(bytes: [u8; Self::SIZE]) -> Self {
+        let (data, len) = bytes.split_at(Self::DATA);
+        Self {
+            data: CUmemPoolPtrExportData {
+                reserved: data.try_into().unwrap(),
+            },
+            len: usize::from_ne_bytes(len.try_into().unwrap()),
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        let (data, len) = bytes.split_at_mut(Self::DATA);
+        data.copy_from_slice(&self.data.reserved);
+        len.copy_from_slice(&self.len.to_ne_bytes());
+        bytes
+    }
+
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl DevMem<'_> {
+    /// 导出从可共享的存储池分配的存储。
+    #[inline]
+    #[track_caller]
+    pub fn pool_ptr_handle(&self) -> PoolPtrHandle {
+        self.try_pool_ptr_handle().unwrap()
+    }
+
+    pub fn try_pool_ptr_handle(&self) -> Result<PoolPtrHandle, Error> {
+        let mut data = MaybeUninit::uninit();
+        try_driver!(cuMemPoolExportPointer(
+            data.as_mut_ptr(),
+            self.as_ptr() as _
+        ))?;
+        Ok(PoolPtrHandle {
+            data: unsafe { data.assume_init() },
+            len: self.len(),
+        })
+    }
+}
+
+/// 从导入的存储池中导入的存储。
+///
+/// 导出的进程释放这块存储之前，导入的进程应当先释放。
+pub struct ImportedMem<'a> {
+    ptr: CUdeviceptr,
+    len: usize,
+    _phantom: PhantomData<&'a ()>,
+}
+
+impl MemPool {
+    /// 导入其他进程中这个存储池分配的存储。
+    #[inline]
+    #[track_caller]
+    pub fn import_ptr<'a>(
+        &'a self,
+        ctx: &'a CurrentCtx,
+        handle: &PoolPtrHandle,
+    ) -> ImportedMem<'a> {
+        self.try_import_ptr(ctx, handle).unwrap()
+    }
+
+    pub fn try_import_ptr<'a>(
+        &'a self,
+        _ctx: &'a CurrentCtx,
+        handle: &PoolPtrHandle,
+    ) -> Result<ImportedMem<'a>, Error> {
+        let mut data = handle.data;
+        let mut ptr = 0;
+        try_driver!(cuMemPoolImportPointer(&mut ptr, self.raw, &mut data))?;
+        Ok(ImportedMem {
+            ptr,
+            len: handle.len,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl Drop for ImportedMem<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        driver!(cuMemFree_v2(self.ptr))
+    }
+}
+
+impl AsRaw for ImportedMem<'_> {
+    type Raw = CUdeviceptr;
+    #[inline]
+    unsafe fn as_raw(&self) -> Self::Raw {
+        self.ptr
+    }
+}
+
+impl Deref for ImportedMem<'_> {
+    type Target = [DevByte];
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        if self.len == 0 {
+            &[]
+        } else {
+            unsafe { from_raw_parts(self.ptr as _, self.len) }
+        }
+    }
+}
+
+impl DerefMut for ImportedMem<'_> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        if self.len == 0 {
+            &mut []
+        } else {
+            unsafe { from_raw_parts_mut(self.ptr as _, self.len) }
+        }
+    }
+}
+
+fn reuse_attr(policy: MemPoolReuse) -> CUmemPool_attribute {
+    use CUmemPool_attribute::*;
+    match policy {
+        MemPoolReuse::FollowEventDependencies => CU_MEMPOOL_ATTR_REUSE_FOLLOW_EVENT_DEPENDENCIES,
+        MemPoolReuse::AllowOpportunistic => CU_MEMPOOL_ATTR_REUSE_ALLOW_OPPORTUNISTIC,
+        MemPoolReuse::AllowInternalDependencies => {
+            CU_MEMPOOL_ATTR_REUSE_ALLOW_INTERNAL_DEPENDENCIES
+        }
+    }
+}
+
+#[test]
+fn test_behavior() {
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    let dev = crate::Device::new(0);
+    let pool = dev.mem_pool_builder().build();
+    pool.set_release_threshold(u64::MAX);
+    assert_eq!(pool.release_threshold(), u64::MAX);
+    pool.set_reuse(MemPoolReuse::AllowOpportunistic, false);
+    assert!(!pool.reuse(MemPoolReuse::AllowOpportunistic));
+    assert!(pool.reuse(MemPoolReuse::FollowEventDependencies));
+    assert_eq!(
+        pool.access(&dev),
+        CUmemAccess_flags::CU_MEM_ACCESS_FLAGS_PROT_READWRITE
+    );
+
+    dev.context().apply(|ctx| {
+        let stream = ctx.stream();
+        let mem = stream.malloc_from::<u8>(&pool, 1 << 20);
+        stream.synchronize();
+        assert_eq!(pool.used_mem_current(), 1 << 20);
+        assert!(pool.reserved_mem_current() >= 1 << 20);
+
+        stream.free(mem).synchronize();
+        assert_eq!(pool.used_mem_current(), 0);
+        assert_eq!(pool.used_mem_high(), 1 << 20);
+        // 释放阈值足够大，存储留在池中
+        assert!(pool.reserved_mem_current() >= 1 << 20);
+
+        pool.trim_to(0);
+        assert_eq!(pool.reserved_mem_current(), 0);
+        pool.reset_used_mem_high();
+        pool.reset_reserved_mem_high();
+        assert_eq!(pool.used_mem_high(), 0);
+        assert_eq!(pool.reserved_mem_high(), 0)
+    })
+}
+
+#[cfg(unix)]
+#[test]
+fn test_share() {
+    use std::os::fd::AsFd;
+
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    let dev = crate::Device::new(0);
+    let pool = dev.mem_pool_builder().shareable(true).build();
+    let fd = pool.export_fd();
+    // 导入的进程通常通过 unix socket 接收文件描述符
+    let imported = MemPool::import_fd(fd.as_fd());
+
+    dev.context().apply(|ctx| {
+        let stream = ctx.stream();
+        let mut mem = stream.malloc_from::<u32>(&pool, 256);
+        stream.memset_d32(&mut mem, 7).synchronize();
+
+        let handle = PoolPtrHandle::from_bytes(mem.pool_ptr_handle().to_bytes());
+        assert_eq!(handle.len(), mem.len());
+        let shared = imported.import_ptr(ctx, &handle);
+        let mut host = [0u32; 256];
+        crate::memcpy_d2h(&mut host, &shared);
+        assert!(host.iter().all(|&x| x == 7));
+        drop(shared);
+
+        // 不可共享的存储池不能导出存储
+        let private = ctx.stream().malloc::<u8>(64);
+        assert!(private.try_pool_ptr_handle().is_err())
+    })
+}
@@ -0,0 +1,389 @@
+use crate::{CurrentCtx, DevByte, Error, Stream};
+use context_spore::AsRaw;
+use std::ffi::c_uint;
+
+/// 可用于填充设备存储的元素类型：`u8`、`u16` 或 `u32`。
+pub trait MemsetElem: Copy + sealed::Sealed {
+    #[doc(hidden)]
+    fn to_bits(self) -> c_uint;
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for u8 {}
+    impl Sealed for u16 {}
+    impl Sealed for u32 {}
+}
+
+impl MemsetElem for u8 {
+    #[inline]
+    fn to_bits(self) -> c_uint {
+        self as _
+    }
+}
+
+impl MemsetElem for u16 {
+    #[inline]
+    fn to_bits(self) -> c_uint {
+        self as _
+    }
+}
+
+impl MemsetElem for u32 {
+    #[inline]
+    fn to_bits(self) -> c_uint {
+        self
+    }
+}
+
+/// 计算填充的元素数量。
+pub(crate) fn elements<T: MemsetElem>(dst: &[DevByte]) -> Result<usize, Error> {
+    if !dst.len().is_multiple_of(size_of::<T>()) {
+        return Err(invalid_value!("dst.len() is a multiple of size_of::<T>()"));
+    }
+    Ok(dst.len() / size_of::<T>())
+}
+
+/// 检查二维填充的区域在 `dst` 范围内，`width` 以元素计。
+fn check_2d<T: MemsetElem>(
+    dst: &[DevByte],
+    pitch: usize,
+    width: usize,
+    height: usize,
+) -> Result<(), Error> {
+    if width == 0 || height == 0 {
+        return Ok(());
+    }
+    let row = width
+        .checked_mul(size_of::<T>())
+        .ok_or_else(|| invalid_value!("width * size_of::<T>() fits in usize"))?;
+    if height > 1 && pitch < row {
+        return Err(invalid_value!("row fits in pitch"));
+    }
+    let end = (height - 1)
+        .checked_mul(pitch)
+        .and_then(|n| n.checked_add(row))
+        .ok_or_else(|| invalid_value!("(height - 1) * pitch + row fits in usize"))?;
+    if end > dst.len() {
+        return Err(invalid_value!("2d region fits in dst"));
+    }
+    Ok(())
+}
+
+impl CurrentCtx {
+    #[inline]
+    #[track_caller]
+    pub fn memset_d8(&self, dst: &mut [DevByte], value: u8) {
+        self.try_memset_d8(dst, value).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn memset_d16(&self, dst: &mut [DevByte], value: u16) {
+        self.try_memset_d16(dst, value).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn memset_d32(&self, dst: &mut [DevByte], value: u32) {
+        self.try_memset_d32(dst, value).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn memset_d8_2d(
+        &self,
+        dst: &mut [DevByte],
+        pitch: usize,
+        value: u8,
+        width: usize,
+        height: usize,
+    ) {
+        self.try_memset_d8_2d(dst, pitch, value, width, height)
+            .unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn memset_d16_2d(
+        &self,
+        dst: &mut [DevByte],
+        pitch: usize,
+        value: u16,
+        width: usize,
+        height: usize,
+    ) {
+        self.try_memset_d16_2d(dst, pitch, value, width, height)
+            .unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn memset_d32_2d(
+        &self,
+        dst: &mut [DevByte],
+        pitch: usize,
+        value: u32,
+        width: usize,
+        height: usize,
+    ) {
+        self.try_memset_d32_2d(dst, pitch, value, width, height)
+            .unwrap()
+    }
+
+    pub fn try_memset_d8(&self, dst: &mut [DevByte], value: u8) -> Result<(), Error> {
+        let len = elements::<u8>(dst)?;
+        try_driver!(cuMemsetD8_v2(dst.as_mut_ptr() as _, value, len))
+    }
+
+    pub fn try_memset_d16(&self, dst: &mut [DevByte], value: u16) -> Result<(), Error> {
+        let len = elements::<u16>(dst)?;
+        try_driver!(cuMemsetD16_v2(dst.as_mut_ptr() as _, value, len))
+    }
+
+    pub fn try_memset_d32(&self, dst: &mut [DevByte], value: u32) -> Result<(), Error> {
+        let len = elements::<u32>(dst)?;
+        try_driver!(cuMemsetD32_v2(dst.as_mut_ptr() as _, value, len))
+    }
+
+    /// 填充 `height` 行，每行 `width` 个元素，行起始地址间隔 `pitch` 字节。
+    pub fn try_memset_d8_2d(
+        &self,
+        dst: &mut [DevByte],
+        pitch: usize,
+        value: u8,
+        width: usize,
+        height: usize,
+    ) -> Result<(), Error> {
+        check_2d::<u8>(dst, pitch, width, height)?;
+        let ptr = dst.as_mut_ptr() as _;
+        try_driver!(cuMemsetD2D8_v2(ptr, pitch, value, width, height))
+    }
+
+    /// 填充 `height` 行，每行 `width` 个元素，行起始地址间隔 `pitch` 字节。
+    pub fn try_memset_d16_2d(
+        &self,
+        dst: &mut [DevByte],
+        pitch: usize,
+        value: u16,
+        width: usize,
+        height: usize,
+    ) -> Result<(), Error> {
+        check_2d::<u16>(dst, pitch, width, height)?;
+        let ptr = dst.as_mut_ptr() as _;
+        try_driver!(cuMemsetD2D16_v2(ptr, pitch, value, width, height))
+    }
+
+    /// 填充 `height` 行，每行 `width` 个元素，行起始地址间隔 `pitch` 字节。
+    pub fn try_memset_d32_2d(
+        &self,
+        dst: &mut [DevByte],
+        pitch: usize,
+        value: u32,
+        width: usize,
+        height: usize,
+    ) -> Result<(), Error> {
+        check_2d::<u32>(dst, pitch, width, height)?;
+        let ptr = dst.as_mut_ptr() as _;
+        try_driver!(cuMemsetD2D32_v2(ptr, pitch, value, width, height))
+    }
+}
+
+impl Stream<'_> {
+    #[inline]
+    #[track_caller]
+    pub fn memset_d8(&self, dst: &mut [DevByte], value: u8) -> &Self {
+        self.try_memset_d8(dst, value).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn memset_d16(&self, dst: &mut [DevByte], value: u16) -> &Self {
+        self.try_memset_d16(dst, value).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn memset_d32(&self, dst: &mut [DevByte], value: u32) -> &Self {
+        self.try_memset_d32(dst, value).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn memset_d8_2d(
+        &self,
+        dst: &mut [DevByte],
+        pitch: usize,
+        value: u8,
+        width: usize,
+        height: usize,
+    ) -> &Self {
+        self.try_memset_d8_2d(dst, pitch, value, width, height)
+            .unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn memset_d16_2d(
+        &self,
+        dst: &mut [DevByte],
+        pitch: usize,
+        value: u16,
+        width: usize,
+        height: usize,
+    ) -> &Self {
+        self.try_memset_d16_2d(dst, pitch, value, width, height)
+            .unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn memset_d32_2d(
+        &self,
+        dst: &mut [DevByte],
+        pitch: usize,
+        value: u32,
+        width: usize,
+        height: usize,
+    ) -> &Self {
+        self.try_memset_d32_2d(dst, pitch, value, width, height)
+            .unwrap()
+    }
+
+    pub fn try_memset_d8(&self, dst: &mut [DevByte], value: u8) -> Result<&Self, Error> {
+        let len = elements::<u8>(dst)?;
+        try_driver!(cuMemsetD8Async(
+            dst.as_mut_ptr() as _,
+            value,
+            len,
+            self.as_raw()
+        ))?;
+        Ok(self)
+    }
+
+    pub fn try_memset_d16(&self, dst: &mut [DevByte], value: u16) -> Result<&Self, Error> {
+        let len = elements::<u16>(dst)?;
+        try_driver!(cuMemsetD16Async(
+            dst.as_mut_ptr() as _,
+            value,
+            len,
+            self.as_raw()
+        ))?;
+        Ok(self)
+    }
+
+    pub fn try_memset_d32(&self, dst: &mut [DevByte], value: u32) -> Result<&Self, Error> {
+        let len = elements::<u32>(dst)?;
+        try_driver!(cuMemsetD32Async(
+            dst.as_mut_ptr() as _,
+            value,
+            len,
+            self.as_raw()
+        ))?;
+        Ok(self)
+    }
+
+    /// 填充 `height` 行，每行 `width` 个元素，行起始地址间隔 `pitch` 字节。
+    pub fn try_memset_d8_2d(
+        &self,
+        dst: &mut [DevByte],
+        pitch: usize,
+        value: u8,
+        width: usize,
+        height: usize,
+    ) -> Result<&Self, Error> {
+        check_2d::<u8>(dst, pitch, width, height)?;
+        try_driver!(cuMemsetD2D8Async(
+            dst.as_mut_ptr() as _,
+            pitch,
+            value,
+            width,
+            height,
+            self.as_raw()
+        ))?;
+        Ok(self)
+    }
+
+    /// 填充 `height` 行，每行 `width` 个元素，行起始地址间隔 `pitch` 字节。
+    pub fn try_memset_d16_2d(
+        &self,
+        dst: &mut [DevByte],
+        pitch: usize,
+        value: u16,
+        width: usize,
+        height: usize,
+    ) -> Result<&Self, Error> {
+        check_2d::<u16>(dst, pitch, width, height)?;
+        try_driver!(cuMemsetD2D16Async(
+            dst.as_mut_ptr() as _,
+            pitch,
+            value,
+            width,
+            height,
+            self.as_raw()
+        ))?;
+        Ok(self)
+    }
+
+    /// 填充 `height` 行，每行 `width` 个元素，行起始地址间隔 `pitch` 字节。
+    pub fn try_memset_d32_2d(
+        &self,
+        dst: &mut [DevByte],
+        pitch: usize,
+        value: u32,
+        width: usize,
+        height: usize,
+    ) -> Result<&Self, Error> {
+        check_2d::<u32>(dst, pitch, width, height)?;
+        try_driver!(cuMemsetD2D32Async(
+            dst.as_mut_ptr() as _,
+            pitch,
+            value,
+            width,
+            height,
+            self.as_raw()
+        ))?;
+        Ok(self)
+    }
+}
+
+#[test]
+fn test_behavior() {
+    use crate::{bindings::CUresult::CUDA_ERROR_INVALID_VALUE, memcpy_d2h};
+
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    crate::Device::new(0).context().apply(|ctx| {
+        let mut mem = ctx.malloc::<u32>(64);
+        let mut host = [0u32; 64];
+
+        ctx.memset_d32(&mut mem, 0x12345678);
+        memcpy_d2h(&mut host, &mem);
+        assert!(host.iter().all(|&x| x == 0x12345678));
+
+        ctx.memset_d16(&mut mem[..8], 0xabcd);
+        ctx.memset_d8(&mut mem[8..12], 0xff);
+        memcpy_d2h(&mut host, &mem);
+        assert_eq!(&host[..3], &[0xabcdabcd, 0xabcdabcd, 0xffffffff]);
+
+        let e = ctx.try_memset_d32(&mut mem[..6], 0).unwrap_err();
+        assert_eq!(e.code(), CUDA_ERROR_INVALID_VALUE);
+        let e = ctx.try_memset_d32_2d(&mut mem, 32, 0, 9, 2).unwrap_err();
+        assert_eq!(e.code(), CUDA_ERROR_INVALID_VALUE);
+        let e = ctx
+            .try_memset_d8_2d(&mut mem, usize::MAX, 0, 1, 3)
+            .unwrap_err();
+        assert_eq!(e.code(), CUDA_ERROR_INVALID_VALUE);
+
+        // 8 行，每行 4 个元素，行间隔 32 字节
+        let stream = ctx.stream();
+        stream
+            .memset_d32(&mut mem, 0)
+            .memset_d32_2d(&mut mem, 32, 1, 4, 8);
+        stream.memcpy_d2h(&mut host, &mem).synchronize();
+        for (i, x) in host.into_iter().enumerate() {
+            assert_eq!(x, if i % 8 < 4 { 1 } else { 0 })
+        }
+    })
+}
@@ -0,0 +1,416 @@
+use super::*;
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_int, c_uint, c_void},
+    ptr::null_mut,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering::SeqCst},
+    },
+};
+
+/// 模拟的设备数量。
+pub(super) const DEVICE_COUNT: c_int = 2;
+/// 模拟的每个设备的存储空间。
+pub(super) const TOTAL_MEMORY: usize = 8 << 30;
+
+const DRIVER_VERSION: c_int = 12080;
+const DEVICE_NAME: &[u8] = b"Mock Device";
+
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+static DEVICES: [Device; DEVICE_COUNT as usize] = [Device::new(0), Device::new(1)];
+
+thread_local! {
+    /// 当前线程的上下文栈。
+    static STACK: RefCell<Vec<CUcontext>> = const { RefCell::new(Vec::new()) };
+}
+
+pub(super) struct Device {
+    /// 主上下文和它的引用计数。
+    primary: Mutex<(usize, usize)>,
+    /// 已分配的存储空间。
+    pub(super) used: AtomicUsize,
+    /// 默认存储池。
+    pub(super) pool: MemPool,
+}
+
+impl Device {
+    const fn new(dev: CUdevice) -> Self {
+        Self {
+            primary: Mutex::new((0, 0)),
+            used: AtomicUsize::new(0),
+            pool: MemPool::new(dev, false, 0),
+        }
+    }
+
+    /// 在设备上记录分配 `len` 字节，超出容量时失败。
+    pub(super) fn reserve(&self, len: usize) -> Result<(), CUresult> {
+        self.used
+            .fetch_update(SeqCst, SeqCst, |used| {
+                used.checked_add(len).filter(|&used| used <= TOTAL_MEMORY)
+            })
+            .map(|_| ())
+            .map_err(|_| CUDA_ERROR_OUT_OF_MEMORY)
+    }
+
+    /// 在设备上记录释放 `len` 字节。
+    pub(super) fn release(&self, len: usize) {
+        self.used.fetch_sub(len, SeqCst);
+    }
+}
+
+pub(super) struct Context {
+    pub(super) dev: CUdevice,
+    /// 已启用对等访问的上下文。
+    peers: Mutex<Vec<usize>>,
+}
+
+impl Context {
+    fn new(dev: CUdevice) -> Self {
+        Self {
+            dev,
+            peers: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+pub(super) fn initialized() -> Result<(), CUresult> {
+    if INITIALIZED.load(SeqCst) {
+        Ok(())
+    } else {
+        Err(CUDA_ERROR_NOT_INITIALIZED)
+    }
+}
+
+pub(super) fn device(dev: CUdevice) -> Result<&'static Device, CUresult> {
+    initialized()?;
+    usize::try_from(dev)
+        .ok()
+        .and_then(|i| DEVICES.get(i))
+        .ok_or(CUDA_ERROR_INVALID_DEVICE)
+}
+
+/// 当前上下文所在的设备。
+pub(super) fn current_device() -> Result<CUdevice, CUresult> {
+    let ctx = STACK.with_borrow(|stack| stack.last().copied());
+    let ctx = ctx.ok_or(CUDA_ERROR_INVALID_CONTEXT)?;
+    Ok(unsafe { from_handle::<Context, _>(ctx) }?.dev)
+}
+
+pub unsafe extern "C" fn cuGetErrorString(error: CUresult, pStr: *mut *const c_char) -> CUresult {
+    result(|| unsafe { write(pStr, error.text().1.as_ptr()) })
+}
+
+pub unsafe extern "C" fn cuGetErrorName(error: CUresult, pStr: *mut *const c_char) -> CUresult {
+    result(|| unsafe { write(pStr, error.text().0.as_ptr()) })
+}
+
+pub unsafe extern "C" fn cuInit(Flags: c_uint) -> CUresult {
+    result(|| {
+        if Flags != 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        INITIALIZED.store(true, SeqCst);
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuDriverGetVersion(driverVersion: *mut c_int) -> CUresult {
+    result(|| unsafe { write(driverVersion, DRIVER_VERSION) })
+}
+
+pub unsafe extern "C" fn cuDeviceGet(device_: *mut CUdevice, ordinal: c_int) -> CUresult {
+    result(|| {
+        device(ordinal)?;
+        unsafe { write(device_, ordinal) }
+    })
+}
+
+pub unsafe extern "C" fn cuDeviceGetCount(count: *mut c_int) -> CUresult {
+    result(|| {
+        initialized()?;
+        unsafe { write(count, DEVICE_COUNT) }
+    })
+}
+
+pub unsafe extern "C" fn cuDeviceGetName(name: *mut c_char, len: c_int, dev: CUdevice) -> CUresult {
+    result(|| {
+        device(dev)?;
+        let len = usize::try_from(len).map_err(|_| CUDA_ERROR_INVALID_VALUE)?;
+        if name.is_null() || len == 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let n = DEVICE_NAME.len().min(len - 1);
+        unsafe {
+            name.copy_from_nonoverlapping(DEVICE_NAME.as_ptr().cast(), n);
+            name.add(n).write(0)
+        }
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuDeviceTotalMem_v2(bytes: *mut usize, dev: CUdevice) -> CUresult {
+    result(|| {
+        device(dev)?;
+        unsafe { write(bytes, TOTAL_MEMORY) }
+    })
+}
+
+pub unsafe extern "C" fn cuDeviceGetAttribute(
+    pi: *mut c_int,
+    attrib: CUdevice_attribute,
+    dev: CUdevice,
+) -> CUresult {
+    use CUdevice_attribute::*;
+    result(|| {
+        device(dev)?;
+        #[rustfmt::skip]
+        let value = match attrib {
+            CU_DEVICE_ATTRIBUTE_MAX_THREADS_PER_BLOCK                      => 1024,
+            CU_DEVICE_ATTRIBUTE_MAX_BLOCK_DIM_X                            => 1024,
+            CU_DEVICE_ATTRIBUTE_MAX_BLOCK_DIM_Y                            => 1024,
+            CU_DEVICE_ATTRIBUTE_MAX_BLOCK_DIM_Z                            => 64,
+            CU_DEVICE_ATTRIBUTE_MAX_GRID_DIM_X                             => c_int::MAX,
+            CU_DEVICE_ATTRIBUTE_MAX_GRID_DIM_Y                             => 65535,
+            CU_DEVICE_ATTRIBUTE_MAX_GRID_DIM_Z                             => 65535,
+            CU_DEVICE_ATTRIBUTE_MAX_SHARED_MEMORY_PER_BLOCK                => 48 << 10,
+            CU_DEVICE_ATTRIBUTE_WARP_SIZE                                  => 32,
+            CU_DEVICE_ATTRIBUTE_MAX_REGISTERS_PER_BLOCK                    => 64 << 10,
+            CU_DEVICE_ATTRIBUTE_TEXTURE_ALIGNMENT                          => 512,
+            CU_DEVICE_ATTRIBUTE_MULTIPROCESSOR_COUNT                       => 108,
+            CU_DEVICE_ATTRIBUTE_CAN_MAP_HOST_MEMORY                        => 1,
+            CU_DEVICE_ATTRIBUTE_MAX_THREADS_PER_MULTIPROCESSOR             => 2048,
+            CU_DEVICE_ATTRIBUTE_UNIFIED_ADDRESSING                         => 1,
+            CU_DEVICE_ATTRIBUTE_COMPUTE_CAPABILITY_MAJOR                   => 8,
+            CU_DEVICE_ATTRIBUTE_COMPUTE_CAPABILITY_MINOR                   => 0,
+            CU_DEVICE_ATTRIBUTE_STREAM_PRIORITIES_SUPPORTED                => 1,
+            CU_DEVICE_ATTRIBUTE_MAX_SHARED_MEMORY_PER_MULTIPROCESSOR       => 164 << 10,
+            CU_DEVICE_ATTRIBUTE_MAX_REGISTERS_PER_MULTIPROCESSOR           => 64 << 10,
+            CU_DEVICE_ATTRIBUTE_MANAGED_MEMORY                             => 1,
+            CU_DEVICE_ATTRIBUTE_CONCURRENT_MANAGED_ACCESS                  => 1,
+            CU_DEVICE_ATTRIBUTE_MAX_SHARED_MEMORY_PER_BLOCK_OPTIN          => 163 << 10,
+            CU_DEVICE_ATTRIBUTE_VIRTUAL_MEMORY_MANAGEMENT_SUPPORTED        => 1,
+            CU_DEVICE_ATTRIBUTE_HANDLE_TYPE_POSIX_FILE_DESCRIPTOR_SUPPORTED => 1,
+            CU_DEVICE_ATTRIBUTE_MAX_BLOCKS_PER_MULTIPROCESSOR              => 32,
+            CU_DEVICE_ATTRIBUTE_RESERVED_SHARED_MEMORY_PER_BLOCK           => 1 << 10,
+            CU_DEVICE_ATTRIBUTE_MEMORY_POOLS_SUPPORTED                     => 1,
+            CU_DEVICE_ATTRIBUTE_MEMPOOL_SUPPORTED_HANDLE_TYPES             => 1,
+            _                                                              => 0,
+        };
+        unsafe { write(pi, value) }
+    })
+}
+
+pub unsafe extern "C" fn cuDeviceCanAccessPeer(
+    canAccessPeer: *mut c_int,
+    dev: CUdevice,
+    peerDev: CUdevice,
+) -> CUresult {
+    result(|| {
+        device(dev)?;
+        device(peerDev)?;
+        // 模拟的设备之间都可以对等访问
+        unsafe { write(canAccessPeer, (dev != peerDev) as _) }
+    })
+}
+
+pub unsafe extern "C" fn cuDeviceGetP2PAttribute(
+    value: *mut c_int,
+    attrib: CUdevice_P2PAttribute,
+    srcDevice: CUdevice,
+    dstDevice: CUdevice,
+) -> CUresult {
+    use CUdevice_P2PAttribute::*;
+    result(|| {
+        device(srcDevice)?;
+        device(dstDevice)?;
+        if srcDevice == dstDevice {
+            return Err(CUDA_ERROR_INVALID_DEVICE);
+        }
+        let ans = match attrib {
+            CU_DEVICE_P2P_ATTRIBUTE_PERFORMANCE_RANK => 0,
+            _ => 1,
+        };
+        unsafe { write(value, ans) }
+    })
+}
+
+pub unsafe extern "C" fn cuDeviceGetDefaultMemPool(
+    pool_out: *mut CUmemoryPool,
+    dev: CUdevice,
+) -> CUresult {
+    result(|| {
+        let pool = &device(dev)?.pool;
+        unsafe { write(pool_out, (pool as *const MemPool).cast_mut().cast()) }
+    })
+}
+
+pub unsafe extern "C" fn cuDevicePrimaryCtxRetain(pctx: *mut CUcontext, dev: CUdevice) -> CUresult {
+    result(|| {
+        let mut primary = device(dev)?.primary.lock().unwrap();
+        if pctx.is_null() {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let (ctx, count) = &mut *primary;
+        if *count == 0 {
+            *ctx = into_handle::<_, CUctx_st>(Context::new(dev)) as _
+        }
+        *count += 1;
+        unsafe { write(pctx, *ctx as _) }
+    })
+}
+
+pub unsafe extern "C" fn cuDevicePrimaryCtxRelease_v2(dev: CUdevice) -> CUresult {
+    result(|| {
+        let mut primary = device(dev)?.primary.lock().unwrap();
+        let (ctx, count) = &mut *primary;
+        match *count {
+            0 => Err(CUDA_ERROR_INVALID_CONTEXT),
+            1 => {
+                *count = 0;
+                unsafe { drop_handle::<Context, _>(std::mem::take(ctx) as CUcontext) }
+            }
+            _ => {
+                *count -= 1;
+                Ok(())
+            }
+        }
+    })
+}
+
+pub unsafe extern "C" fn cuDevicePrimaryCtxGetState(
+    dev: CUdevice,
+    flags: *mut c_uint,
+    active: *mut c_int,
+) -> CUresult {
+    result(|| {
+        let (_, count) = *device(dev)?.primary.lock().unwrap();
+        unsafe {
+            write(flags, 0)?;
+            write(active, (count > 0) as _)
+        }
+    })
+}
+
+pub unsafe extern "C" fn cuCtxCreate_v2(
+    pctx: *mut CUcontext,
+    flags: c_uint,
+    dev: CUdevice,
+) -> CUresult {
+    result(|| {
+        device(dev)?;
+        if pctx.is_null() || flags != 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let ctx = into_handle(Context::new(dev));
+        STACK.with_borrow_mut(|stack| stack.push(ctx));
+        unsafe { write(pctx, ctx) }
+    })
+}
+
+pub unsafe extern "C" fn cuCtxDestroy_v2(ctx: CUcontext) -> CUresult {
+    result(|| {
+        STACK.with_borrow_mut(|stack| stack.retain(|&c| c != ctx));
+        unsafe { drop_handle::<Context, _>(ctx) }
+    })
+}
+
+pub unsafe extern "C" fn cuCtxPushCurrent_v2(ctx: CUcontext) -> CUresult {
+    result(|| {
+        unsafe { from_handle::<Context, _>(ctx) }.map_err(|_| CUDA_ERROR_INVALID_CONTEXT)?;
+        STACK.with_borrow_mut(|stack| stack.push(ctx));
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuCtxPopCurrent_v2(pctx: *mut CUcontext) -> CUresult {
+    result(|| {
+        let ctx = STACK
+            .with_borrow_mut(Vec::pop)
+            .ok_or(CUDA_ERROR_INVALID_CONTEXT)?;
+        if !pctx.is_null() {
+            unsafe { pctx.write(ctx) }
+        }
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuCtxGetCurrent(pctx: *mut CUcontext) -> CUresult {
+    result(|| {
+        let ctx = STACK.with_borrow(|stack| stack.last().copied());
+        unsafe { write(pctx, ctx.unwrap_or(null_mut())) }
+    })
+}
+
+pub unsafe extern "C" fn cuCtxGetDevice(device: *mut CUdevice) -> CUresult {
+    result(|| unsafe { write(device, current_device()?) })
+}
+
+pub unsafe extern "C" fn cuCtxEnablePeerAccess(peerContext: CUcontext, Flags: c_uint) -> CUresult {
+    result(|| {
+        let ctx = STACK.with_borrow(|stack| stack.last().copied());
+        let ctx = unsafe { from_handle::<Context, _>(ctx.ok_or(CUDA_ERROR_INVALID_CONTEXT)?) }?;
+        let peer = unsafe { from_handle::<Context, _>(peerContext) }
+            .map_err(|_| CUDA_ERROR_INVALID_CONTEXT)?;
+        if Flags != 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        if ctx.dev == peer.dev {
+            return Err(CUDA_ERROR_INVALID_DEVICE);
+        }
+        let mut peers = ctx.peers.lock().unwrap();
+        if peers.contains(&(peerContext as usize)) {
+            return Err(CUDA_ERROR_PEER_ACCESS_ALREADY_ENABLED);
+        }
+        peers.push(peerContext as _);
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuCtxDisablePeerAccess(peerContext: CUcontext) -> CUresult {
+    result(|| {
+        let ctx = STACK.with_borrow(|stack| stack.last().copied());
+        let ctx = unsafe { from_handle::<Context, _>(ctx.ok_or(CUDA_ERROR_INVALID_CONTEXT)?) }?;
+        let mut peers = ctx.peers.lock().unwrap();
+        let i = peers
+            .iter()
+            .position(|&p| p == peerContext as usize)
+            .ok_or(CUDA_ERROR_PEER_ACCESS_NOT_ENABLED)?;
+        peers.swap_remove(i);
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuCtxSynchronize() -> CUresult {
+    result(|| current_device().map(|_| ()))
+}
+
+pub unsafe extern "C" fn cuModuleLoadData(module: *mut CUmodule, image: *const c_void) -> CUresult {
+    result(|| {
+        current_device()?;
+        if module.is_null() || image.is_null() {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        Err(CUDA_ERROR_NOT_SUPPORTED)
+    })
+}
+
+pub unsafe extern "C" fn cuModuleUnload(_hmod: CUmodule) -> CUresult {
+    CUDA_ERROR_INVALID_HANDLE
+}
+
+pub unsafe extern "C" fn cuModuleGetFunction(
+    _hfunc: *mut CUfunction,
+    _hmod: CUmodule,
+    _name: *const c_char,
+) -> CUresult {
+    CUDA_ERROR_INVALID_HANDLE
+}
+
+pub unsafe extern "C" fn cuFuncGetAttribute(
+    _pi: *mut c_int,
+    _attrib: CUfunction_attribute,
+    _hfunc: CUfunction,
+) -> CUresult {
+    CUDA_ERROR_INVALID_HANDLE
+}
@@ -0,0 +1,1023 @@
+use super::{
+    memory::{Region, RegionKind},
+    *,
+};
+use std::{
+    ffi::{CStr, c_char, c_uint, c_ulonglong, c_void},
+    fmt::Write,
+    ptr::{null, null_mut},
+    slice::from_raw_parts,
+    sync::{Arc, Mutex},
+};
+
+/// 图节点或流上的操作。
+#[derive(Clone)]
+pub(super) enum Op {
+    Memcpy(CUDA_MEMCPY3D),
+    Memset(CUDA_MEMSET_NODE_PARAMS),
+    Host(CUDA_HOST_NODE_PARAMS),
+    MemAlloc(Arc<GraphMem>),
+    MemFree(CUdeviceptr),
+    Conditional(Arc<Conditional>),
+}
+
+impl Op {
+    fn node_type(&self) -> CUgraphNodeType {
+        use CUgraphNodeType::*;
+        match self {
+            Self::Memcpy(_) => CU_GRAPH_NODE_TYPE_MEMCPY,
+            Self::Memset(_) => CU_GRAPH_NODE_TYPE_MEMSET,
+            Self::Host(_) => CU_GRAPH_NODE_TYPE_HOST,
+            Self::MemAlloc(_) => CU_GRAPH_NODE_TYPE_MEM_ALLOC,
+            Self::MemFree(_) => CU_GRAPH_NODE_TYPE_MEM_FREE,
+            Self::Conditional(_) => CU_GRAPH_NODE_TYPE_CONDITIONAL,
+        }
+    }
+
+    /// 能否出现在条件节点的子图中。
+    fn allowed_in_body(&self) -> bool {
+        match self {
+            Self::Host(_) | Self::MemAlloc(_) | Self::MemFree(_) => false,
+            Self::Conditional(cond) => cond.bodies_allowed(),
+            _ => true,
+        }
+    }
+
+    /// 检查操作的参数。
+    pub(super) fn check(&self) -> Result<(), CUresult> {
+        match self {
+            Self::Host(CUDA_HOST_NODE_PARAMS { fn_: None, .. }) => Err(CUDA_ERROR_INVALID_VALUE),
+            _ => Ok(()),
+        }
+    }
+
+    /// 执行操作。
+    pub(super) fn run(&self) -> Result<(), CUresult> {
+        match self {
+            Self::Memcpy(p) => memory::memcpy_3d(p),
+            Self::Memset(p) => memory::memset_2d(p),
+            &Self::Host(CUDA_HOST_NODE_PARAMS { fn_, userData }) => {
+                unsafe { fn_.ok_or(CUDA_ERROR_INVALID_VALUE)?(userData) };
+                Ok(())
+            }
+            // 图分配的存储在创建节点时分配，随节点释放
+            Self::MemAlloc(_) | Self::MemFree(_) => Ok(()),
+            Self::Conditional(cond) => cond.run(),
+        }
+    }
+}
+
+/// 条件节点的条件值。
+pub(super) struct CondHandle {
+    ctx: usize,
+    /// 每次启动图时赋予的默认值。
+    default: Option<c_uint>,
+}
+
+/// 条件节点，子图由节点持有。
+pub(super) struct Conditional {
+    handle: Arc<CondHandle>,
+    type_: CUgraphConditionalNodeType,
+    /// 子图的句柄，以驱动持有的数组返回给调用者。
+    bodies: Box<[usize]>,
+}
+
+impl Conditional {
+    fn bodies_allowed(&self) -> bool {
+        self.bodies.iter().all(|&body| {
+            let graph = unsafe { &*(body as *const Graph) };
+            let nodes = graph.nodes.lock().unwrap();
+            nodes
+                .iter()
+                .all(|&node| op_of(node).unwrap().allowed_in_body())
+        })
+    }
+
+    fn run(&self) -> Result<(), CUresult> {
+        use CUgraphConditionalNodeType::*;
+        // 模拟驱动不能执行 kernel，条件值只能来自启动时赋予的默认值
+        let value = self.handle.default.unwrap_or(0) as usize;
+        let body = match self.type_ {
+            CU_GRAPH_COND_TYPE_IF if value != 0 => self.bodies.first(),
+            CU_GRAPH_COND_TYPE_IF => self.bodies.get(1),
+            // 没有 kernel 能修改条件值，条件非零的循环不会结束
+            CU_GRAPH_COND_TYPE_WHILE if value != 0 => return Err(CUDA_ERROR_LAUNCH_TIMEOUT),
+            CU_GRAPH_COND_TYPE_WHILE => None,
+            CU_GRAPH_COND_TYPE_SWITCH => self.bodies.get(value),
+        };
+        let Some(&body) = body else { return Ok(()) };
+        let graph = unsafe { &*(body as *const Graph) };
+        for node in graph.exec_nodes() {
+            node.op.check()?;
+            node.op.run()?
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Conditional {
+    fn drop(&mut self) {
+        for &body in &self.bodies {
+            unsafe { drop_handle::<Graph, _>(body as CUgraph) }.unwrap()
+        }
+    }
+}
+
+/// 图分配节点的存储。
+pub(super) struct GraphMem {
+    ptr: usize,
+    len: usize,
+    dev: CUdevice,
+}
+
+impl GraphMem {
+    pub(super) fn new(dev: CUdevice, len: usize) -> Result<Self, CUresult> {
+        if len == 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let device = driver::device(dev)?;
+        device.reserve(len)?;
+        let ptr = memory::map_anonymous(len).inspect_err(|_| device.release(len))?;
+        let kind = RegionKind::Graph;
+        memory::insert_region(ptr, Region { len, kind });
+        Ok(Self { ptr, len, dev })
+    }
+
+    #[inline]
+    pub(super) fn ptr(&self) -> CUdeviceptr {
+        self.ptr as _
+    }
+}
+
+impl Drop for GraphMem {
+    fn drop(&mut self) {
+        memory::remove_region(self.ptr);
+        let _ = memory::unmap_anonymous(self.ptr, self.len);
+        if let Ok(device) = driver::device(self.dev) {
+            device.release(self.len)
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct Graph {
+    nodes: Mutex<Vec<CUgraphNode>>,
+    /// 图持有的用户对象引用，每个引用占一项。
+    objects: Mutex<Vec<Arc<UserObject>>>,
+    /// 在图上创建的条件值句柄。
+    handles: Mutex<Vec<Arc<CondHandle>>>,
+}
+
+/// 用户对象，所有引用释放后调用析构函数。
+pub(super) struct UserObject {
+    ptr: usize,
+    destroy: CUhostFn,
+}
+
+impl Drop for UserObject {
+    fn drop(&mut self) {
+        if let Some(destroy) = self.destroy {
+            unsafe { destroy(self.ptr as _) }
+        }
+    }
+}
+
+struct Node {
+    op: Op,
+    deps: Box<[CUgraphNode]>,
+}
+
+impl Graph {
+    /// 添加节点。依赖必须是图中已有的节点，因此节点的添加顺序就是一个拓扑序。
+    pub(super) fn add(&self, op: Op, deps: &[CUgraphNode]) -> Result<CUgraphNode, CUresult> {
+        op.check()?;
+        let mut nodes = self.nodes.lock().unwrap();
+        if !deps.iter().all(|dep| nodes.contains(dep)) {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let deps = deps.into();
+        let node = into_handle(Node { op, deps });
+        nodes.push(node);
+        Ok(node)
+    }
+
+    /// 按拓扑序复制节点，依赖转换为序号。
+    fn exec_nodes(&self) -> Vec<ExecNode> {
+        let nodes = self.nodes.lock().unwrap();
+        nodes
+            .iter()
+            .map(|&node| {
+                let Node { op, deps } = unsafe { &*node.cast::<Node>() };
+                ExecNode {
+                    node,
+                    op: op.clone(),
+                    deps: deps
+                        .iter()
+                        .map(|dep| nodes.iter().position(|n| n == dep).unwrap())
+                        .collect(),
+                    enabled: true,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Drop for Graph {
+    fn drop(&mut self) {
+        for node in self.nodes.get_mut().unwrap().drain(..) {
+            unsafe { drop_handle::<Node, _>(node) }.unwrap()
+        }
+    }
+}
+
+/// 实例化的图，按拓扑序保存节点。
+struct Exec {
+    nodes: Mutex<Vec<ExecNode>>,
+    /// 实例化时图持有的用户对象。
+    _objects: Vec<Arc<UserObject>>,
+}
+
+/// 执行图中的节点。
+struct ExecNode {
+    /// 实例化时图中对应的节点。
+    node: CUgraphNode,
+    op: Op,
+    /// 依赖的节点在执行图中的序号。
+    deps: Box<[usize]>,
+    enabled: bool,
+}
+
+impl ExecNode {
+    /// 检查能否以 `new` 更新节点。
+    fn check_update(&self, new: &Self) -> CUgraphExecUpdateResult {
+        use CUgraphExecUpdateResult::*;
+        if self.deps != new.deps {
+            return CU_GRAPH_EXEC_UPDATE_ERROR_TOPOLOGY_CHANGED;
+        }
+        match (&self.op, &new.op) {
+            (Op::Memcpy(old), Op::Memcpy(new)) => {
+                if old.srcMemoryType == new.srcMemoryType && old.dstMemoryType == new.dstMemoryType
+                {
+                    CU_GRAPH_EXEC_UPDATE_SUCCESS
+                } else {
+                    CU_GRAPH_EXEC_UPDATE_ERROR_PARAMETERS_CHANGED
+                }
+            }
+            (Op::Memset(_), Op::Memset(_)) | (Op::Host(_), Op::Host(_)) => {
+                CU_GRAPH_EXEC_UPDATE_SUCCESS
+            }
+            // 图分配、释放和条件节点不能更新
+            (Op::MemAlloc(_), Op::MemAlloc(_))
+            | (Op::MemFree(_), Op::MemFree(_))
+            | (Op::Conditional(_), Op::Conditional(_)) => CU_GRAPH_EXEC_UPDATE_ERROR_NOT_SUPPORTED,
+            _ => CU_GRAPH_EXEC_UPDATE_ERROR_NODE_TYPE_CHANGED,
+        }
+    }
+}
+
+/// 修改执行图中 `hNode` 对应节点的操作。
+fn set_exec_op(
+    hGraphExec: CUgraphExec,
+    hNode: CUgraphNode,
+    f: impl FnOnce(&mut Op) -> Result<(), CUresult>,
+) -> Result<(), CUresult> {
+    let exec = unsafe { from_handle::<Exec, _>(hGraphExec) }?;
+    let mut nodes = exec.nodes.lock().unwrap();
+    let node = nodes
+        .iter_mut()
+        .find(|n| n.node == hNode)
+        .ok_or(CUDA_ERROR_INVALID_VALUE)?;
+    f(&mut node.op)
+}
+
+/// 向图中添加节点。
+///
+/// # Safety
+///
+/// `dependencies` 指向 `numDependencies` 个节点。
+unsafe fn add_node(
+    phGraphNode: *mut CUgraphNode,
+    hGraph: CUgraph,
+    dependencies: *const CUgraphNode,
+    numDependencies: usize,
+    op: Op,
+) -> Result<(), CUresult> {
+    let graph = unsafe { from_handle::<Graph, _>(hGraph) }?;
+    if phGraphNode.is_null() || (dependencies.is_null() && numDependencies > 0) {
+        return Err(CUDA_ERROR_INVALID_VALUE);
+    }
+    let deps = if numDependencies == 0 {
+        &[]
+    } else {
+        unsafe { from_raw_parts(dependencies, numDependencies) }
+    };
+    let node = graph.add(op, deps)?;
+    unsafe { write(phGraphNode, node) }
+}
+
+/// 读取节点的操作。
+fn op_of<'a>(hNode: CUgraphNode) -> Result<&'a Op, CUresult> {
+    Ok(&unsafe { from_handle::<Node, _>(hNode) }?.op)
+}
+
+pub unsafe extern "C" fn cuGraphCreate(phGraph: *mut CUgraph, flags: c_uint) -> CUresult {
+    result(|| {
+        if flags != 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        unsafe { write(phGraph, into_handle(Graph::default())) }
+    })
+}
+
+pub unsafe extern "C" fn cuGraphDestroy(hGraph: CUgraph) -> CUresult {
+    result(|| unsafe { drop_handle::<Graph, _>(hGraph) })
+}
+
+pub unsafe extern "C" fn cuGraphDebugDotPrint(
+    hGraph: CUgraph,
+    path: *const c_char,
+    _flags: c_uint,
+) -> CUresult {
+    result(|| {
+        let graph = unsafe { from_handle::<Graph, _>(hGraph) }?;
+        if path.is_null() {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let path = unsafe { CStr::from_ptr(path) }
+            .to_str()
+            .map_err(|_| CUDA_ERROR_INVALID_VALUE)?;
+
+        let nodes = graph.nodes.lock().unwrap();
+        let index = |node| nodes.iter().position(|&n| n == node).unwrap();
+        let mut dot = String::from("digraph mock {\n");
+        for (i, &node) in nodes.iter().enumerate() {
+            let node = unsafe { &*node.cast::<Node>() };
+            let ty = format!("{:?}", node.op.node_type());
+            let ty = ty.trim_start_matches("CU_GRAPH_NODE_TYPE_");
+            writeln!(dot, "  node{i} [label=\"{ty}\"];").unwrap();
+            for &dep in &node.deps {
+                writeln!(dot, "  node{} -> node{i};", index(dep)).unwrap()
+            }
+        }
+        dot.push_str("}\n");
+        std::fs::write(path, dot).map_err(|_| CUDA_ERROR_OPERATING_SYSTEM)
+    })
+}
+
+pub unsafe extern "C" fn cuGraphGetNodes(
+    hGraph: CUgraph,
+    nodes: *mut CUgraphNode,
+    numNodes: *mut usize,
+) -> CUresult {
+    result(|| {
+        let graph = unsafe { from_handle::<Graph, _>(hGraph) }?;
+        if numNodes.is_null() {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let all = graph.nodes.lock().unwrap();
+        let num = if nodes.is_null() {
+            all.len()
+        } else {
+            let num = unsafe { *numNodes }.min(all.len());
+            unsafe { nodes.copy_from_nonoverlapping(all.as_ptr(), num) };
+            num
+        };
+        unsafe { write(numNodes, num) }
+    })
+}
+
+pub unsafe extern "C" fn cuGraphNodeGetType(
+    hNode: CUgraphNode,
+    type_: *mut CUgraphNodeType,
+) -> CUresult {
+    result(|| unsafe { write(type_, op_of(hNode)?.node_type()) })
+}
+
+/// 实例化图。失败时返回实例化的结果和导致失败的节点。
+fn instantiate(
+    phGraphExec: *mut CUgraphExec,
+    hGraph: CUgraph,
+    flags: c_ulonglong,
+) -> Result<(), (CUresult, CUgraphInstantiateResult, CUgraphNode)> {
+    use CUgraphInstantiate_flags::*;
+    use CUgraphInstantiateResult::*;
+    let error = |e| (e, CUDA_GRAPH_INSTANTIATE_ERROR, null_mut());
+
+    driver::current_device().map_err(error)?;
+    let graph = unsafe { from_handle::<Graph, _>(hGraph) }.map_err(error)?;
+    let all = [
+        CUDA_GRAPH_INSTANTIATE_FLAG_AUTO_FREE_ON_LAUNCH,
+        CUDA_GRAPH_INSTANTIATE_FLAG_UPLOAD,
+        CUDA_GRAPH_INSTANTIATE_FLAG_DEVICE_LAUNCH,
+        CUDA_GRAPH_INSTANTIATE_FLAG_USE_NODE_PRIORITY,
+    ]
+    .iter()
+    .fold(0, |acc, &flag| acc | flag as c_ulonglong);
+    if flags & !all != 0 {
+        return Err(error(CUDA_ERROR_INVALID_VALUE));
+    }
+    let nodes = graph.exec_nodes();
+    // 设备启动的图只能包含设备上的操作
+    if flags & CUDA_GRAPH_INSTANTIATE_FLAG_DEVICE_LAUNCH as c_ulonglong != 0
+        && let Some(node) = nodes
+            .iter()
+            .find(|n| matches!(n.op, Op::Host(_) | Op::MemAlloc(_) | Op::MemFree(_)))
+    {
+        return Err((
+            CUDA_ERROR_INVALID_VALUE,
+            CUDA_GRAPH_INSTANTIATE_NODE_OPERATION_NOT_SUPPORTED,
+            node.node,
+        ));
+    }
+    // 条件节点的子图只能包含设备上的操作
+    if let Some(node) = nodes
+        .iter()
+        .find(|n| matches!(&n.op, Op::Conditional(cond) if !cond.bodies_allowed()))
+    {
+        return Err((
+            CUDA_ERROR_INVALID_VALUE,
+            CUDA_GRAPH_INSTANTIATE_NODE_OPERATION_NOT_SUPPORTED,
+            node.node,
+        ));
+    }
+    let exec = Exec {
+        nodes: Mutex::new(nodes),
+        _objects: graph.objects.lock().unwrap().clone(),
+    };
+    unsafe { write(phGraphExec, into_handle(exec)) }.map_err(error)
+}
+
+pub unsafe extern "C" fn cuGraphInstantiateWithFlags(
+    phGraphExec: *mut CUgraphExec,
+    hGraph: CUgraph,
+    flags: c_ulonglong,
+) -> CUresult {
+    result(|| {
+        // 上传需要指定流，只能通过参数结构体指定
+        if flags & CUgraphInstantiate_flags::CUDA_GRAPH_INSTANTIATE_FLAG_UPLOAD as c_ulonglong != 0
+        {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        instantiate(phGraphExec, hGraph, flags).map_err(|(e, ..)| e)
+    })
+}
+
+pub unsafe extern "C" fn cuGraphInstantiateWithParams(
+    phGraphExec: *mut CUgraphExec,
+    hGraph: CUgraph,
+    instantiateParams: *mut CUDA_GRAPH_INSTANTIATE_PARAMS,
+) -> CUresult {
+    result(|| {
+        let p = unsafe { instantiateParams.as_mut() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        p.hErrNode_out = null_mut();
+        p.result_out = CUgraphInstantiateResult::CUDA_GRAPH_INSTANTIATE_SUCCESS;
+        let upload = CUgraphInstantiate_flags::CUDA_GRAPH_INSTANTIATE_FLAG_UPLOAD as c_ulonglong;
+        if p.flags & upload != 0 {
+            stream::forbid_capture(p.hUploadStream)?
+        }
+        instantiate(phGraphExec, hGraph, p.flags).map_err(|(e, result, node)| {
+            p.result_out = result;
+            p.hErrNode_out = node;
+            e
+        })
+    })
+}
+
+pub unsafe extern "C" fn cuGraphUpload(hGraphExec: CUgraphExec, hStream: CUstream) -> CUresult {
+    result(|| {
+        unsafe { from_handle::<Exec, _>(hGraphExec) }?;
+        driver::current_device()?;
+        // 模拟的执行图不需要上传
+        stream::forbid_capture(hStream)
+    })
+}
+
+pub unsafe extern "C" fn cuUserObjectCreate(
+    object_out: *mut CUuserObject,
+    ptr: *mut c_void,
+    destroy: CUhostFn,
+    initialRefcount: c_uint,
+    flags: c_uint,
+) -> CUresult {
+    result(|| {
+        if object_out.is_null()
+            || destroy.is_none()
+            || initialRefcount == 0
+            || flags != CUuserObject_flags::CU_USER_OBJECT_NO_DESTRUCTOR_SYNC as c_uint
+        {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let obj = Arc::into_raw(Arc::new(UserObject {
+            ptr: ptr as _,
+            destroy,
+        }));
+        for _ in 1..initialRefcount {
+            unsafe { Arc::increment_strong_count(obj) }
+        }
+        unsafe { write(object_out, obj.cast_mut().cast()) }
+    })
+}
+
+pub unsafe extern "C" fn cuUserObjectRetain(object: CUuserObject, count: c_uint) -> CUresult {
+    result(|| {
+        let obj = unsafe { from_handle::<UserObject, _>(object) }? as *const UserObject;
+        for _ in 0..count {
+            unsafe { Arc::increment_strong_count(obj) }
+        }
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuUserObjectRelease(object: CUuserObject, count: c_uint) -> CUresult {
+    result(|| {
+        let obj = unsafe { from_handle::<UserObject, _>(object) }? as *const UserObject;
+        for _ in 0..count {
+            unsafe { Arc::decrement_strong_count(obj) }
+        }
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuGraphRetainUserObject(
+    graph: CUgraph,
+    object: CUuserObject,
+    count: c_uint,
+    flags: c_uint,
+) -> CUresult {
+    result(|| {
+        let graph = unsafe { from_handle::<Graph, _>(graph) }?;
+        let obj = unsafe { from_handle::<UserObject, _>(object) }? as *const UserObject;
+        let move_ = CUuserObjectRetain_flags::CU_GRAPH_USER_OBJECT_MOVE as c_uint;
+        if flags & !move_ != 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let mut objects = graph.objects.lock().unwrap();
+        for _ in 0..count {
+            // 转移调用者的引用，否则增加一个引用
+            if flags & move_ == 0 {
+                unsafe { Arc::increment_strong_count(obj) }
+            }
+            objects.push(unsafe { Arc::from_raw(obj) })
+        }
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuGraphReleaseUserObject(
+    graph: CUgraph,
+    object: CUuserObject,
+    count: c_uint,
+) -> CUresult {
+    result(|| {
+        let graph = unsafe { from_handle::<Graph, _>(graph) }?;
+        let obj = unsafe { from_handle::<UserObject, _>(object) }? as *const UserObject;
+        let mut objects = graph.objects.lock().unwrap();
+        for _ in 0..count {
+            let i = objects
+                .iter()
+                .position(|o| Arc::as_ptr(o) == obj)
+                .ok_or(CUDA_ERROR_INVALID_VALUE)?;
+            objects.swap_remove(i);
+        }
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuGraphExecDestroy(hGraphExec: CUgraphExec) -> CUresult {
+    result(|| unsafe { drop_handle::<Exec, _>(hGraphExec) })
+}
+
+pub unsafe extern "C" fn cuGraphLaunch(hGraphExec: CUgraphExec, hStream: CUstream) -> CUresult {
+    result(|| {
+        let exec = unsafe { from_handle::<Exec, _>(hGraphExec) }?;
+        let ops = exec
+            .nodes
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|n| n.enabled)
+            .map(|n| n.op.clone())
+            .collect::<Vec<_>>();
+        stream::launch(hStream, &ops)
+    })
+}
+
+pub unsafe extern "C" fn cuGraphExecUpdate_v2(
+    hGraphExec: CUgraphExec,
+    hGraph: CUgraph,
+    resultInfo: *mut CUgraphExecUpdateResultInfo,
+) -> CUresult {
+    use CUgraphExecUpdateResult::*;
+    result(|| {
+        let exec = unsafe { from_handle::<Exec, _>(hGraphExec) }?;
+        let graph = unsafe { from_handle::<Graph, _>(hGraph) }?;
+        if resultInfo.is_null() {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let new = graph.exec_nodes();
+        let mut nodes = exec.nodes.lock().unwrap();
+        let mut info = CUgraphExecUpdateResultInfo {
+            result: CU_GRAPH_EXEC_UPDATE_SUCCESS,
+            errorNode: null_mut(),
+            errorFromNode: null_mut(),
+        };
+        if nodes.len() != new.len() {
+            info.result = CU_GRAPH_EXEC_UPDATE_ERROR_TOPOLOGY_CHANGED
+        } else if let Some((old, new, result)) = nodes
+            .iter()
+            .zip(&new)
+            .map(|(old, new)| (old, new, old.check_update(new)))
+            .find(|(.., result)| *result != CU_GRAPH_EXEC_UPDATE_SUCCESS)
+        {
+            info = CUgraphExecUpdateResultInfo {
+                result,
+                errorNode: new.node,
+                errorFromNode: old.node,
+            }
+        }
+        unsafe { write(resultInfo, info) }?;
+        if info.result != CU_GRAPH_EXEC_UPDATE_SUCCESS {
+            return Err(CUDA_ERROR_GRAPH_EXEC_UPDATE_FAILURE);
+        }
+        // 节点仍以实例化时的节点标识，启用状态不变
+        for (old, new) in nodes.iter_mut().zip(new) {
+            old.op = new.op
+        }
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuGraphExecKernelNodeSetParams_v2(
+    hGraphExec: CUgraphExec,
+    hNode: CUgraphNode,
+    _nodeParams: *const CUDA_KERNEL_NODE_PARAMS,
+) -> CUresult {
+    // 模拟驱动不能加载模块，不存在 kernel 节点
+    result(|| set_exec_op(hGraphExec, hNode, |_| Err(CUDA_ERROR_INVALID_VALUE)))
+}
+
+pub unsafe extern "C" fn cuGraphExecMemcpyNodeSetParams(
+    hGraphExec: CUgraphExec,
+    hNode: CUgraphNode,
+    copyParams: *const CUDA_MEMCPY3D,
+    _ctx: CUcontext,
+) -> CUresult {
+    result(|| {
+        let new = *unsafe { copyParams.as_ref() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        set_exec_op(hGraphExec, hNode, |op| match op {
+            // 不能改变存储的类型
+            Op::Memcpy(p)
+                if p.srcMemoryType == new.srcMemoryType && p.dstMemoryType == new.dstMemoryType =>
+            {
+                *p = new;
+                Ok(())
+            }
+            _ => Err(CUDA_ERROR_INVALID_VALUE),
+        })
+    })
+}
+
+pub unsafe extern "C" fn cuGraphExecMemsetNodeSetParams(
+    hGraphExec: CUgraphExec,
+    hNode: CUgraphNode,
+    memsetParams: *const CUDA_MEMSET_NODE_PARAMS,
+    _ctx: CUcontext,
+) -> CUresult {
+    result(|| {
+        let new = *unsafe { memsetParams.as_ref() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        set_exec_op(hGraphExec, hNode, |op| match op {
+            Op::Memset(p) => {
+                *p = new;
+                Ok(())
+            }
+            _ => Err(CUDA_ERROR_INVALID_VALUE),
+        })
+    })
+}
+
+pub unsafe extern "C" fn cuGraphExecHostNodeSetParams(
+    hGraphExec: CUgraphExec,
+    hNode: CUgraphNode,
+    nodeParams: *const CUDA_HOST_NODE_PARAMS,
+) -> CUresult {
+    result(|| {
+        let new = *unsafe { nodeParams.as_ref() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        if new.fn_.is_none() {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        set_exec_op(hGraphExec, hNode, |op| match op {
+            Op::Host(p) => {
+                *p = new;
+                Ok(())
+            }
+            _ => Err(CUDA_ERROR_INVALID_VALUE),
+        })
+    })
+}
+
+/// 查找执行图中能启用和禁用的节点。
+fn switchable_node(
+    hGraphExec: CUgraphExec,
+    hNode: CUgraphNode,
+    f: impl FnOnce(&mut bool) -> Result<(), CUresult>,
+) -> Result<(), CUresult> {
+    let exec = unsafe { from_handle::<Exec, _>(hGraphExec) }?;
+    let mut nodes = exec.nodes.lock().unwrap();
+    match nodes.iter_mut().find(|n| n.node == hNode) {
+        Some(ExecNode {
+            op: Op::Memcpy(_) | Op::Memset(_),
+            enabled,
+            ..
+        }) => f(enabled),
+        _ => Err(CUDA_ERROR_INVALID_VALUE),
+    }
+}
+
+pub unsafe extern "C" fn cuGraphNodeSetEnabled(
+    hGraphExec: CUgraphExec,
+    hNode: CUgraphNode,
+    isEnabled: c_uint,
+) -> CUresult {
+    result(|| {
+        switchable_node(hGraphExec, hNode, |enabled| {
+            *enabled = isEnabled != 0;
+            Ok(())
+        })
+    })
+}
+
+pub unsafe extern "C" fn cuGraphNodeGetEnabled(
+    hGraphExec: CUgraphExec,
+    hNode: CUgraphNode,
+    isEnabled: *mut c_uint,
+) -> CUresult {
+    result(|| {
+        switchable_node(hGraphExec, hNode, |enabled| unsafe {
+            write(isEnabled, *enabled as _)
+        })
+    })
+}
+
+pub unsafe extern "C" fn cuGraphConditionalHandleCreate(
+    pHandle_out: *mut CUgraphConditionalHandle,
+    hGraph: CUgraph,
+    ctx: CUcontext,
+    defaultLaunchValue: c_uint,
+    flags: c_uint,
+) -> CUresult {
+    result(|| {
+        let graph = unsafe { from_handle::<Graph, _>(hGraph) }?;
+        unsafe { from_handle::<driver::Context, _>(ctx) }
+            .map_err(|_| CUDA_ERROR_INVALID_CONTEXT)?;
+        let default = match flags {
+            0 => None,
+            CU_GRAPH_COND_ASSIGN_DEFAULT => Some(defaultLaunchValue),
+            _ => return Err(CUDA_ERROR_INVALID_VALUE),
+        };
+        let handle = Arc::new(CondHandle {
+            ctx: ctx as _,
+            default,
+        });
+        unsafe { write(pHandle_out, Arc::as_ptr(&handle) as _) }?;
+        graph.handles.lock().unwrap().push(handle);
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuGraphAddNode(
+    phGraphNode: *mut CUgraphNode,
+    hGraph: CUgraph,
+    dependencies: *const CUgraphNode,
+    numDependencies: usize,
+    nodeParams: *mut CUgraphNodeParams,
+) -> CUresult {
+    use CUgraphConditionalNodeType::*;
+    result(|| {
+        let p = unsafe { nodeParams.as_mut() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        // 模拟驱动只以通用接口添加条件节点
+        if p.type_ != CUgraphNodeType::CU_GRAPH_NODE_TYPE_CONDITIONAL {
+            return Err(CUDA_ERROR_NOT_SUPPORTED);
+        }
+        let graph = unsafe { from_handle::<Graph, _>(hGraph) }?;
+        let params = unsafe { &mut p.__bindgen_anon_1.conditional };
+        // 条件值句柄必须在同一个图上创建
+        let handle = graph
+            .handles
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|h| Arc::as_ptr(h) as CUgraphConditionalHandle == params.handle)
+            .cloned()
+            .ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        if handle.ctx != params.ctx as usize {
+            return Err(CUDA_ERROR_INVALID_CONTEXT);
+        }
+        let size = params.size as usize;
+        let valid = match params.type_ {
+            CU_GRAPH_COND_TYPE_IF => matches!(size, 1 | 2),
+            CU_GRAPH_COND_TYPE_WHILE => size == 1,
+            CU_GRAPH_COND_TYPE_SWITCH => size > 0,
+        };
+        if !valid {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let bodies = (0..size)
+            .map(|_| into_handle::<_, CUgraph_st>(Graph::default()) as usize)
+            .collect::<Box<[_]>>();
+        let graphs = bodies.as_ptr().cast::<CUgraph>().cast_mut();
+        let cond = Conditional {
+            handle,
+            type_: params.type_,
+            bodies,
+        };
+        unsafe {
+            add_node(
+                phGraphNode,
+                hGraph,
+                dependencies,
+                numDependencies,
+                Op::Conditional(Arc::new(cond)),
+            )
+        }?;
+        params.phGraph_out = graphs;
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuGraphAddKernelNode_v2(
+    _phGraphNode: *mut CUgraphNode,
+    hGraph: CUgraph,
+    _dependencies: *const CUgraphNode,
+    _numDependencies: usize,
+    nodeParams: *const CUDA_KERNEL_NODE_PARAMS,
+) -> CUresult {
+    result(|| {
+        unsafe { from_handle::<Graph, _>(hGraph) }?;
+        // 模拟驱动不能加载模块，不存在合法的函数
+        match unsafe { nodeParams.as_ref() } {
+            Some(p) if !p.func.is_null() => Err(CUDA_ERROR_INVALID_HANDLE),
+            _ => Err(CUDA_ERROR_INVALID_VALUE),
+        }
+    })
+}
+
+pub unsafe extern "C" fn cuGraphKernelNodeGetParams_v2(
+    hNode: CUgraphNode,
+    _nodeParams: *mut CUDA_KERNEL_NODE_PARAMS,
+) -> CUresult {
+    result(|| op_of(hNode).and(Err(CUDA_ERROR_INVALID_VALUE)))
+}
+
+pub unsafe extern "C" fn cuGraphAddMemcpyNode(
+    phGraphNode: *mut CUgraphNode,
+    hGraph: CUgraph,
+    dependencies: *const CUgraphNode,
+    numDependencies: usize,
+    copyParams: *const CUDA_MEMCPY3D,
+    _ctx: CUcontext,
+) -> CUresult {
+    result(|| {
+        let p = *unsafe { copyParams.as_ref() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        unsafe {
+            add_node(
+                phGraphNode,
+                hGraph,
+                dependencies,
+                numDependencies,
+                Op::Memcpy(p),
+            )
+        }
+    })
+}
+
+pub unsafe extern "C" fn cuGraphMemcpyNodeGetParams(
+    hNode: CUgraphNode,
+    nodeParams: *mut CUDA_MEMCPY3D,
+) -> CUresult {
+    result(|| match op_of(hNode)? {
+        Op::Memcpy(p) => unsafe { write(nodeParams, *p) },
+        _ => Err(CUDA_ERROR_INVALID_VALUE),
+    })
+}
+
+pub unsafe extern "C" fn cuGraphAddMemsetNode(
+    phGraphNode: *mut CUgraphNode,
+    hGraph: CUgraph,
+    dependencies: *const CUgraphNode,
+    numDependencies: usize,
+    memsetParams: *const CUDA_MEMSET_NODE_PARAMS,
+    _ctx: CUcontext,
+) -> CUresult {
+    result(|| {
+        let p = *unsafe { memsetParams.as_ref() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        unsafe {
+            add_node(
+                phGraphNode,
+                hGraph,
+                dependencies,
+                numDependencies,
+                Op::Memset(p),
+            )
+        }
+    })
+}
+
+pub unsafe extern "C" fn cuGraphAddHostNode(
+    phGraphNode: *mut CUgraphNode,
+    hGraph: CUgraph,
+    dependencies: *const CUgraphNode,
+    numDependencies: usize,
+    nodeParams: *const CUDA_HOST_NODE_PARAMS,
+) -> CUresult {
+    result(|| {
+        let p = *unsafe { nodeParams.as_ref() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        unsafe {
+            add_node(
+                phGraphNode,
+                hGraph,
+                dependencies,
+                numDependencies,
+                Op::Host(p),
+            )
+        }
+    })
+}
+
+pub unsafe extern "C" fn cuGraphAddMemAllocNode(
+    phGraphNode: *mut CUgraphNode,
+    hGraph: CUgraph,
+    dependencies: *const CUgraphNode,
+    numDependencies: usize,
+    nodeParams: *mut CUDA_MEM_ALLOC_NODE_PARAMS,
+) -> CUresult {
+    result(|| {
+        let p = unsafe { nodeParams.as_mut() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        let location = p.poolProps.location;
+        if location.type_ != CUmemLocationType::CU_MEM_LOCATION_TYPE_DEVICE {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let mem = Arc::new(GraphMem::new(location.id, p.bytesize)?);
+        let ptr = mem.ptr();
+        unsafe {
+            add_node(
+                phGraphNode,
+                hGraph,
+                dependencies,
+                numDependencies,
+                Op::MemAlloc(mem),
+            )
+        }?;
+        p.dptr = ptr;
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuGraphMemAllocNodeGetParams(
+    hNode: CUgraphNode,
+    params_out: *mut CUDA_MEM_ALLOC_NODE_PARAMS,
+) -> CUresult {
+    result(|| match op_of(hNode)? {
+        Op::MemAlloc(mem) => {
+            let mut p: CUDA_MEM_ALLOC_NODE_PARAMS = unsafe { std::mem::zeroed() };
+            p.poolProps.allocType = CUmemAllocationType::CU_MEM_ALLOCATION_TYPE_PINNED;
+            p.poolProps.handleTypes = CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_NONE;
+            p.poolProps.location = CUmemLocation {
+                type_: CUmemLocationType::CU_MEM_LOCATION_TYPE_DEVICE,
+                id: mem.dev,
+            };
+            p.accessDescs = null();
+            p.bytesize = mem.len;
+            p.dptr = mem.ptr();
+            unsafe { write(params_out, p) }
+        }
+        _ => Err(CUDA_ERROR_INVALID_VALUE),
+    })
+}
+
+pub unsafe extern "C" fn cuGraphAddMemFreeNode(
+    phGraphNode: *mut CUgraphNode,
+    hGraph: CUgraph,
+    dependencies: *const CUgraphNode,
+    numDependencies: usize,
+    dptr: CUdeviceptr,
+) -> CUresult {
+    result(|| unsafe {
+        add_node(
+            phGraphNode,
+            hGraph,
+            dependencies,
+            numDependencies,
+            Op::MemFree(dptr),
+        )
+    })
+}
+
+pub unsafe extern "C" fn cuGraphMemFreeNodeGetParams(
+    hNode: CUgraphNode,
+    dptr_out: *mut CUdeviceptr,
+) -> CUresult {
+    result(|| match op_of(hNode)? {
+        &Op::MemFree(ptr) => unsafe { write(dptr_out, ptr) },
+        _ => Err(CUDA_ERROR_INVALID_VALUE),
+    })
+}
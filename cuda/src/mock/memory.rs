@@ -0,0 +1,1393 @@
+use super::{driver::device, *};
+use std::{
+    collections::BTreeMap,
+    ffi::{c_int, c_uchar, c_uint, c_ulonglong, c_ushort, c_void},
+    ptr::null_mut,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicI32, AtomicU64, Ordering::SeqCst},
+    },
+};
+
+/// 设备可访问的地址区间：起始地址 -> 区间。
+static REGIONS: Mutex<BTreeMap<usize, Region>> = Mutex::new(BTreeMap::new());
+/// 锁页的主机内存区间：起始地址 -> 区间。
+static LOCKED: Mutex<BTreeMap<usize, Locked>> = Mutex::new(BTreeMap::new());
+
+pub(super) struct Region {
+    pub(super) len: usize,
+    pub(super) kind: RegionKind,
+}
+
+pub(super) enum RegionKind {
+    /// `cuMemAlloc` 等分配的存储，由 `cuMemFree` 释放。
+    Alloc(CUdevice),
+    /// 从存储池分配的存储。
+    Pooled(&'static MemPool),
+    /// 从导入的存储池导入的存储，映射到导出的存储的物理页。
+    Imported,
+    /// 图分配节点的存储，随节点释放。
+    Graph,
+    /// 统一内存，主机和设备都能访问，不计入设备的用量。
+    Managed,
+    /// 映射到虚地址的物理页，各设备的访问权限由 `cuMemSetAccess` 设置。
+    Mapped(Access),
+}
+
+/// 各设备访问映射的权限。
+pub(super) type Access = [CUmemAccess_flags; driver::DEVICE_COUNT as usize];
+
+/// 任一设备具有的最高访问权限。
+pub(super) fn widest(access: &Access) -> CUmemAccess_flags {
+    use CUmemAccess_flags::*;
+    access
+        .iter()
+        .fold(CU_MEM_ACCESS_FLAGS_PROT_NONE, |acc, &flags| {
+            match (acc, flags) {
+                (CU_MEM_ACCESS_FLAGS_PROT_READWRITE, _)
+                | (_, CU_MEM_ACCESS_FLAGS_PROT_READWRITE) => CU_MEM_ACCESS_FLAGS_PROT_READWRITE,
+                (CU_MEM_ACCESS_FLAGS_PROT_READ, _) | (_, CU_MEM_ACCESS_FLAGS_PROT_READ) => {
+                    CU_MEM_ACCESS_FLAGS_PROT_READ
+                }
+                _ => CU_MEM_ACCESS_FLAGS_PROT_NONE,
+            }
+        })
+}
+
+struct Locked {
+    len: usize,
+    /// 由 `cuMemHostAlloc` 分配，而不是注册的主机内存。
+    owned: bool,
+}
+
+/// 分配按需提交的主机内存作为模拟的设备存储。
+pub(super) fn map_anonymous(len: usize) -> Result<usize, CUresult> {
+    let ptr = unsafe {
+        libc::mmap(
+            null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE,
+            -1,
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        Err(CUDA_ERROR_OUT_OF_MEMORY)
+    } else {
+        Ok(ptr as _)
+    }
+}
+
+/// 分配可以重复映射的主机内存，用于可共享的存储。
+fn map_shared(len: usize) -> Result<usize, CUresult> {
+    let ptr = unsafe {
+        libc::mmap(
+            null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE,
+            -1,
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        Err(CUDA_ERROR_OUT_OF_MEMORY)
+    } else {
+        Ok(ptr as _)
+    }
+}
+
+pub(super) fn unmap_anonymous(ptr: usize, len: usize) -> Result<(), CUresult> {
+    if unsafe { libc::munmap(ptr as _, len) } == 0 {
+        Ok(())
+    } else {
+        Err(CUDA_ERROR_INVALID_VALUE)
+    }
+}
+
+pub(super) fn insert_region(ptr: usize, region: Region) {
+    REGIONS.lock().unwrap().insert(ptr, region);
+}
+
+pub(super) fn remove_region(ptr: usize) -> Option<Region> {
+    REGIONS.lock().unwrap().remove(&ptr)
+}
+
+/// `[ptr, ptr + len)` 是否与已有的区间重叠。
+pub(super) fn overlaps(ptr: usize, len: usize) -> bool {
+    let regions = REGIONS.lock().unwrap();
+    regions
+        .range(..ptr + len)
+        .next_back()
+        .is_some_and(|(&start, region)| ptr < start + region.len)
+}
+
+/// 在 `[ptr, ptr + len)` 范围内的区间上执行操作。
+pub(super) fn with_regions<T>(
+    ptr: usize,
+    len: usize,
+    f: impl FnOnce(&mut dyn Iterator<Item = (&usize, &mut Region)>) -> T,
+) -> T {
+    let mut regions = REGIONS.lock().unwrap();
+    f(&mut regions.range_mut(ptr..ptr + len))
+}
+
+/// 查找包含 `ptr` 的区间。
+pub(super) fn find_region<T>(ptr: usize, f: impl FnOnce(&Region) -> T) -> Option<T> {
+    let regions = REGIONS.lock().unwrap();
+    regions
+        .range(..=ptr)
+        .next_back()
+        .filter(|(start, region)| ptr < *start + region.len)
+        .map(|(_, region)| f(region))
+}
+
+/// 检查设备能否访问 `[ptr, ptr + len)`。
+///
+/// 锁页的主机内存在统一地址空间中，设备也能访问。
+/// 虚地址上相邻的映射可以一起访问。
+/// 模拟驱动不区分发起访问的设备，映射的物理页按任一设备具有的最高权限检查。
+pub(super) fn check_device(ptr: CUdeviceptr, len: usize, write: bool) -> Result<(), CUresult> {
+    if len == 0 {
+        return Ok(());
+    }
+    let ptr = ptr as usize;
+    let end = ptr.checked_add(len).ok_or(CUDA_ERROR_INVALID_VALUE)?;
+    {
+        let regions = REGIONS.lock().unwrap();
+        if let Some((&start, region)) = regions.range(..=ptr).next_back()
+            && ptr < start + region.len
+        {
+            use CUmemAccess_flags::*;
+            if !matches!(region.kind, RegionKind::Mapped(_)) {
+                return if end <= start + region.len {
+                    Ok(())
+                } else {
+                    Err(CUDA_ERROR_INVALID_VALUE)
+                };
+            }
+            let mut cursor = start;
+            for (&start, region) in regions.range(start..) {
+                let RegionKind::Mapped(access) = &region.kind else {
+                    break;
+                };
+                if start != cursor {
+                    break;
+                }
+                match widest(access) {
+                    CU_MEM_ACCESS_FLAGS_PROT_READWRITE => {}
+                    CU_MEM_ACCESS_FLAGS_PROT_READ if !write => {}
+                    _ => break,
+                }
+                cursor += region.len;
+                if end <= cursor {
+                    return Ok(());
+                }
+            }
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+    }
+    let locked = LOCKED.lock().unwrap();
+    match locked.range(..=ptr).next_back() {
+        Some((&start, locked)) if end <= start + locked.len => Ok(()),
+        _ => Err(CUDA_ERROR_INVALID_VALUE),
+    }
+}
+
+/// 在设备上分配存储。
+pub(super) fn alloc(dev: CUdevice, len: usize) -> Result<CUdeviceptr, CUresult> {
+    alloc_region(dev, len, RegionKind::Alloc(dev))
+}
+
+fn alloc_region(dev: CUdevice, len: usize, kind: RegionKind) -> Result<CUdeviceptr, CUresult> {
+    if len == 0 {
+        return Err(CUDA_ERROR_INVALID_VALUE);
+    }
+    let device = device(dev)?;
+    device.reserve(len)?;
+    let ptr = match kind {
+        RegionKind::Pooled(pool) if pool.shareable => map_shared(len),
+        _ => map_anonymous(len),
+    }
+    .inspect_err(|_| device.release(len))?;
+    insert_region(ptr, Region { len, kind });
+    Ok(ptr as _)
+}
+
+/// 释放 [`alloc`] 分配的存储。
+pub(super) fn free(ptr: CUdeviceptr) -> Result<(), CUresult> {
+    let ptr = ptr as usize;
+    let mut regions = REGIONS.lock().unwrap();
+    let (len, dev) = match regions.get(&ptr) {
+        Some(&Region {
+            len,
+            kind: RegionKind::Alloc(dev),
+        }) => (len, Some(dev)),
+        Some(&Region {
+            len,
+            kind: RegionKind::Pooled(pool),
+        }) => {
+            pool.free(len as _);
+            (len, Some(pool.dev))
+        }
+        Some(&Region {
+            len,
+            kind: RegionKind::Imported | RegionKind::Managed,
+        }) => (len, None),
+        _ => return Err(CUDA_ERROR_INVALID_VALUE),
+    };
+    regions.remove(&ptr);
+    drop(regions);
+    unmap_anonymous(ptr, len)?;
+    if let Some(dev) = dev {
+        device(dev)?.release(len)
+    }
+    Ok(())
+}
+
+/// 构造一维拷贝的参数。
+pub(super) fn memcpy_1d(
+    dst: (CUmemorytype, CUdeviceptr),
+    src: (CUmemorytype, CUdeviceptr),
+    len: usize,
+) -> CUDA_MEMCPY3D {
+    let (srcMemoryType, src) = src;
+    let (dstMemoryType, dst) = dst;
+    CUDA_MEMCPY3D {
+        srcXInBytes: 0,
+        srcY: 0,
+        srcZ: 0,
+        srcLOD: 0,
+        srcMemoryType,
+        srcHost: src as _,
+        srcDevice: src,
+        srcArray: null_mut(),
+        reserved0: null_mut(),
+        srcPitch: 0,
+        srcHeight: 0,
+        dstXInBytes: 0,
+        dstY: 0,
+        dstZ: 0,
+        dstLOD: 0,
+        dstMemoryType,
+        dstHost: dst as _,
+        dstDevice: dst,
+        dstArray: null_mut(),
+        reserved1: null_mut(),
+        dstPitch: 0,
+        dstHeight: 0,
+        WidthInBytes: len,
+        Height: 1,
+        Depth: 1,
+    }
+}
+
+/// 拷贝的一侧。
+struct Side {
+    base: usize,
+    pitch: usize,
+    height: usize,
+    origin: (usize, usize, usize),
+}
+
+impl Side {
+    fn new(
+        ty: CUmemorytype,
+        host: usize,
+        device: CUdeviceptr,
+        origin: (usize, usize, usize),
+        (pitch, height): (usize, usize),
+        p: &CUDA_MEMCPY3D,
+        write: bool,
+    ) -> Result<Self, CUresult> {
+        use CUmemorytype::*;
+        let side = Self {
+            base: 0,
+            pitch: if pitch == 0 { p.WidthInBytes } else { pitch },
+            height: if height == 0 { p.Height } else { height },
+            origin,
+        };
+        if side.pitch < p.WidthInBytes || (p.Depth > 1 && side.height < p.Height) {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let len = side.offset(p.WidthInBytes, p.Height - 1, p.Depth - 1) - side.offset(0, 0, 0);
+        let base = match ty {
+            CU_MEMORYTYPE_HOST => host,
+            CU_MEMORYTYPE_DEVICE => {
+                check_device(device + side.offset(0, 0, 0) as CUdeviceptr, len, write)?;
+                device as _
+            }
+            CU_MEMORYTYPE_UNIFIED => device as _,
+            _ => return Err(CUDA_ERROR_NOT_SUPPORTED),
+        };
+        if base == 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        Ok(Self { base, ..side })
+    }
+
+    fn offset(&self, x: usize, y: usize, z: usize) -> usize {
+        let (x0, y0, z0) = self.origin;
+        x0 + x + ((z0 + z) * self.height + y0 + y) * self.pitch
+    }
+}
+
+/// 执行三维拷贝。
+pub(super) fn memcpy_3d(p: &CUDA_MEMCPY3D) -> Result<(), CUresult> {
+    if p.WidthInBytes == 0 || p.Height == 0 || p.Depth == 0 {
+        return Ok(());
+    }
+    let src = Side::new(
+        p.srcMemoryType,
+        p.srcHost as _,
+        p.srcDevice,
+        (p.srcXInBytes, p.srcY, p.srcZ),
+        (p.srcPitch, p.srcHeight),
+        p,
+        false,
+    )?;
+    let dst = Side::new(
+        p.dstMemoryType,
+        p.dstHost as _,
+        p.dstDevice,
+        (p.dstXInBytes, p.dstY, p.dstZ),
+        (p.dstPitch, p.dstHeight),
+        p,
+        true,
+    )?;
+    for z in 0..p.Depth {
+        for y in 0..p.Height {
+            let src = (src.base + src.offset(0, y, z)) as *const u8;
+            let dst = (dst.base + dst.offset(0, y, z)) as *mut u8;
+            unsafe { dst.copy_from(src, p.WidthInBytes) }
+        }
+    }
+    Ok(())
+}
+
+/// 执行二维填充。
+pub(super) fn memset_2d(p: &CUDA_MEMSET_NODE_PARAMS) -> Result<(), CUresult> {
+    let &CUDA_MEMSET_NODE_PARAMS {
+        dst,
+        pitch,
+        value,
+        elementSize,
+        width,
+        height,
+    } = p;
+    let align = elementSize as usize;
+    if !matches!(align, 1 | 2 | 4)
+        || !(dst as usize).is_multiple_of(align)
+        || !pitch.is_multiple_of(align)
+    {
+        return Err(CUDA_ERROR_INVALID_VALUE);
+    }
+    if width == 0 || height == 0 {
+        return Ok(());
+    }
+    let row = width * elementSize as usize;
+    let pitch = if height == 1 { row } else { pitch };
+    if pitch < row {
+        return Err(CUDA_ERROR_INVALID_VALUE);
+    }
+    check_device(dst, pitch * (height - 1) + row, true)?;
+    for y in 0..height {
+        let ptr = dst as usize + y * pitch;
+        unsafe {
+            match elementSize {
+                1 => (ptr as *mut u8).write_bytes(value as _, width),
+                2 => std::slice::from_raw_parts_mut(ptr as *mut u16, width).fill(value as _),
+                4 => std::slice::from_raw_parts_mut(ptr as *mut u32, width).fill(value),
+                _ => unreachable!(),
+            }
+        }
+    }
+    Ok(())
+}
+
+pub unsafe extern "C" fn cuMemGetInfo_v2(free: *mut usize, total: *mut usize) -> CUresult {
+    result(|| {
+        let used = device(driver::current_device()?)?.used.load(SeqCst);
+        unsafe {
+            write(free, driver::TOTAL_MEMORY - used)?;
+            write(total, driver::TOTAL_MEMORY)
+        }
+    })
+}
+
+pub unsafe extern "C" fn cuMemAlloc_v2(dptr: *mut CUdeviceptr, bytesize: usize) -> CUresult {
+    result(|| {
+        if dptr.is_null() {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let ptr = alloc(driver::current_device()?, bytesize)?;
+        unsafe { write(dptr, ptr) }
+    })
+}
+
+pub unsafe extern "C" fn cuMemFree_v2(dptr: CUdeviceptr) -> CUresult {
+    result(|| {
+        driver::current_device()?;
+        free(dptr)
+    })
+}
+
+/// 模拟的行跨度对齐。
+const PITCH_ALIGN: usize = 512;
+
+pub unsafe extern "C" fn cuMemAllocPitch_v2(
+    dptr: *mut CUdeviceptr,
+    pPitch: *mut usize,
+    WidthInBytes: usize,
+    Height: usize,
+    ElementSizeBytes: c_uint,
+) -> CUresult {
+    result(|| {
+        if dptr.is_null()
+            || pPitch.is_null()
+            || WidthInBytes == 0
+            || Height == 0
+            || !matches!(ElementSizeBytes, 4 | 8 | 16)
+        {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let pitch = WidthInBytes.next_multiple_of(PITCH_ALIGN);
+        let ptr = alloc(driver::current_device()?, pitch * Height)?;
+        unsafe {
+            write(dptr, ptr)?;
+            write(pPitch, pitch)
+        }
+    })
+}
+
+pub unsafe extern "C" fn cuMemHostAlloc(
+    pp: *mut *mut c_void,
+    bytesize: usize,
+    Flags: c_uint,
+) -> CUresult {
+    result(|| {
+        driver::current_device()?;
+        let valid =
+            CU_MEMHOSTALLOC_PORTABLE | CU_MEMHOSTALLOC_DEVICEMAP | CU_MEMHOSTALLOC_WRITECOMBINED;
+        if pp.is_null() || bytesize == 0 || Flags & !valid != 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let ptr = map_anonymous(bytesize)?;
+        LOCKED.lock().unwrap().insert(
+            ptr,
+            Locked {
+                len: bytesize,
+                owned: true,
+            },
+        );
+        unsafe { write(pp, ptr as _) }
+    })
+}
+
+pub unsafe extern "C" fn cuMemFreeHost(p: *mut c_void) -> CUresult {
+    result(|| {
+        if p.is_null() {
+            return Ok(());
+        }
+        let mut locked = LOCKED.lock().unwrap();
+        match locked.get(&(p as usize)) {
+            Some(&Locked { len, owned: true }) => {
+                locked.remove(&(p as usize));
+                unmap_anonymous(p as _, len)
+            }
+            _ => Err(CUDA_ERROR_INVALID_VALUE),
+        }
+    })
+}
+
+pub unsafe extern "C" fn cuMemHostRegister_v2(
+    p: *mut c_void,
+    bytesize: usize,
+    Flags: c_uint,
+) -> CUresult {
+    result(|| {
+        driver::current_device()?;
+        let valid = CU_MEMHOSTREGISTER_PORTABLE
+            | CU_MEMHOSTREGISTER_DEVICEMAP
+            | CU_MEMHOSTREGISTER_READ_ONLY;
+        if Flags & CU_MEMHOSTREGISTER_IOMEMORY != 0 {
+            return Err(CUDA_ERROR_NOT_SUPPORTED);
+        }
+        if p.is_null() || bytesize == 0 || Flags & !valid != 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let ptr = p as usize;
+        let mut locked = LOCKED.lock().unwrap();
+        let overlapped = locked
+            .range(..ptr + bytesize)
+            .next_back()
+            .is_some_and(|(&start, locked)| ptr < start + locked.len);
+        if overlapped {
+            return Err(CUDA_ERROR_HOST_MEMORY_ALREADY_REGISTERED);
+        }
+        locked.insert(
+            ptr,
+            Locked {
+                len: bytesize,
+                owned: false,
+            },
+        );
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuMemHostUnregister(p: *mut c_void) -> CUresult {
+    result(|| {
+        let mut locked = LOCKED.lock().unwrap();
+        match locked.get(&(p as usize)) {
+            Some(Locked { owned: false, .. }) => {
+                locked.remove(&(p as usize));
+                Ok(())
+            }
+            _ => Err(CUDA_ERROR_HOST_MEMORY_NOT_REGISTERED),
+        }
+    })
+}
+
+pub unsafe extern "C" fn cuMemAllocManaged(
+    dptr: *mut CUdeviceptr,
+    bytesize: usize,
+    flags: c_uint,
+) -> CUresult {
+    use CUmemAttach_flags::*;
+    result(|| {
+        driver::current_device()?;
+        let valid = [CU_MEM_ATTACH_GLOBAL as c_uint, CU_MEM_ATTACH_HOST as c_uint];
+        if dptr.is_null() || bytesize == 0 || !valid.contains(&flags) {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let ptr = map_anonymous(bytesize)?;
+        let kind = RegionKind::Managed;
+        insert_region(
+            ptr,
+            Region {
+                len: bytesize,
+                kind,
+            },
+        );
+        unsafe { write(dptr, ptr as _) }
+    })
+}
+
+/// 检查 `[ptr, ptr + len)` 在同一块统一内存中，`dev` 是设备或主机。
+fn check_managed(ptr: CUdeviceptr, len: usize, dev: CUdevice) -> Result<(), CUresult> {
+    if dev != CU_DEVICE_CPU {
+        device(dev).map_err(|_| CUDA_ERROR_INVALID_DEVICE)?;
+    }
+    let ptr = ptr as usize;
+    let regions = REGIONS.lock().unwrap();
+    match regions.range(..=ptr).next_back() {
+        Some((
+            &start,
+            &Region {
+                len: region,
+                kind: RegionKind::Managed,
+            },
+        )) if len > 0 && ptr + len <= start + region => Ok(()),
+        _ => Err(CUDA_ERROR_INVALID_VALUE),
+    }
+}
+
+/// 模拟的统一内存始终在主机内存中，预取只检查参数。
+pub unsafe extern "C" fn cuMemPrefetchAsync(
+    devPtr: CUdeviceptr,
+    count: usize,
+    dstDevice: CUdevice,
+    hStream: CUstream,
+) -> CUresult {
+    result(|| {
+        driver::current_device()?;
+        stream::forbid_capture(hStream)?;
+        check_managed(devPtr, count, dstDevice)
+    })
+}
+
+pub unsafe extern "C" fn cuMemAdvise(
+    devPtr: CUdeviceptr,
+    count: usize,
+    advice: CUmem_advise,
+    device: CUdevice,
+) -> CUresult {
+    use CUmem_advise::*;
+    result(|| match advice {
+        // 只读建议不使用设备参数
+        CU_MEM_ADVISE_SET_READ_MOSTLY | CU_MEM_ADVISE_UNSET_READ_MOSTLY => {
+            check_managed(devPtr, count, CU_DEVICE_CPU)
+        }
+        _ => check_managed(devPtr, count, device),
+    })
+}
+
+/// 锁页的主机内存在统一地址空间中，设备指针与主机指针相同。
+pub unsafe extern "C" fn cuMemHostGetDevicePointer_v2(
+    pdptr: *mut CUdeviceptr,
+    p: *mut c_void,
+    Flags: c_uint,
+) -> CUresult {
+    result(|| {
+        driver::current_device()?;
+        if Flags != 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let ptr = p as usize;
+        let locked = LOCKED.lock().unwrap();
+        match locked.range(..=ptr).next_back() {
+            Some((&start, locked)) if ptr < start + locked.len => unsafe { write(pdptr, ptr as _) },
+            _ => Err(CUDA_ERROR_INVALID_VALUE),
+        }
+    })
+}
+
+use CUmemorytype::{CU_MEMORYTYPE_DEVICE as DEVICE, CU_MEMORYTYPE_HOST as HOST};
+
+pub unsafe extern "C" fn cuMemcpyHtoD_v2(
+    dstDevice: CUdeviceptr,
+    srcHost: *const c_void,
+    ByteCount: usize,
+) -> CUresult {
+    result(|| {
+        driver::current_device()?;
+        memcpy_3d(&memcpy_1d(
+            (DEVICE, dstDevice),
+            (HOST, srcHost as _),
+            ByteCount,
+        ))
+    })
+}
+
+pub unsafe extern "C" fn cuMemcpyDtoH_v2(
+    dstHost: *mut c_void,
+    srcDevice: CUdeviceptr,
+    ByteCount: usize,
+) -> CUresult {
+    result(|| {
+        driver::current_device()?;
+        memcpy_3d(&memcpy_1d(
+            (HOST, dstHost as _),
+            (DEVICE, srcDevice),
+            ByteCount,
+        ))
+    })
+}
+
+pub unsafe extern "C" fn cuMemcpyDtoD_v2(
+    dstDevice: CUdeviceptr,
+    srcDevice: CUdeviceptr,
+    ByteCount: usize,
+) -> CUresult {
+    result(|| {
+        driver::current_device()?;
+        memcpy_3d(&memcpy_1d(
+            (DEVICE, dstDevice),
+            (DEVICE, srcDevice),
+            ByteCount,
+        ))
+    })
+}
+
+pub unsafe extern "C" fn cuMemcpyHtoDAsync_v2(
+    dstDevice: CUdeviceptr,
+    srcHost: *const c_void,
+    ByteCount: usize,
+    hStream: CUstream,
+) -> CUresult {
+    result(|| {
+        let p = memcpy_1d((DEVICE, dstDevice), (HOST, srcHost as _), ByteCount);
+        stream::submit(hStream, Op::Memcpy(p))
+    })
+}
+
+pub unsafe extern "C" fn cuMemcpyDtoHAsync_v2(
+    dstHost: *mut c_void,
+    srcDevice: CUdeviceptr,
+    ByteCount: usize,
+    hStream: CUstream,
+) -> CUresult {
+    result(|| {
+        let p = memcpy_1d((HOST, dstHost as _), (DEVICE, srcDevice), ByteCount);
+        stream::submit(hStream, Op::Memcpy(p))
+    })
+}
+
+pub unsafe extern "C" fn cuMemcpyDtoDAsync_v2(
+    dstDevice: CUdeviceptr,
+    srcDevice: CUdeviceptr,
+    ByteCount: usize,
+    hStream: CUstream,
+) -> CUresult {
+    result(|| {
+        let p = memcpy_1d((DEVICE, dstDevice), (DEVICE, srcDevice), ByteCount);
+        stream::submit(hStream, Op::Memcpy(p))
+    })
+}
+
+pub unsafe extern "C" fn cuMemcpyPeerAsync(
+    dstDevice: CUdeviceptr,
+    dstContext: CUcontext,
+    srcDevice: CUdeviceptr,
+    srcContext: CUcontext,
+    ByteCount: usize,
+    hStream: CUstream,
+) -> CUresult {
+    result(|| {
+        // 模拟的设备共享主机内存，不需要启用对等访问
+        for ctx in [dstContext, srcContext] {
+            unsafe { from_handle::<driver::Context, _>(ctx) }
+                .map_err(|_| CUDA_ERROR_INVALID_CONTEXT)?;
+        }
+        let p = memcpy_1d((DEVICE, dstDevice), (DEVICE, srcDevice), ByteCount);
+        stream::submit(hStream, Op::Memcpy(p))
+    })
+}
+
+pub unsafe extern "C" fn cuMemcpy2DAsync_v2(
+    pCopy: *const CUDA_MEMCPY2D,
+    hStream: CUstream,
+) -> CUresult {
+    result(|| {
+        let p = unsafe { pCopy.as_ref() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        let p = CUDA_MEMCPY3D {
+            srcXInBytes: p.srcXInBytes,
+            srcY: p.srcY,
+            srcZ: 0,
+            srcLOD: 0,
+            srcMemoryType: p.srcMemoryType,
+            srcHost: p.srcHost,
+            srcDevice: p.srcDevice,
+            srcArray: p.srcArray,
+            reserved0: null_mut(),
+            srcPitch: p.srcPitch,
+            srcHeight: 0,
+            dstXInBytes: p.dstXInBytes,
+            dstY: p.dstY,
+            dstZ: 0,
+            dstLOD: 0,
+            dstMemoryType: p.dstMemoryType,
+            dstHost: p.dstHost,
+            dstDevice: p.dstDevice,
+            dstArray: p.dstArray,
+            reserved1: null_mut(),
+            dstPitch: p.dstPitch,
+            dstHeight: 0,
+            WidthInBytes: p.WidthInBytes,
+            Height: p.Height,
+            Depth: 1,
+        };
+        stream::submit(hStream, Op::Memcpy(p))
+    })
+}
+
+pub unsafe extern "C" fn cuMemcpy3DAsync_v2(
+    pCopy: *const CUDA_MEMCPY3D,
+    hStream: CUstream,
+) -> CUresult {
+    result(|| {
+        let p = unsafe { pCopy.as_ref() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        stream::submit(hStream, Op::Memcpy(*p))
+    })
+}
+
+/// 构造填充参数。
+fn memset_params(
+    dst: CUdeviceptr,
+    pitch: usize,
+    value: c_uint,
+    elementSize: c_uint,
+    width: usize,
+    height: usize,
+) -> CUDA_MEMSET_NODE_PARAMS {
+    CUDA_MEMSET_NODE_PARAMS {
+        dst,
+        pitch,
+        value,
+        elementSize,
+        width,
+        height,
+    }
+}
+
+pub unsafe extern "C" fn cuMemsetD8_v2(dstDevice: CUdeviceptr, uc: c_uchar, N: usize) -> CUresult {
+    result(|| {
+        driver::current_device()?;
+        memset_2d(&memset_params(dstDevice, 0, uc as _, 1, N, 1))
+    })
+}
+
+pub unsafe extern "C" fn cuMemsetD2D8_v2(
+    dstDevice: CUdeviceptr,
+    dstPitch: usize,
+    uc: c_uchar,
+    Width: usize,
+    Height: usize,
+) -> CUresult {
+    result(|| {
+        driver::current_device()?;
+        memset_2d(&memset_params(
+            dstDevice, dstPitch, uc as _, 1, Width, Height,
+        ))
+    })
+}
+
+pub unsafe extern "C" fn cuMemsetD8Async(
+    dstDevice: CUdeviceptr,
+    uc: c_uchar,
+    N: usize,
+    hStream: CUstream,
+) -> CUresult {
+    result(|| {
+        let p = memset_params(dstDevice, 0, uc as _, 1, N, 1);
+        stream::submit(hStream, Op::Memset(p))
+    })
+}
+
+pub unsafe extern "C" fn cuMemsetD2D8Async(
+    dstDevice: CUdeviceptr,
+    dstPitch: usize,
+    uc: c_uchar,
+    Width: usize,
+    Height: usize,
+    hStream: CUstream,
+) -> CUresult {
+    result(|| {
+        let p = memset_params(dstDevice, dstPitch, uc as _, 1, Width, Height);
+        stream::submit(hStream, Op::Memset(p))
+    })
+}
+
+pub unsafe extern "C" fn cuMemsetD16_v2(
+    dstDevice: CUdeviceptr,
+    us: c_ushort,
+    N: usize,
+) -> CUresult {
+    result(|| {
+        driver::current_device()?;
+        memset_2d(&memset_params(dstDevice, 0, us as _, 2, N, 1))
+    })
+}
+
+pub unsafe extern "C" fn cuMemsetD2D16_v2(
+    dstDevice: CUdeviceptr,
+    dstPitch: usize,
+    us: c_ushort,
+    Width: usize,
+    Height: usize,
+) -> CUresult {
+    result(|| {
+        driver::current_device()?;
+        memset_2d(&memset_params(
+            dstDevice, dstPitch, us as _, 2, Width, Height,
+        ))
+    })
+}
+
+pub unsafe extern "C" fn cuMemsetD16Async(
+    dstDevice: CUdeviceptr,
+    us: c_ushort,
+    N: usize,
+    hStream: CUstream,
+) -> CUresult {
+    result(|| {
+        let p = memset_params(dstDevice, 0, us as _, 2, N, 1);
+        stream::submit(hStream, Op::Memset(p))
+    })
+}
+
+pub unsafe extern "C" fn cuMemsetD2D16Async(
+    dstDevice: CUdeviceptr,
+    dstPitch: usize,
+    us: c_ushort,
+    Width: usize,
+    Height: usize,
+    hStream: CUstream,
+) -> CUresult {
+    result(|| {
+        let p = memset_params(dstDevice, dstPitch, us as _, 2, Width, Height);
+        stream::submit(hStream, Op::Memset(p))
+    })
+}
+
+pub unsafe extern "C" fn cuMemsetD32_v2(dstDevice: CUdeviceptr, ui: c_uint, N: usize) -> CUresult {
+    result(|| {
+        driver::current_device()?;
+        memset_2d(&memset_params(dstDevice, 0, ui as _, 4, N, 1))
+    })
+}
+
+pub unsafe extern "C" fn cuMemsetD2D32_v2(
+    dstDevice: CUdeviceptr,
+    dstPitch: usize,
+    ui: c_uint,
+    Width: usize,
+    Height: usize,
+) -> CUresult {
+    result(|| {
+        driver::current_device()?;
+        memset_2d(&memset_params(
+            dstDevice, dstPitch, ui as _, 4, Width, Height,
+        ))
+    })
+}
+
+pub unsafe extern "C" fn cuMemsetD32Async(
+    dstDevice: CUdeviceptr,
+    ui: c_uint,
+    N: usize,
+    hStream: CUstream,
+) -> CUresult {
+    result(|| {
+        let p = memset_params(dstDevice, 0, ui as _, 4, N, 1);
+        stream::submit(hStream, Op::Memset(p))
+    })
+}
+
+pub unsafe extern "C" fn cuMemsetD2D32Async(
+    dstDevice: CUdeviceptr,
+    dstPitch: usize,
+    ui: c_uint,
+    Width: usize,
+    Height: usize,
+    hStream: CUstream,
+) -> CUresult {
+    result(|| {
+        let p = memset_params(dstDevice, dstPitch, ui as _, 4, Width, Height);
+        stream::submit(hStream, Op::Memset(p))
+    })
+}
+
+pub unsafe extern "C" fn cuMemAllocAsync(
+    dptr: *mut CUdeviceptr,
+    bytesize: usize,
+    hStream: CUstream,
+) -> CUresult {
+    result(|| {
+        if dptr.is_null() {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let dev = stream::device_of(hStream)?;
+        let ptr = if stream::capturing(hStream)? {
+            let mem = GraphMem::new(dev, bytesize)?;
+            let ptr = mem.ptr();
+            stream::capture(hStream, Op::MemAlloc(mem.into()))?;
+            ptr
+        } else {
+            alloc_from(&device(dev)?.pool, bytesize)?
+        };
+        unsafe { write(dptr, ptr) }
+    })
+}
+
+pub unsafe extern "C" fn cuMemFreeAsync(dptr: CUdeviceptr, hStream: CUstream) -> CUresult {
+    result(|| {
+        if stream::capturing(hStream)? {
+            stream::capture(hStream, Op::MemFree(dptr)).map(|_| ())
+        } else {
+            free(dptr)
+        }
+    })
+}
+
+/// 存储池。
+pub(super) struct MemPool {
+    dev: CUdevice,
+    /// 由 `cuMemPoolCreate` 创建或导入，而不是设备的默认存储池。
+    owned: bool,
+    /// 可以导出到其他进程。
+    shareable: bool,
+    /// 导入的存储池对应的导出的存储池，导入的存储池不能分配存储。
+    origin: Option<&'static MemPool>,
+    destroyed: AtomicBool,
+    max_size: usize,
+    release_threshold: AtomicU64,
+    reuse: [AtomicI32; 3],
+    usage: Mutex<Usage>,
+    /// 其他设备的访问权限。
+    access: Mutex<[CUmemAccess_flags; driver::DEVICE_COUNT as usize]>,
+}
+
+/// 存储池的用量，单位为字节。
+struct Usage {
+    used: u64,
+    /// 已释放但留在池中的存储。
+    cached: u64,
+    used_high: u64,
+    reserved_high: u64,
+}
+
+impl MemPool {
+    pub(super) const fn new(dev: CUdevice, owned: bool, max_size: usize) -> Self {
+        Self {
+            dev,
+            owned,
+            shareable: false,
+            origin: None,
+            destroyed: AtomicBool::new(false),
+            max_size,
+            release_threshold: AtomicU64::new(0),
+            reuse: [const { AtomicI32::new(1) }; 3],
+            usage: Mutex::new(Usage {
+                used: 0,
+                cached: 0,
+                used_high: 0,
+                reserved_high: 0,
+            }),
+            access: Mutex::new(
+                [CUmemAccess_flags::CU_MEM_ACCESS_FLAGS_PROT_NONE; driver::DEVICE_COUNT as usize],
+            ),
+        }
+    }
+
+    /// 从池中分配 `len` 字节，优先使用池中保留的存储。
+    fn alloc(&self, len: u64) -> Result<(), CUresult> {
+        let mut usage = self.usage.lock().unwrap();
+        let reserved = usage.used + usage.cached.max(len);
+        if self.max_size != 0 && reserved > self.max_size as u64 {
+            return Err(CUDA_ERROR_OUT_OF_MEMORY);
+        }
+        usage.used += len;
+        usage.cached = usage.cached.saturating_sub(len);
+        usage.used_high = usage.used_high.max(usage.used);
+        usage.reserved_high = usage.reserved_high.max(usage.used + usage.cached);
+        Ok(())
+    }
+
+    /// 释放 `len` 字节到池中，模拟的流总是同步的，超出阈值的部分立即归还。
+    fn free(&self, len: u64) {
+        let mut usage = self.usage.lock().unwrap();
+        usage.used -= len;
+        usage.cached += len;
+        let keep = self
+            .release_threshold
+            .load(SeqCst)
+            .saturating_sub(usage.used);
+        usage.cached = usage.cached.min(keep)
+    }
+}
+
+/// 从句柄获取未销毁的存储池。
+///
+/// # Safety
+///
+/// `pool` 为空或来自 [`cuMemPoolCreate`] 或 `cuDeviceGetDefaultMemPool`。
+unsafe fn mem_pool(pool: CUmemoryPool) -> Result<&'static MemPool, CUresult> {
+    let pool = unsafe { from_handle::<MemPool, _>(pool) }?;
+    if pool.destroyed.load(SeqCst) {
+        Err(CUDA_ERROR_INVALID_VALUE)
+    } else {
+        Ok(pool)
+    }
+}
+
+/// 从存储池分配存储。
+fn alloc_from(pool: &'static MemPool, len: usize) -> Result<CUdeviceptr, CUresult> {
+    pool.alloc(len as _)?;
+    alloc_region(pool.dev, len, RegionKind::Pooled(pool)).inspect_err(|_| pool.free(len as _))
+}
+
+/// 设备上的位置，其他位置不支持。
+fn location_device(location: &CUmemLocation) -> Result<CUdevice, CUresult> {
+    if location.type_ != CUmemLocationType::CU_MEM_LOCATION_TYPE_DEVICE {
+        return Err(CUDA_ERROR_INVALID_VALUE);
+    }
+    device(location.id)?;
+    Ok(location.id)
+}
+
+pub unsafe extern "C" fn cuMemPoolCreate(
+    pool: *mut CUmemoryPool,
+    poolProps: *const CUmemPoolProps,
+) -> CUresult {
+    result(|| {
+        let props = unsafe { poolProps.as_ref() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        if props.allocType != CUmemAllocationType::CU_MEM_ALLOCATION_TYPE_PINNED {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        use CUmemAllocationHandleType::*;
+        let shareable = match props.handleTypes {
+            CU_MEM_HANDLE_TYPE_NONE => false,
+            CU_MEM_HANDLE_TYPE_POSIX_FILE_DESCRIPTOR => true,
+            _ => return Err(CUDA_ERROR_NOT_SUPPORTED),
+        };
+        let dev = location_device(&props.location)?;
+        // 销毁的存储池可能仍有未释放的存储，模拟驱动不回收存储池本身
+        let obj = Box::leak(Box::new(MemPool {
+            shareable,
+            ..MemPool::new(dev, true, props.maxSize)
+        }));
+        unsafe { write(pool, (obj as *mut MemPool).cast()) }
+    })
+}
+
+pub unsafe extern "C" fn cuMemPoolDestroy(pool: CUmemoryPool) -> CUresult {
+    result(|| {
+        let pool = unsafe { mem_pool(pool) }?;
+        if !pool.owned {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        pool.destroyed.store(true, SeqCst);
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuMemPoolSetAttribute(
+    pool: CUmemoryPool,
+    attr: CUmemPool_attribute,
+    value: *mut c_void,
+) -> CUresult {
+    use CUmemPool_attribute::*;
+    result(|| {
+        let pool = unsafe { mem_pool(pool) }?;
+        if value.is_null() {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let reuse = |i: usize| {
+            let value = unsafe { value.cast::<c_int>().read() };
+            pool.reuse[i].store(value, SeqCst)
+        };
+        let u64_value = || unsafe { value.cast::<u64>().read() };
+        match attr {
+            CU_MEMPOOL_ATTR_REUSE_FOLLOW_EVENT_DEPENDENCIES => reuse(0),
+            CU_MEMPOOL_ATTR_REUSE_ALLOW_OPPORTUNISTIC => reuse(1),
+            CU_MEMPOOL_ATTR_REUSE_ALLOW_INTERNAL_DEPENDENCIES => reuse(2),
+            CU_MEMPOOL_ATTR_RELEASE_THRESHOLD => pool.release_threshold.store(u64_value(), SeqCst),
+            // 最大值只能重置为当前值
+            CU_MEMPOOL_ATTR_RESERVED_MEM_HIGH if u64_value() == 0 => {
+                let mut usage = pool.usage.lock().unwrap();
+                usage.reserved_high = usage.used + usage.cached
+            }
+            CU_MEMPOOL_ATTR_USED_MEM_HIGH if u64_value() == 0 => {
+                let mut usage = pool.usage.lock().unwrap();
+                usage.used_high = usage.used
+            }
+            _ => return Err(CUDA_ERROR_INVALID_VALUE),
+        }
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuMemPoolGetAttribute(
+    pool: CUmemoryPool,
+    attr: CUmemPool_attribute,
+    value: *mut c_void,
+) -> CUresult {
+    use CUmemPool_attribute::*;
+    result(|| {
+        let pool = unsafe { mem_pool(pool) }?;
+        let reuse = |i: usize| unsafe { write(value.cast::<c_int>(), pool.reuse[i].load(SeqCst)) };
+        let usage = pool.usage.lock().unwrap();
+        let value = value.cast::<u64>();
+        match attr {
+            CU_MEMPOOL_ATTR_REUSE_FOLLOW_EVENT_DEPENDENCIES => reuse(0),
+            CU_MEMPOOL_ATTR_REUSE_ALLOW_OPPORTUNISTIC => reuse(1),
+            CU_MEMPOOL_ATTR_REUSE_ALLOW_INTERNAL_DEPENDENCIES => reuse(2),
+            CU_MEMPOOL_ATTR_RELEASE_THRESHOLD => unsafe {
+                write(value, pool.release_threshold.load(SeqCst))
+            },
+            CU_MEMPOOL_ATTR_RESERVED_MEM_CURRENT => unsafe {
+                write(value, usage.used + usage.cached)
+            },
+            CU_MEMPOOL_ATTR_RESERVED_MEM_HIGH => unsafe { write(value, usage.reserved_high) },
+            CU_MEMPOOL_ATTR_USED_MEM_CURRENT => unsafe { write(value, usage.used) },
+            CU_MEMPOOL_ATTR_USED_MEM_HIGH => unsafe { write(value, usage.used_high) },
+        }
+    })
+}
+
+pub unsafe extern "C" fn cuMemPoolTrimTo(pool: CUmemoryPool, minBytesToKeep: usize) -> CUresult {
+    result(|| {
+        let pool = unsafe { mem_pool(pool) }?;
+        let mut usage = pool.usage.lock().unwrap();
+        let keep = (minBytesToKeep as u64).saturating_sub(usage.used);
+        usage.cached = usage.cached.min(keep);
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuMemPoolSetAccess(
+    pool: CUmemoryPool,
+    map: *const CUmemAccessDesc,
+    count: usize,
+) -> CUresult {
+    result(|| {
+        let pool = unsafe { mem_pool(pool) }?;
+        if map.is_null() && count != 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let map = unsafe { std::slice::from_raw_parts(map, count) };
+        let mut access = pool.access.lock().unwrap();
+        for desc in map {
+            let dev = location_device(&desc.location)?;
+            // 存储池所在的设备总是可以读写
+            if dev == pool.dev {
+                if desc.flags != CUmemAccess_flags::CU_MEM_ACCESS_FLAGS_PROT_READWRITE {
+                    return Err(CUDA_ERROR_INVALID_DEVICE);
+                }
+            } else {
+                access[dev as usize] = desc.flags
+            }
+        }
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuMemPoolGetAccess(
+    flags: *mut CUmemAccess_flags,
+    memPool: CUmemoryPool,
+    location: *mut CUmemLocation,
+) -> CUresult {
+    result(|| {
+        let pool = unsafe { mem_pool(memPool) }?;
+        let location = unsafe { location.as_ref() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        let dev = location_device(location)?;
+        let value = if dev == pool.dev {
+            CUmemAccess_flags::CU_MEM_ACCESS_FLAGS_PROT_READWRITE
+        } else {
+            pool.access.lock().unwrap()[dev as usize]
+        };
+        unsafe { write(flags, value) }
+    })
+}
+
+pub unsafe extern "C" fn cuMemAllocFromPoolAsync(
+    dptr: *mut CUdeviceptr,
+    bytesize: usize,
+    pool: CUmemoryPool,
+    hStream: CUstream,
+) -> CUresult {
+    result(|| {
+        if dptr.is_null() {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let pool = unsafe { mem_pool(pool) }?;
+        if pool.origin.is_some() {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        stream::device_of(hStream)?;
+        if stream::capturing(hStream)? {
+            return Err(CUDA_ERROR_NOT_SUPPORTED);
+        }
+        let ptr = alloc_from(pool, bytesize)?;
+        unsafe { write(dptr, ptr) }
+    })
+}
+
+pub unsafe extern "C" fn cuMemPoolExportToShareableHandle(
+    handle_out: *mut c_void,
+    pool: CUmemoryPool,
+    handleType: CUmemAllocationHandleType,
+    flags: c_ulonglong,
+) -> CUresult {
+    result(|| {
+        let pool = unsafe { mem_pool(pool) }?;
+        if handleType != CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_POSIX_FILE_DESCRIPTOR
+            || flags != 0
+        {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        if !pool.shareable {
+            return Err(CUDA_ERROR_NOT_SUPPORTED);
+        }
+        let fd = export_fd(Shared::MemPool(pool as *const MemPool as _))?;
+        unsafe { write(handle_out.cast::<c_int>(), fd) }
+    })
+}
+
+pub unsafe extern "C" fn cuMemPoolImportFromShareableHandle(
+    pool_out: *mut CUmemoryPool,
+    handle: *mut c_void,
+    handleType: CUmemAllocationHandleType,
+    flags: c_ulonglong,
+) -> CUresult {
+    result(|| {
+        if handleType != CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_POSIX_FILE_DESCRIPTOR
+            || flags != 0
+        {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let Shared::MemPool(origin) = import_fd(handle as usize as _)? else {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        };
+        let origin = unsafe { mem_pool(origin as CUmemoryPool) }?;
+        let obj = Box::leak(Box::new(MemPool {
+            shareable: true,
+            origin: Some(origin),
+            ..MemPool::new(origin.dev, true, 0)
+        }));
+        unsafe { write(pool_out, (obj as *mut MemPool).cast()) }
+    })
+}
+
+/// 导出数据中的地址和存储池。
+fn export_data(data: &CUmemPoolPtrExportData) -> (u32, usize, usize) {
+    let bytes = &data.reserved;
+    let pid = u32::from_ne_bytes(bytes[..4].try_into().unwrap());
+    let ptr = usize::from_ne_bytes(bytes[8..16].try_into().unwrap());
+    let pool = usize::from_ne_bytes(bytes[16..24].try_into().unwrap());
+    (pid, ptr, pool)
+}
+
+pub unsafe extern "C" fn cuMemPoolExportPointer(
+    shareData_out: *mut CUmemPoolPtrExportData,
+    ptr: CUdeviceptr,
+) -> CUresult {
+    result(|| {
+        let ptr = ptr as usize;
+        let pool = match REGIONS.lock().unwrap().get(&ptr) {
+            Some(Region {
+                kind: RegionKind::Pooled(pool),
+                ..
+            }) if pool.shareable => *pool,
+            _ => return Err(CUDA_ERROR_INVALID_VALUE),
+        };
+        let mut reserved = [0; 64];
+        reserved[..4].copy_from_slice(&std::process::id().to_ne_bytes());
+        reserved[8..16].copy_from_slice(&ptr.to_ne_bytes());
+        reserved[16..24].copy_from_slice(&(pool as *const MemPool as usize).to_ne_bytes());
+        unsafe { write(shareData_out, CUmemPoolPtrExportData { reserved }) }
+    })
+}
+
+pub unsafe extern "C" fn cuMemPoolImportPointer(
+    ptr_out: *mut CUdeviceptr,
+    pool: CUmemoryPool,
+    shareData: *mut CUmemPoolPtrExportData,
+) -> CUresult {
+    result(|| {
+        let pool = unsafe { mem_pool(pool) }?;
+        let data = unsafe { shareData.as_ref() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        let (pid, ptr, origin) = export_data(data);
+        if pid != std::process::id() {
+            return Err(CUDA_ERROR_NOT_SUPPORTED);
+        }
+        if pool.origin.map(|p| p as *const MemPool as usize) != Some(origin) {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let mut regions = REGIONS.lock().unwrap();
+        let len = match regions.get(&ptr) {
+            Some(&Region {
+                len,
+                kind: RegionKind::Pooled(p),
+            }) if p as *const MemPool as usize == origin => len,
+            _ => return Err(CUDA_ERROR_INVALID_VALUE),
+        };
+        // 将导出的存储的物理页再映射一次
+        let alias = unsafe { libc::mremap(ptr as _, 0, len, libc::MREMAP_MAYMOVE) };
+        if alias == libc::MAP_FAILED {
+            return Err(CUDA_ERROR_OUT_OF_MEMORY);
+        }
+        let alias = alias as usize;
+        regions.insert(
+            alias,
+            Region {
+                len,
+                kind: RegionKind::Imported,
+            },
+        );
+        unsafe { write(ptr_out, alias as _) }
+    })
+}
@@ -0,0 +1,195 @@
+//! 在主机内存中模拟驱动和 NVRTC，用于在没有 GPU 的环境中测试。
+//!
+//! 启用 `mock` 特性时，[`bindings`](crate::bindings) 中的函数由这个模块实现：
+//!
+//! - 模拟 2 个设备，每个设备 8 GiB 存储空间，设备存储空间在主机内存中按需提交；
+//! - 流上的操作立即执行，事件记录执行时的时刻；
+//! - 捕获流将操作记录为图节点，执行图时按添加顺序执行节点；
+//! - 不能编译和执行 kernel，NVRTC 编译总是失败，加载模块返回 `CUDA_ERROR_NOT_SUPPORTED`。
+//!
+//! 访问设备存储的操作会检查地址范围，越界访问返回 `CUDA_ERROR_INVALID_VALUE` 而不是破坏主机内存。
+//! 导出到其他进程的对象只能在导出它的进程中导入。
+
+mod driver;
+mod graph;
+mod memory;
+mod nvrtc;
+mod stream;
+mod types;
+mod vmm;
+
+pub use driver::*;
+pub use graph::*;
+pub use memory::*;
+pub use nvrtc::*;
+pub use stream::*;
+pub use types::*;
+pub use vmm::*;
+
+use CUresult::*;
+use std::{collections::BTreeMap, ffi::c_int, sync::Mutex};
+
+/// 执行模拟的驱动调用，将结果转换为返回码。
+fn result(f: impl FnOnce() -> Result<(), CUresult>) -> CUresult {
+    f().err().unwrap_or(CUDA_SUCCESS)
+}
+
+/// 向驱动调用的输出参数写入结果。
+///
+/// # Safety
+///
+/// `ptr` 为空或指向可写的 `T`。
+unsafe fn write<T>(ptr: *mut T, value: T) -> Result<(), CUresult> {
+    if ptr.is_null() {
+        Err(CUDA_ERROR_INVALID_VALUE)
+    } else {
+        unsafe { ptr.write(value) };
+        Ok(())
+    }
+}
+
+/// 导出的文件描述符：inode -> 代表的模拟对象。
+static SHARED_FDS: Mutex<BTreeMap<u64, Shared>> = Mutex::new(BTreeMap::new());
+
+/// 文件描述符代表的模拟对象。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Shared {
+    /// 存储池的地址。
+    MemPool(usize),
+    /// 物理页所在的设备。
+    Physical(CUdevice),
+}
+
+/// 创建代表模拟对象 `obj` 的文件描述符。
+fn export_fd(obj: Shared) -> Result<c_int, CUresult> {
+    let fd = unsafe { libc::memfd_create(c"cuda-mock".as_ptr(), libc::MFD_CLOEXEC) };
+    if fd < 0 {
+        return Err(CUDA_ERROR_OPERATING_SYSTEM);
+    }
+    register_fd(fd, obj).inspect_err(|_| unsafe {
+        libc::close(fd);
+    })?;
+    Ok(fd)
+}
+
+/// 登记代表模拟对象 `obj` 的文件描述符。
+fn register_fd(fd: c_int, obj: Shared) -> Result<(), CUresult> {
+    let inode = inode(fd)?;
+    SHARED_FDS.lock().unwrap().insert(inode, obj);
+    Ok(())
+}
+
+/// 查找文件描述符代表的模拟对象。
+fn import_fd(fd: c_int) -> Result<Shared, CUresult> {
+    let inode = inode(fd)?;
+    SHARED_FDS
+        .lock()
+        .unwrap()
+        .get(&inode)
+        .copied()
+        .ok_or(CUDA_ERROR_INVALID_VALUE)
+}
+
+fn inode(fd: c_int) -> Result<u64, CUresult> {
+    let mut stat = std::mem::MaybeUninit::uninit();
+    if unsafe { libc::fstat(fd, stat.as_mut_ptr()) } == 0 {
+        Ok(unsafe { stat.assume_init() }.st_ino)
+    } else {
+        Err(CUDA_ERROR_INVALID_VALUE)
+    }
+}
+
+/// 将模拟对象转换为驱动句柄。
+fn into_handle<T, H>(obj: T) -> *mut H {
+    Box::into_raw(Box::new(obj)).cast()
+}
+
+/// 从驱动句柄借用模拟对象。
+///
+/// # Safety
+///
+/// `handle` 为空或由 [`into_handle`] 从 `T` 创建且未释放。
+unsafe fn from_handle<'a, T, H>(handle: *mut H) -> Result<&'a T, CUresult> {
+    unsafe { handle.cast::<T>().as_ref() }.ok_or(CUDA_ERROR_INVALID_HANDLE)
+}
+
+/// 释放驱动句柄对应的模拟对象。
+///
+/// # Safety
+///
+/// `handle` 为空或由 [`into_handle`] 从 `T` 创建且未释放。
+unsafe fn drop_handle<T, H>(handle: *mut H) -> Result<(), CUresult> {
+    if handle.is_null() {
+        Err(CUDA_ERROR_INVALID_HANDLE)
+    } else {
+        drop(unsafe { Box::from_raw(handle.cast::<T>()) });
+        Ok(())
+    }
+}
+
+#[test]
+fn test_out_of_bounds() {
+    crate::init().unwrap();
+    crate::Device::new(0).context().apply(|ctx| {
+        let mem = ctx.malloc::<u8>(16);
+        let host = [0u8; 16];
+        let ptr = mem.as_ptr() as CUdeviceptr;
+        // 越界的拷贝返回错误，不访问范围外的主机内存
+        let e = try_driver!(cuMemcpyHtoD_v2(ptr + 8, host.as_ptr().cast(), 16)).unwrap_err();
+        assert_eq!(e.code(), CUDA_ERROR_INVALID_VALUE);
+        driver!(cuMemcpyHtoD_v2(ptr + 8, host.as_ptr().cast(), 8));
+        // 释放后的地址不能再访问
+        drop(mem);
+        let e = try_driver!(cuMemcpyHtoD_v2(ptr, host.as_ptr().cast(), 1)).unwrap_err();
+        assert_eq!(e.code(), CUDA_ERROR_INVALID_VALUE);
+    })
+}
+
+#[test]
+fn test_capture_invalidated() {
+    crate::init().unwrap();
+    crate::Device::new(0).context().apply(|ctx| {
+        let stream = ctx.stream().capture();
+        // 捕获期间同步流使捕获失效
+        assert!(stream.try_synchronize().is_err());
+        let e = stream.try_end().err().unwrap();
+        assert_eq!(e.code(), CUDA_ERROR_STREAM_CAPTURE_INVALIDATED);
+    })
+}
+
+#[test]
+fn test_ipc_event() {
+    crate::init().unwrap();
+    crate::Device::new(0).context().apply(|ctx| {
+        let event = ctx
+            .event_builder()
+            .disable_timing(true)
+            .interprocess(true)
+            .build();
+        // 模拟驱动只能在导出事件的进程中打开
+        let opened = ctx.open_ipc_event(&event.ipc_handle());
+        ctx.stream().record_event(&event);
+        opened.synchronize();
+        // 计时事件不能导出
+        assert!(ctx.event().try_ipc_handle().is_err())
+    })
+}
+
+#[test]
+fn test_read_only_mapping() {
+    use CUmemAccess_flags::*;
+    crate::init().unwrap();
+    let dev = crate::Device::new(0);
+    let prop = dev.mem_prop();
+    let len = prop.granularity_minimum();
+    let mut virmem = crate::VirMem::new(len, 0);
+    let mapped = virmem.map_with_access(
+        0,
+        prop.create(len),
+        &[(&dev, CU_MEM_ACCESS_FLAGS_PROT_READ)],
+    );
+    // 只读的映射不能写入
+    let host = vec![0u8; len];
+    dev.context()
+        .apply(|_| assert!(crate::try_memcpy_h2d(mapped, &host).is_err()))
+}
@@ -0,0 +1,108 @@
+use super::*;
+use nvrtcResult::*;
+use std::ffi::{CStr, c_char, c_int};
+
+const LOG: &CStr = c"the mock driver cannot compile CUDA C++";
+
+/// 程序只保存编译日志，编译总是失败。
+struct Program {
+    compiled: bool,
+}
+
+fn program<'a>(prog: nvrtcProgram) -> Result<&'a mut Program, nvrtcResult> {
+    unsafe { prog.cast::<Program>().as_mut() }.ok_or(NVRTC_ERROR_INVALID_PROGRAM)
+}
+
+fn nvrtc_result(f: impl FnOnce() -> Result<(), nvrtcResult>) -> nvrtcResult {
+    f().err().unwrap_or(NVRTC_SUCCESS)
+}
+
+pub unsafe extern "C" fn nvrtcGetErrorString(result: nvrtcResult) -> *const c_char {
+    match result {
+        NVRTC_SUCCESS => c"NVRTC_SUCCESS",
+        NVRTC_ERROR_OUT_OF_MEMORY => c"NVRTC_ERROR_OUT_OF_MEMORY",
+        NVRTC_ERROR_PROGRAM_CREATION_FAILURE => c"NVRTC_ERROR_PROGRAM_CREATION_FAILURE",
+        NVRTC_ERROR_INVALID_INPUT => c"NVRTC_ERROR_INVALID_INPUT",
+        NVRTC_ERROR_INVALID_PROGRAM => c"NVRTC_ERROR_INVALID_PROGRAM",
+        NVRTC_ERROR_INVALID_OPTION => c"NVRTC_ERROR_INVALID_OPTION",
+        NVRTC_ERROR_COMPILATION => c"NVRTC_ERROR_COMPILATION",
+        NVRTC_ERROR_INTERNAL_ERROR => c"NVRTC_ERROR_INTERNAL_ERROR",
+    }
+    .as_ptr()
+}
+
+pub unsafe extern "C" fn nvrtcCreateProgram(
+    prog: *mut nvrtcProgram,
+    src: *const c_char,
+    _name: *const c_char,
+    numHeaders: c_int,
+    headers: *const *const c_char,
+    includeNames: *const *const c_char,
+) -> nvrtcResult {
+    nvrtc_result(|| {
+        if prog.is_null() || src.is_null() || numHeaders < 0 {
+            return Err(NVRTC_ERROR_INVALID_INPUT);
+        }
+        if numHeaders > 0 && (headers.is_null() || includeNames.is_null()) {
+            return Err(NVRTC_ERROR_INVALID_INPUT);
+        }
+        unsafe { prog.write(into_handle(Program { compiled: false })) };
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn nvrtcDestroyProgram(prog: *mut nvrtcProgram) -> nvrtcResult {
+    nvrtc_result(|| {
+        let prog = unsafe { prog.as_mut() }.ok_or(NVRTC_ERROR_INVALID_INPUT)?;
+        unsafe { drop_handle::<Program, _>(std::mem::replace(prog, std::ptr::null_mut())) }
+            .map_err(|_| NVRTC_ERROR_INVALID_PROGRAM)
+    })
+}
+
+pub unsafe extern "C" fn nvrtcCompileProgram(
+    prog: nvrtcProgram,
+    _numOptions: c_int,
+    _options: *const *const c_char,
+) -> nvrtcResult {
+    nvrtc_result(|| {
+        program(prog)?.compiled = true;
+        Err(NVRTC_ERROR_COMPILATION)
+    })
+}
+
+pub unsafe extern "C" fn nvrtcGetPTXSize(
+    prog: nvrtcProgram,
+    _ptxSizeRet: *mut usize,
+) -> nvrtcResult {
+    nvrtc_result(|| program(prog).and(Err(NVRTC_ERROR_INVALID_PROGRAM)))
+}
+
+pub unsafe extern "C" fn nvrtcGetPTX(prog: nvrtcProgram, _ptx: *mut c_char) -> nvrtcResult {
+    nvrtc_result(|| program(prog).and(Err(NVRTC_ERROR_INVALID_PROGRAM)))
+}
+
+pub unsafe extern "C" fn nvrtcGetProgramLogSize(
+    prog: nvrtcProgram,
+    logSizeRet: *mut usize,
+) -> nvrtcResult {
+    nvrtc_result(|| {
+        let len = if program(prog)?.compiled {
+            LOG.count_bytes() + 1
+        } else {
+            1
+        };
+        unsafe { write(logSizeRet, len) }.map_err(|_| NVRTC_ERROR_INVALID_INPUT)
+    })
+}
+
+pub unsafe extern "C" fn nvrtcGetProgramLog(prog: nvrtcProgram, log: *mut c_char) -> nvrtcResult {
+    nvrtc_result(|| {
+        let text = if program(prog)?.compiled { LOG } else { c"" };
+        if log.is_null() {
+            return Err(NVRTC_ERROR_INVALID_INPUT);
+        }
+        let bytes = text.to_bytes_with_nul();
+        unsafe { log.copy_from_nonoverlapping(bytes.as_ptr().cast(), bytes.len()) };
+        Ok(())
+    })
+}
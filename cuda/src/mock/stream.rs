@@ -0,0 +1,494 @@
+use super::*;
+use std::{
+    collections::BTreeMap,
+    ffi::{c_int, c_uint, c_ulonglong, c_void},
+    ptr::null_mut,
+    sync::{
+        Arc, Mutex, Weak,
+        atomic::{AtomicU64, Ordering::SeqCst},
+    },
+    time::Instant,
+};
+
+/// 优先级范围，数值越小优先级越高。
+const LEAST_PRIORITY: c_int = 0;
+const GREATEST_PRIORITY: c_int = -5;
+
+/// 流标识，空流占用 0。
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+pub(super) struct Stream {
+    dev: CUdevice,
+    flags: c_uint,
+    priority: c_int,
+    id: c_ulonglong,
+    capture: Mutex<Option<Capture>>,
+}
+
+/// 流捕获的状态。
+struct Capture {
+    id: cuuint64_t,
+    graph: CUgraph,
+    /// 下一个节点的依赖。
+    deps: Vec<CUgraphNode>,
+    invalidated: bool,
+}
+
+/// 流所在的设备。空流属于当前上下文。
+pub(super) fn device_of(hStream: CUstream) -> Result<CUdevice, CUresult> {
+    if hStream.is_null() {
+        driver::current_device()
+    } else {
+        Ok(unsafe { from_handle::<Stream, _>(hStream) }?.dev)
+    }
+}
+
+/// 流是否正在捕获。已经失效的捕获返回错误。
+pub(super) fn capturing(hStream: CUstream) -> Result<bool, CUresult> {
+    if hStream.is_null() {
+        return driver::current_device().map(|_| false);
+    }
+    let stream = unsafe { from_handle::<Stream, _>(hStream) }?;
+    match &*stream.capture.lock().unwrap() {
+        Some(Capture {
+            invalidated: true, ..
+        }) => Err(CUDA_ERROR_STREAM_CAPTURE_INVALIDATED),
+        capture => Ok(capture.is_some()),
+    }
+}
+
+/// 将操作添加到捕获流的图中。
+pub(super) fn capture(hStream: CUstream, op: Op) -> Result<CUgraphNode, CUresult> {
+    let stream = unsafe { from_handle::<Stream, _>(hStream) }?;
+    let mut capture = stream.capture.lock().unwrap();
+    let capture = capture.as_mut().ok_or(CUDA_ERROR_ILLEGAL_STATE)?;
+    if let Err(e) = op.check() {
+        capture.invalidated = true;
+        return Err(e);
+    }
+    let graph = unsafe { from_handle::<Graph, _>(capture.graph) }?;
+    let node = graph.add(op, &capture.deps)?;
+    capture.deps = vec![node];
+    Ok(node)
+}
+
+/// 捕获流上不允许的操作，使捕获失效。
+pub(super) fn forbid_capture(hStream: CUstream) -> Result<(), CUresult> {
+    if hStream.is_null() {
+        return Ok(());
+    }
+    let stream = unsafe { from_handle::<Stream, _>(hStream) }?;
+    match &mut *stream.capture.lock().unwrap() {
+        Some(capture) => {
+            capture.invalidated = true;
+            Err(CUDA_ERROR_STREAM_CAPTURE_UNSUPPORTED)
+        }
+        None => Ok(()),
+    }
+}
+
+/// 在流上提交操作：捕获流将操作记录为图节点，否则立即执行。
+pub(super) fn submit(hStream: CUstream, op: Op) -> Result<(), CUresult> {
+    if capturing(hStream)? {
+        capture(hStream, op).map(|_| ())
+    } else {
+        op.check()?;
+        op.run()
+    }
+}
+
+/// 在流上执行已实例化的图。
+pub(super) fn launch(hStream: CUstream, ops: &[Op]) -> Result<(), CUresult> {
+    driver::current_device()?;
+    forbid_capture(hStream)?;
+    for op in ops {
+        op.check()?;
+        op.run()?
+    }
+    Ok(())
+}
+
+pub unsafe extern "C" fn cuStreamCreate(phStream: *mut CUstream, Flags: c_uint) -> CUresult {
+    unsafe { cuStreamCreateWithPriority(phStream, Flags, LEAST_PRIORITY) }
+}
+
+pub unsafe extern "C" fn cuStreamCreateWithPriority(
+    phStream: *mut CUstream,
+    flags: c_uint,
+    priority: c_int,
+) -> CUresult {
+    result(|| {
+        let dev = driver::current_device()?;
+        if flags > CUstream_flags::CU_STREAM_NON_BLOCKING as c_uint {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let stream = Stream {
+            dev,
+            flags,
+            priority: priority.clamp(GREATEST_PRIORITY, LEAST_PRIORITY),
+            id: NEXT_ID.fetch_add(1, SeqCst),
+            capture: Mutex::new(None),
+        };
+        unsafe { write(phStream, into_handle(stream)) }
+    })
+}
+
+pub unsafe extern "C" fn cuCtxGetStreamPriorityRange(
+    leastPriority: *mut c_int,
+    greatestPriority: *mut c_int,
+) -> CUresult {
+    result(|| {
+        // 两个参数都可以为空
+        unsafe {
+            if !leastPriority.is_null() {
+                leastPriority.write(LEAST_PRIORITY)
+            }
+            if !greatestPriority.is_null() {
+                greatestPriority.write(GREATEST_PRIORITY)
+            }
+        }
+        Ok(())
+    })
+}
+
+/// 读取流的属性，空流使用默认值。
+fn stream_attr<T>(
+    hStream: CUstream,
+    default: T,
+    f: impl FnOnce(&Stream) -> T,
+) -> Result<T, CUresult> {
+    if hStream.is_null() {
+        driver::current_device()?;
+        Ok(default)
+    } else {
+        Ok(f(unsafe { from_handle::<Stream, _>(hStream) }?))
+    }
+}
+
+pub unsafe extern "C" fn cuStreamGetFlags(hStream: CUstream, flags: *mut c_uint) -> CUresult {
+    result(|| unsafe { write(flags, stream_attr(hStream, 0, |s| s.flags)?) })
+}
+
+pub unsafe extern "C" fn cuStreamGetPriority(hStream: CUstream, priority: *mut c_int) -> CUresult {
+    result(|| unsafe {
+        write(
+            priority,
+            stream_attr(hStream, LEAST_PRIORITY, |s| s.priority)?,
+        )
+    })
+}
+
+pub unsafe extern "C" fn cuStreamGetId(hStream: CUstream, streamId: *mut c_ulonglong) -> CUresult {
+    result(|| unsafe { write(streamId, stream_attr(hStream, 0, |s| s.id)?) })
+}
+
+pub unsafe extern "C" fn cuStreamDestroy_v2(hStream: CUstream) -> CUresult {
+    result(|| {
+        let stream = unsafe { from_handle::<Stream, _>(hStream) }?;
+        if let Some(capture) = stream.capture.lock().unwrap().take() {
+            unsafe { drop_handle::<Graph, _>(capture.graph) }?
+        }
+        unsafe { drop_handle::<Stream, _>(hStream) }
+    })
+}
+
+pub unsafe extern "C" fn cuStreamSynchronize(hStream: CUstream) -> CUresult {
+    result(|| {
+        device_of(hStream)?;
+        forbid_capture(hStream)
+    })
+}
+
+pub unsafe extern "C" fn cuStreamWaitEvent(
+    hStream: CUstream,
+    hEvent: CUevent,
+    Flags: c_uint,
+) -> CUresult {
+    result(|| {
+        device_of(hStream)?;
+        unsafe { from_handle::<Event, _>(hEvent) }?;
+        if Flags != 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuLaunchHostFunc(
+    hStream: CUstream,
+    fn_: CUhostFn,
+    userData: *mut c_void,
+) -> CUresult {
+    result(|| submit(hStream, Op::Host(CUDA_HOST_NODE_PARAMS { fn_, userData })))
+}
+
+/// 流上的操作立即执行，回调在返回前以成功状态调用。
+pub unsafe extern "C" fn cuStreamAddCallback(
+    hStream: CUstream,
+    callback: CUstreamCallback,
+    userData: *mut c_void,
+    flags: c_uint,
+) -> CUresult {
+    result(|| {
+        device_of(hStream)?;
+        forbid_capture(hStream)?;
+        let callback = callback.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        if flags != 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        unsafe { callback(hStream, CUDA_SUCCESS, userData) };
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuLaunchKernel(
+    f: CUfunction,
+    _gridDimX: c_uint,
+    _gridDimY: c_uint,
+    _gridDimZ: c_uint,
+    _blockDimX: c_uint,
+    _blockDimY: c_uint,
+    _blockDimZ: c_uint,
+    _sharedMemBytes: c_uint,
+    hStream: CUstream,
+    _kernelParams: *mut *mut c_void,
+    _extra: *mut *mut c_void,
+) -> CUresult {
+    result(|| {
+        device_of(hStream)?;
+        // 模拟驱动不能加载模块，不存在合法的函数
+        if f.is_null() {
+            Err(CUDA_ERROR_INVALID_VALUE)
+        } else {
+            Err(CUDA_ERROR_INVALID_HANDLE)
+        }
+    })
+}
+
+pub unsafe extern "C" fn cuStreamBeginCapture_v2(
+    hStream: CUstream,
+    _mode: CUstreamCaptureMode,
+) -> CUresult {
+    result(|| {
+        if hStream.is_null() {
+            return Err(CUDA_ERROR_STREAM_CAPTURE_UNSUPPORTED);
+        }
+        let stream = unsafe { from_handle::<Stream, _>(hStream) }?;
+        let mut capture = stream.capture.lock().unwrap();
+        if capture.is_some() {
+            return Err(CUDA_ERROR_ILLEGAL_STATE);
+        }
+        *capture = Some(Capture {
+            id: NEXT_ID.fetch_add(1, SeqCst),
+            graph: into_handle(Graph::default()),
+            deps: Vec::new(),
+            invalidated: false,
+        });
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuStreamIsCapturing(
+    hStream: CUstream,
+    captureStatus: *mut CUstreamCaptureStatus,
+) -> CUresult {
+    unsafe {
+        cuStreamGetCaptureInfo_v2(
+            hStream,
+            captureStatus,
+            null_mut(),
+            null_mut(),
+            null_mut(),
+            null_mut(),
+        )
+    }
+}
+
+pub unsafe extern "C" fn cuStreamGetCaptureInfo_v2(
+    hStream: CUstream,
+    captureStatus_out: *mut CUstreamCaptureStatus,
+    id_out: *mut cuuint64_t,
+    graph_out: *mut CUgraph,
+    dependencies_out: *mut *const CUgraphNode,
+    numDependencies_out: *mut usize,
+) -> CUresult {
+    use CUstreamCaptureStatus::*;
+    result(|| {
+        device_of(hStream)?;
+        if hStream.is_null() {
+            return unsafe { write(captureStatus_out, CU_STREAM_CAPTURE_STATUS_NONE) };
+        }
+        let stream = unsafe { from_handle::<Stream, _>(hStream) }?;
+        let capture = stream.capture.lock().unwrap();
+        let Some(capture) = &*capture else {
+            return unsafe { write(captureStatus_out, CU_STREAM_CAPTURE_STATUS_NONE) };
+        };
+        let status = if capture.invalidated {
+            CU_STREAM_CAPTURE_STATUS_INVALIDATED
+        } else {
+            CU_STREAM_CAPTURE_STATUS_ACTIVE
+        };
+        unsafe {
+            write(captureStatus_out, status)?;
+            // 其他输出参数都是可选的
+            if !id_out.is_null() {
+                id_out.write(capture.id)
+            }
+            if !graph_out.is_null() {
+                graph_out.write(capture.graph)
+            }
+            // 依赖数组只在下一次操作流之前有效
+            if !dependencies_out.is_null() {
+                dependencies_out.write(capture.deps.as_ptr())
+            }
+            if !numDependencies_out.is_null() {
+                numDependencies_out.write(capture.deps.len())
+            }
+        }
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuStreamEndCapture(hStream: CUstream, phGraph: *mut CUgraph) -> CUresult {
+    result(|| {
+        let stream = unsafe { from_handle::<Stream, _>(hStream) }?;
+        let capture = stream.capture.lock().unwrap().take();
+        let capture = capture.ok_or(CUDA_ERROR_ILLEGAL_STATE)?;
+        if capture.invalidated {
+            unsafe { drop_handle::<Graph, _>(capture.graph) }?;
+            return Err(CUDA_ERROR_STREAM_CAPTURE_INVALIDATED);
+        }
+        unsafe { write(phGraph, capture.graph) }
+    })
+}
+
+pub(super) struct Event {
+    flags: c_uint,
+    time: Arc<Mutex<Option<Instant>>>,
+}
+
+/// 导出的事件：标识 -> 事件记录的时刻。
+///
+/// 模拟驱动不能跨进程共享事件，只能在导出事件的进程中打开。
+static IPC_EVENTS: Mutex<BTreeMap<u64, Weak<Mutex<Option<Instant>>>>> = Mutex::new(BTreeMap::new());
+static NEXT_IPC_EVENT: AtomicU64 = AtomicU64::new(0);
+
+pub unsafe extern "C" fn cuEventCreate(phEvent: *mut CUevent, Flags: c_uint) -> CUresult {
+    use CUevent_flags::*;
+    result(|| {
+        driver::current_device()?;
+        let known = CU_EVENT_BLOCKING_SYNC as c_uint
+            | CU_EVENT_DISABLE_TIMING as c_uint
+            | CU_EVENT_INTERPROCESS as c_uint;
+        // 跨进程的事件必须禁用计时
+        let interprocess = Flags & CU_EVENT_INTERPROCESS as c_uint != 0;
+        let timing = Flags & CU_EVENT_DISABLE_TIMING as c_uint == 0;
+        if Flags & !known != 0 || (interprocess && timing) {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let event = Event {
+            flags: Flags,
+            time: Default::default(),
+        };
+        unsafe { write(phEvent, into_handle(event)) }
+    })
+}
+
+pub unsafe extern "C" fn cuIpcGetEventHandle(
+    pHandle: *mut CUipcEventHandle,
+    event: CUevent,
+) -> CUresult {
+    result(|| {
+        let event = unsafe { from_handle::<Event, _>(event) }?;
+        if event.flags & CUevent_flags::CU_EVENT_INTERPROCESS as c_uint == 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let key = NEXT_IPC_EVENT.fetch_add(1, SeqCst);
+        IPC_EVENTS
+            .lock()
+            .unwrap()
+            .insert(key, Arc::downgrade(&event.time));
+
+        let mut reserved = [0u8; 64];
+        reserved[..4].copy_from_slice(&std::process::id().to_ne_bytes());
+        reserved[8..16].copy_from_slice(&key.to_ne_bytes());
+        unsafe {
+            write(
+                pHandle,
+                CUipcEventHandle {
+                    reserved: reserved.map(|b| b as _),
+                },
+            )
+        }
+    })
+}
+
+pub unsafe extern "C" fn cuIpcOpenEventHandle(
+    phEvent: *mut CUevent,
+    handle: CUipcEventHandle,
+) -> CUresult {
+    use CUevent_flags::*;
+    result(|| {
+        driver::current_device()?;
+        let reserved = handle.reserved.map(|b| b as u8);
+        let pid = u32::from_ne_bytes(reserved[..4].try_into().unwrap());
+        if pid != std::process::id() {
+            return Err(CUDA_ERROR_NOT_SUPPORTED);
+        }
+        let key = u64::from_ne_bytes(reserved[8..16].try_into().unwrap());
+        let time = IPC_EVENTS.lock().unwrap().get(&key).and_then(Weak::upgrade);
+        let event = Event {
+            flags: CU_EVENT_DISABLE_TIMING as c_uint | CU_EVENT_INTERPROCESS as c_uint,
+            time: time.ok_or(CUDA_ERROR_INVALID_VALUE)?,
+        };
+        unsafe { write(phEvent, into_handle(event)) }
+    })
+}
+
+pub unsafe extern "C" fn cuEventDestroy_v2(hEvent: CUevent) -> CUresult {
+    result(|| unsafe { drop_handle::<Event, _>(hEvent) })
+}
+
+pub unsafe extern "C" fn cuEventRecord(hEvent: CUevent, hStream: CUstream) -> CUresult {
+    result(|| {
+        let event = unsafe { from_handle::<Event, _>(hEvent) }?;
+        // 捕获流上记录的事件只用于表达依赖，不记录时刻
+        if !capturing(hStream)? {
+            *event.time.lock().unwrap() = Some(Instant::now())
+        }
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuEventQuery(hEvent: CUevent) -> CUresult {
+    result(|| unsafe { from_handle::<Event, _>(hEvent) }.map(|_| ()))
+}
+
+pub unsafe extern "C" fn cuEventSynchronize(hEvent: CUevent) -> CUresult {
+    result(|| unsafe { from_handle::<Event, _>(hEvent) }.map(|_| ()))
+}
+
+pub unsafe extern "C" fn cuEventElapsedTime(
+    pMilliseconds: *mut f32,
+    hStart: CUevent,
+    hEnd: CUevent,
+) -> CUresult {
+    result(|| {
+        let time = |event| {
+            let event = unsafe { from_handle::<Event, _>(event) }?;
+            if event.flags & CUevent_flags::CU_EVENT_DISABLE_TIMING as c_uint != 0 {
+                return Err(CUDA_ERROR_INVALID_HANDLE);
+            }
+            let time = *event.time.lock().unwrap();
+            time.ok_or(CUDA_ERROR_INVALID_HANDLE)
+        };
+        let start = time(hStart)?;
+        let end = time(hEnd)?;
+        let ms = if end >= start {
+            (end - start).as_secs_f32() * 1e3
+        } else {
+            -(start - end).as_secs_f32() * 1e3
+        };
+        unsafe { write(pMilliseconds, ms) }
+    })
+}
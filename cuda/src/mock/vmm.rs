@@ -0,0 +1,405 @@
+use super::{
+    memory::{Region, RegionKind},
+    *,
+};
+use std::{
+    collections::BTreeMap,
+    ffi::{c_int, c_ulonglong, c_void},
+    os::fd::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd},
+    ptr::null_mut,
+    slice::from_raw_parts,
+    sync::Mutex,
+};
+
+/// 物理页和虚地址的粒度。
+pub(super) const GRANULARITY: usize = 2 << 20;
+
+/// 保留的虚地址区间：起始地址 -> 长度。
+static RESERVED: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::new());
+
+/// 物理页，由匿名文件模拟，映射时与其他映射共享内容。
+struct Physical {
+    fd: OwnedFd,
+    len: usize,
+    dev: CUdevice,
+    /// 可以导出为文件描述符。
+    shareable: bool,
+}
+
+impl Drop for Physical {
+    fn drop(&mut self) {
+        if let Ok(device) = driver::device(self.dev) {
+            device.release(self.len)
+        }
+    }
+}
+
+fn aligned(value: usize) -> bool {
+    value.is_multiple_of(GRANULARITY)
+}
+
+/// 检查 `[ptr, ptr + len)` 恰好由已映射的区间覆盖。
+fn check_mapped(ptr: usize, len: usize) -> Result<(), CUresult> {
+    memory::with_regions(ptr, len, |regions| {
+        let mut cursor = ptr;
+        for (&start, region) in regions {
+            if start != cursor || !matches!(region.kind, RegionKind::Mapped(_)) {
+                return Err(CUDA_ERROR_INVALID_VALUE);
+            }
+            cursor += region.len
+        }
+        if cursor == ptr + len {
+            Ok(())
+        } else {
+            Err(CUDA_ERROR_INVALID_VALUE)
+        }
+    })
+}
+
+fn mmap_fixed(
+    ptr: usize,
+    len: usize,
+    flags: libc::c_int,
+    fd: libc::c_int,
+    offset: usize,
+) -> Result<(), CUresult> {
+    let ans = unsafe {
+        libc::mmap(
+            ptr as _,
+            len,
+            libc::PROT_NONE,
+            flags | libc::MAP_FIXED,
+            fd,
+            offset as _,
+        )
+    };
+    if ans as usize == ptr {
+        Ok(())
+    } else {
+        Err(CUDA_ERROR_OUT_OF_MEMORY)
+    }
+}
+
+pub unsafe extern "C" fn cuMemAddressReserve(
+    ptr: *mut CUdeviceptr,
+    size: usize,
+    alignment: usize,
+    _addr: CUdeviceptr,
+    flags: c_ulonglong,
+) -> CUresult {
+    result(|| {
+        driver::initialized()?;
+        if ptr.is_null() || size == 0 || !aligned(size) || flags != 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        if alignment != 0 && !alignment.is_power_of_two() {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        // 多保留一段地址以便对齐，再释放头尾多余的部分
+        let align = alignment.max(GRANULARITY);
+        let total = size + align;
+        let base = unsafe {
+            libc::mmap(
+                null_mut(),
+                total,
+                libc::PROT_NONE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE,
+                -1,
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            return Err(CUDA_ERROR_OUT_OF_MEMORY);
+        }
+        let base = base as usize;
+        let start = base.next_multiple_of(align);
+        if start > base {
+            memory::unmap_anonymous(base, start - base)?
+        }
+        let tail = base + total - (start + size);
+        if tail > 0 {
+            memory::unmap_anonymous(start + size, tail)?
+        }
+        RESERVED.lock().unwrap().insert(start, size);
+        unsafe { write(ptr, start as _) }
+    })
+}
+
+pub unsafe extern "C" fn cuMemAddressFree(ptr: CUdeviceptr, size: usize) -> CUresult {
+    result(|| {
+        let ptr = ptr as usize;
+        let mut reserved = RESERVED.lock().unwrap();
+        if reserved.get(&ptr) != Some(&size) {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        reserved.remove(&ptr);
+        let mapped = memory::with_regions(ptr, size, |regions| {
+            regions.map(|(&start, _)| start).collect::<Vec<_>>()
+        });
+        for start in mapped {
+            memory::remove_region(start);
+        }
+        memory::unmap_anonymous(ptr, size)
+    })
+}
+
+pub unsafe extern "C" fn cuMemCreate(
+    handle: *mut CUmemGenericAllocationHandle,
+    size: usize,
+    prop: *const CUmemAllocationProp,
+    flags: c_ulonglong,
+) -> CUresult {
+    result(|| {
+        let prop = unsafe { prop.as_ref() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        if handle.is_null()
+            || size == 0
+            || !aligned(size)
+            || flags != 0
+            || prop.type_ != CUmemAllocationType::CU_MEM_ALLOCATION_TYPE_PINNED
+            || prop.location.type_ != CUmemLocationType::CU_MEM_LOCATION_TYPE_DEVICE
+        {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        use CUmemAllocationHandleType::*;
+        let shareable = match prop.requestedHandleTypes {
+            CU_MEM_HANDLE_TYPE_NONE => false,
+            CU_MEM_HANDLE_TYPE_POSIX_FILE_DESCRIPTOR => true,
+            _ => return Err(CUDA_ERROR_NOT_SUPPORTED),
+        };
+        let dev = prop.location.id;
+        let device = driver::device(dev)?;
+        device.reserve(size)?;
+
+        let fd = unsafe { libc::memfd_create(c"cuda-mock".as_ptr(), libc::MFD_CLOEXEC) };
+        if fd < 0 {
+            device.release(size);
+            return Err(CUDA_ERROR_OUT_OF_MEMORY);
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let phy = Physical {
+            fd,
+            len: size,
+            dev,
+            shareable,
+        };
+        if unsafe { libc::ftruncate(phy.fd.as_raw_fd(), size as _) } != 0 {
+            return Err(CUDA_ERROR_OUT_OF_MEMORY);
+        }
+        unsafe { write(handle, into_handle::<_, Physical>(phy) as _) }
+    })
+}
+
+pub unsafe extern "C" fn cuMemRelease(handle: CUmemGenericAllocationHandle) -> CUresult {
+    // 已建立的映射仍然引用匿名文件的内容
+    result(|| unsafe { drop_handle::<Physical, Physical>(handle as _) })
+}
+
+pub unsafe extern "C" fn cuMemExportToShareableHandle(
+    shareableHandle: *mut c_void,
+    handle: CUmemGenericAllocationHandle,
+    handleType: CUmemAllocationHandleType,
+    flags: c_ulonglong,
+) -> CUresult {
+    result(|| {
+        let phy = unsafe { from_handle::<Physical, Physical>(handle as _) }?;
+        if handleType != CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_POSIX_FILE_DESCRIPTOR
+            || flags != 0
+            || !phy.shareable
+        {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        // 导出的文件描述符指向同一个匿名文件，登记物理页所在的设备
+        let fd = unsafe { libc::fcntl(phy.fd.as_raw_fd(), libc::F_DUPFD_CLOEXEC, 0) };
+        if fd < 0 {
+            return Err(CUDA_ERROR_OPERATING_SYSTEM);
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        register_fd(fd.as_raw_fd(), Shared::Physical(phy.dev))?;
+        unsafe { write(shareableHandle.cast::<c_int>(), fd.into_raw_fd()) }
+    })
+}
+
+pub unsafe extern "C" fn cuMemImportFromShareableHandle(
+    handle: *mut CUmemGenericAllocationHandle,
+    osHandle: *mut c_void,
+    shHandleType: CUmemAllocationHandleType,
+) -> CUresult {
+    result(|| {
+        if shHandleType != CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_POSIX_FILE_DESCRIPTOR {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let fd = osHandle as usize as c_int;
+        let Shared::Physical(dev) = import_fd(fd)? else {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        };
+        let fd = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 0) };
+        if fd < 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let mut stat = std::mem::MaybeUninit::uninit();
+        if unsafe { libc::fstat(fd.as_raw_fd(), stat.as_mut_ptr()) } != 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let len = unsafe { stat.assume_init() }.st_size as usize;
+        // 导入的物理页计入导入者的设备存储
+        driver::device(dev)?.reserve(len)?;
+        let phy = Physical {
+            fd,
+            len,
+            dev,
+            shareable: true,
+        };
+        unsafe { write(handle, into_handle::<_, Physical>(phy) as _) }
+    })
+}
+
+pub unsafe extern "C" fn cuMemGetAllocationPropertiesFromHandle(
+    prop: *mut CUmemAllocationProp,
+    handle: CUmemGenericAllocationHandle,
+) -> CUresult {
+    result(|| {
+        let phy = unsafe { from_handle::<Physical, Physical>(handle as _) }?;
+        let requestedHandleTypes = if phy.shareable {
+            CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_POSIX_FILE_DESCRIPTOR
+        } else {
+            CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_NONE
+        };
+        let value = CUmemAllocationProp {
+            type_: CUmemAllocationType::CU_MEM_ALLOCATION_TYPE_PINNED,
+            requestedHandleTypes,
+            location: CUmemLocation {
+                type_: CUmemLocationType::CU_MEM_LOCATION_TYPE_DEVICE,
+                id: phy.dev,
+            },
+            win32HandleMetaData: null_mut(),
+            allocFlags: unsafe { std::mem::zeroed() },
+        };
+        unsafe { write(prop, value) }
+    })
+}
+
+pub unsafe extern "C" fn cuMemMap(
+    ptr: CUdeviceptr,
+    size: usize,
+    offset: usize,
+    handle: CUmemGenericAllocationHandle,
+    flags: c_ulonglong,
+) -> CUresult {
+    result(|| {
+        let phy = unsafe { from_handle::<Physical, Physical>(handle as _) }?;
+        let ptr = ptr as usize;
+        if size == 0 || flags != 0 || !aligned(ptr) || !aligned(size) || !aligned(offset) {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        if offset.checked_add(size).is_none_or(|end| end > phy.len) {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let reserved = RESERVED.lock().unwrap();
+        match reserved.range(..=ptr).next_back() {
+            Some((&start, &len)) if ptr + size <= start + len => {}
+            _ => return Err(CUDA_ERROR_INVALID_VALUE),
+        }
+        if memory::overlaps(ptr, size) {
+            return Err(CUDA_ERROR_ALREADY_MAPPED);
+        }
+        mmap_fixed(ptr, size, libc::MAP_SHARED, phy.fd.as_raw_fd(), offset)?;
+        let kind = RegionKind::Mapped(
+            [CUmemAccess_flags::CU_MEM_ACCESS_FLAGS_PROT_NONE; driver::DEVICE_COUNT as usize],
+        );
+        memory::insert_region(ptr, Region { len: size, kind });
+        Ok(())
+    })
+}
+
+pub unsafe extern "C" fn cuMemUnmap(ptr: CUdeviceptr, size: usize) -> CUresult {
+    result(|| {
+        let ptr = ptr as usize;
+        check_mapped(ptr, size)?;
+        let mapped = memory::with_regions(ptr, size, |regions| {
+            regions.map(|(&start, _)| start).collect::<Vec<_>>()
+        });
+        for start in mapped {
+            memory::remove_region(start);
+        }
+        // 用不可访问的匿名映射恢复保留的地址
+        let flags = libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE;
+        mmap_fixed(ptr, size, flags, -1, 0)
+    })
+}
+
+pub unsafe extern "C" fn cuMemSetAccess(
+    ptr: CUdeviceptr,
+    size: usize,
+    desc: *const CUmemAccessDesc,
+    count: usize,
+) -> CUresult {
+    use CUmemAccess_flags::*;
+    result(|| {
+        if desc.is_null() || count == 0 {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        let desc = unsafe { from_raw_parts(desc, count) };
+        for desc in desc {
+            if desc.location.type_ != CUmemLocationType::CU_MEM_LOCATION_TYPE_DEVICE {
+                return Err(CUDA_ERROR_INVALID_VALUE);
+            }
+            driver::device(desc.location.id)?;
+        }
+        let ptr = ptr as usize;
+        check_mapped(ptr, size)?;
+        // 只修改列出的设备的权限，主机上的保护取各设备的最高权限
+        memory::with_regions(ptr, size, |regions| {
+            for (&start, region) in regions {
+                let RegionKind::Mapped(access) = &mut region.kind else {
+                    unreachable!()
+                };
+                for desc in desc {
+                    access[desc.location.id as usize] = desc.flags
+                }
+                let prot = match memory::widest(access) {
+                    CU_MEM_ACCESS_FLAGS_PROT_READWRITE => libc::PROT_READ | libc::PROT_WRITE,
+                    CU_MEM_ACCESS_FLAGS_PROT_READ => libc::PROT_READ,
+                    _ => libc::PROT_NONE,
+                };
+                if unsafe { libc::mprotect(start as _, region.len, prot) } != 0 {
+                    return Err(CUDA_ERROR_INVALID_VALUE);
+                }
+            }
+            Ok(())
+        })
+    })
+}
+
+pub unsafe extern "C" fn cuMemGetAccess(
+    flags: *mut c_ulonglong,
+    location: *const CUmemLocation,
+    ptr: CUdeviceptr,
+) -> CUresult {
+    result(|| {
+        let location = unsafe { location.as_ref() }.ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        if location.type_ != CUmemLocationType::CU_MEM_LOCATION_TYPE_DEVICE {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        driver::device(location.id)?;
+        let access = memory::find_region(ptr as _, |region| match &region.kind {
+            RegionKind::Mapped(access) => Some(access[location.id as usize]),
+            _ => None,
+        });
+        let access = access.flatten().ok_or(CUDA_ERROR_INVALID_VALUE)?;
+        unsafe { write(flags, access as _) }
+    })
+}
+
+pub unsafe extern "C" fn cuMemGetAllocationGranularity(
+    granularity: *mut usize,
+    prop: *const CUmemAllocationProp,
+    _option: CUmemAllocationGranularity_flags,
+) -> CUresult {
+    result(|| {
+        if prop.is_null() {
+            return Err(CUDA_ERROR_INVALID_VALUE);
+        }
+        unsafe { write(granularity, GRANULARITY) }
+    })
+}
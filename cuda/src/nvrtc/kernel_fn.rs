@@ -1,5 +1,5 @@
 ﻿use crate::{
-    MemSize, Module, Version,
+    Error, MemSize, Module, Version,
     bindings::{
         CUfunction,
         CUfunction_attribute::{self, *},
@@ -19,15 +19,21 @@ use std::{
 pub struct KernelFn<'m>(CUfunction, PhantomData<&'m ()>);
 
 impl Module<'_> {
-    pub fn get_kernel(&self, name: impl AsRef<CStr>) -> KernelFn {
+    #[inline]
+    #[track_caller]
+    pub fn get_kernel(&self, name: impl AsRef<CStr>) -> KernelFn<'_> {
+        self.try_get_kernel(name).unwrap()
+    }
+
+    pub fn try_get_kernel(&self, name: impl AsRef<CStr>) -> Result<KernelFn<'_>, Error> {
         let name = name.as_ref();
         let mut kernel = null_mut();
-        driver!(cuModuleGetFunction(
+        try_driver!(cuModuleGetFunction(
             &mut kernel,
             self.as_raw(),
             name.as_ptr().cast(),
-        ));
-        KernelFn(kernel, PhantomData)
+        ))?;
+        Ok(KernelFn(kernel, PhantomData))
     }
 }
 
@@ -41,61 +47,105 @@ impl AsRaw for KernelFn<'_> {
 
 impl KernelFn<'_> {
     #[inline]
+    #[track_caller]
     pub fn max_threads_per_block(&self) -> usize {
-        self.get_attribute(CU_FUNC_ATTRIBUTE_MAX_THREADS_PER_BLOCK) as _
+        self.try_max_threads_per_block().unwrap()
     }
 
     #[inline]
+    #[track_caller]
     pub fn static_smem(&self) -> MemSize {
-        self.get_attribute(CU_FUNC_ATTRIBUTE_SHARED_SIZE_BYTES)
-            .into()
+        self.try_static_smem().unwrap()
     }
 
     #[inline]
+    #[track_caller]
     pub fn max_dyn_smem(&self) -> MemSize {
-        self.get_attribute(CU_FUNC_ATTRIBUTE_MAX_DYNAMIC_SHARED_SIZE_BYTES)
-            .into()
+        self.try_max_dyn_smem().unwrap()
     }
 
     #[inline]
+    #[track_caller]
     pub fn local_mem(&self) -> MemSize {
-        self.get_attribute(CU_FUNC_ATTRIBUTE_LOCAL_SIZE_BYTES)
-            .into()
+        self.try_local_mem().unwrap()
     }
 
     #[inline]
+    #[track_caller]
     pub fn num_regs(&self) -> MemSize {
-        self.get_attribute(CU_FUNC_ATTRIBUTE_NUM_REGS).into()
+        self.try_num_regs().unwrap()
     }
 
     #[inline]
+    #[track_caller]
     pub fn ptx_version(&self) -> Version {
-        let version = self.get_attribute(CU_FUNC_ATTRIBUTE_PTX_VERSION);
-        Version {
-            major: version / 10,
-            minor: version % 10,
-        }
+        self.try_ptx_version().unwrap()
     }
 
     #[inline]
+    #[track_caller]
     pub fn binary_version(&self) -> Version {
-        let version = self.get_attribute(CU_FUNC_ATTRIBUTE_PTX_VERSION);
-        Version {
-            major: version / 10,
-            minor: version % 10,
-        }
+        self.try_binary_version().unwrap()
+    }
+
+    #[inline]
+    pub fn try_max_threads_per_block(&self) -> Result<usize, Error> {
+        self.try_get_attribute(CU_FUNC_ATTRIBUTE_MAX_THREADS_PER_BLOCK)
+            .map(|n| n as _)
+    }
+
+    #[inline]
+    pub fn try_static_smem(&self) -> Result<MemSize, Error> {
+        self.try_get_attribute(CU_FUNC_ATTRIBUTE_SHARED_SIZE_BYTES)
+            .map(Into::into)
+    }
+
+    #[inline]
+    pub fn try_max_dyn_smem(&self) -> Result<MemSize, Error> {
+        self.try_get_attribute(CU_FUNC_ATTRIBUTE_MAX_DYNAMIC_SHARED_SIZE_BYTES)
+            .map(Into::into)
+    }
+
+    #[inline]
+    pub fn try_local_mem(&self) -> Result<MemSize, Error> {
+        self.try_get_attribute(CU_FUNC_ATTRIBUTE_LOCAL_SIZE_BYTES)
+            .map(Into::into)
+    }
+
+    #[inline]
+    pub fn try_num_regs(&self) -> Result<MemSize, Error> {
+        self.try_get_attribute(CU_FUNC_ATTRIBUTE_NUM_REGS)
+            .map(Into::into)
+    }
+
+    #[inline]
+    pub fn try_ptx_version(&self) -> Result<Version, Error> {
+        self.try_get_attribute(CU_FUNC_ATTRIBUTE_PTX_VERSION)
+            .map(|version| Version {
+                major: version / 10,
+                minor: version % 10,
+            })
+    }
+
+    #[inline]
+    pub fn try_binary_version(&self) -> Result<Version, Error> {
+        self.try_get_attribute(CU_FUNC_ATTRIBUTE_PTX_VERSION)
+            .map(|version| Version {
+                major: version / 10,
+                minor: version % 10,
+            })
     }
 
     #[inline]
-    pub fn info(&self) -> InfoFmt {
+    pub fn info(&self) -> InfoFmt<'_> {
         InfoFmt(self)
     }
 
     #[inline]
-    fn get_attribute(&self, attr: CUfunction_attribute) -> c_int {
+    fn try_get_attribute(&self, attr: CUfunction_attribute) -> Result<c_int, Error> {
         let mut value = 0;
-        driver!(cuFuncGetAttribute(&mut value, attr, self.0));
-        value
+        try_driver!(cuFuncGetAttribute(&mut value, attr, self.0))?;
+        Ok(value)
     }
 }
 
@@ -174,7 +224,7 @@ impl KernelParams {
         }
     }
 
-    pub fn to_ptrs(&self) -> KernelParamPtrs {
+    pub fn to_ptrs(&self) -> KernelParamPtrs<'_> {
         KernelParamPtrs(
             self.each
                 .iter()
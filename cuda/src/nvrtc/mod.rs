@@ -60,6 +60,7 @@ extern "C" __global__ void kernel2() { printf("Hello World from GPU!\n"); }
 }
 
 #[test]
+#[cfg_attr(feature = "mock", ignore = "the mock driver cannot compile kernels")]
 fn test_behavior() {
     use std::{
         ffi::CString,
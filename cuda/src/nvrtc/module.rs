@@ -1,5 +1,5 @@
 use super::ptx::Ptx;
-use crate::{CurrentCtx, bindings::CUmodule};
+use crate::{CurrentCtx, Error, bindings::CUmodule};
 use context_spore::{AsRaw, impl_spore};
 use std::{marker::PhantomData, ptr::null_mut};
 
@@ -7,10 +7,16 @@ impl_spore!(Module and ModuleSpore by (CurrentCtx, CUmodule));
 
 impl CurrentCtx {
     #[inline]
-    pub fn load(&self, ptx: &Ptx) -> Module {
+    #[track_caller]
+    pub fn load(&self, ptx: &Ptx) -> Module<'_> {
+        self.try_load(ptx).unwrap()
+    }
+
+    #[inline]
+    pub fn try_load(&self, ptx: &Ptx) -> Result<Module<'_>, Error> {
         let mut module = null_mut();
-        driver!(cuModuleLoadData(&mut module, ptx.as_ptr().cast()));
-        Module(unsafe { self.wrap_raw(module) }, PhantomData)
+        try_driver!(cuModuleLoadData(&mut module, ptx.as_ptr().cast()))?;
+        Ok(Module(unsafe { self.wrap_raw(module) }, PhantomData))
     }
 }
 
@@ -0,0 +1,469 @@
+use crate::{
+    DevByte, Error, MapError, MemProp, PhyMem, VirMem, bindings::CUdeviceptr, try_memcpy_d2d,
+};
+use std::{
+    collections::BTreeMap,
+    slice::{from_raw_parts, from_raw_parts_mut},
+    sync::Arc,
+};
+
+/// 序列的编号。
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct SeqId(usize);
+
+/// 页的使用情况。
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct PageStats {
+    pub total: usize,
+    pub free: usize,
+    pub used: usize,
+    /// 被多个序列共享的页数。
+    pub shared: usize,
+}
+
+/// 写入页之前检查共享的结果。
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CopyOnWrite {
+    /// 页只属于这个序列，可以直接写入。
+    Unique(usize),
+    /// 页被共享，已在序列中替换为新页，需要将 `src` 的内容复制到 `dst`。
+    Copy { src: usize, dst: usize },
+}
+
+/// 分页存储的簿记，记录各序列的页表和页的引用计数，不访问设备。
+///
+/// 页以 `0..total` 编号。派生的序列共享原序列的页，写入共享的页之前需要复制。
+#[derive(Clone, Debug)]
+pub struct PageTable {
+    /// 页的引用计数，0 表示空闲。
+    refs: Vec<usize>,
+    free: Vec<usize>,
+    seqs: BTreeMap<SeqId, Vec<usize>>,
+    next_seq: usize,
+}
+
+impl PageTable {
+    pub fn new(total: usize) -> Self {
+        Self {
+            refs: vec![0; total],
+            // 从编号小的页开始分配
+            free: (0..total).rev().collect(),
+            seqs: BTreeMap::new(),
+            next_seq: 0,
+        }
+    }
+
+    pub fn stats(&self) -> PageStats {
+        let total = self.refs.len();
+        let free = self.free.len();
+        PageStats {
+            total,
+            free,
+            used: total - free,
+            shared: self.refs.iter().filter(|&&n| n > 1).count(),
+        }
+    }
+
+    /// 页被多少个序列引用。
+    #[inline]
+    pub fn ref_count(&self, page: usize) -> usize {
+        self.refs[page]
+    }
+
+    /// 创建不含页的序列。
+    pub fn create_seq(&mut self) -> SeqId {
+        let seq = SeqId(self.next_seq);
+        self.next_seq += 1;
+        self.seqs.insert(seq, Vec::new());
+        seq
+    }
+
+    /// 派生与 `seq` 共享所有页的序列。
+    pub fn fork(&mut self, seq: SeqId) -> SeqId {
+        let pages = self.pages(seq).to_vec();
+        for &page in &pages {
+            self.refs[page] += 1
+        }
+        let new = self.create_seq();
+        self.seqs.insert(new, pages);
+        new
+    }
+
+    /// 移除序列，释放只属于它的页。
+    pub fn remove_seq(&mut self, seq: SeqId) {
+        let pages = self.seqs.remove(&seq).expect("sequence not found");
+        for page in pages.into_iter().rev() {
+            self.release(page)
+        }
+    }
+
+    /// 序列依次使用的页。
+    #[inline]
+    pub fn pages(&self, seq: SeqId) -> &[usize] {
+        self.seqs.get(&seq).expect("sequence not found")
+    }
+
+    /// 为序列追加一页，没有空闲页时返回 `None`。
+    pub fn push_page(&mut self, seq: SeqId) -> Option<usize> {
+        assert!(self.seqs.contains_key(&seq), "sequence not found");
+        let page = self.alloc()?;
+        self.seqs.get_mut(&seq).unwrap().push(page);
+        Some(page)
+    }
+
+    /// 将序列缩短到 `len` 页，释放只属于它的页。
+    pub fn truncate(&mut self, seq: SeqId, len: usize) {
+        let pages = self.seqs.get_mut(&seq).expect("sequence not found");
+        let tail = pages.split_off(len.min(pages.len()));
+        for page in tail.into_iter().rev() {
+            self.release(page)
+        }
+    }
+
+    /// 准备写入序列的第 `index` 页，页被共享时替换为新页。没有空闲页时返回 `None`。
+    pub fn copy_on_write(&mut self, seq: SeqId, index: usize) -> Option<CopyOnWrite> {
+        let src = self.pages(seq)[index];
+        if self.refs[src] == 1 {
+            return Some(CopyOnWrite::Unique(src));
+        }
+        let dst = self.alloc()?;
+        self.refs[src] -= 1;
+        self.seqs.get_mut(&seq).unwrap()[index] = dst;
+        Some(CopyOnWrite::Copy { src, dst })
+    }
+
+    /// 撤销 [`Self::copy_on_write`] 对共享页的替换。
+    fn undo_copy_on_write(&mut self, seq: SeqId, index: usize, src: usize, dst: usize) {
+        self.seqs.get_mut(&seq).unwrap()[index] = src;
+        self.refs[src] += 1;
+        self.release(dst)
+    }
+
+    fn alloc(&mut self) -> Option<usize> {
+        let page = self.free.pop()?;
+        self.refs[page] = 1;
+        Some(page)
+    }
+
+    fn release(&mut self, page: usize) {
+        self.refs[page] -= 1;
+        if self.refs[page] == 0 {
+            self.free.push(page)
+        }
+    }
+}
+
+/// 分页存储，用于 paged attention 的 KV cache。
+///
+/// 创建时分配固定大小的物理页，并将所有页依次映射到一个区域。
+/// 每个序列保留一段虚地址，其页依次映射到这段虚地址上，因此序列的存储是连续的。
+pub struct PagedMem {
+    table: PageTable,
+    page_len: usize,
+    max_pages: usize,
+    pages: Box<[Arc<PhyMem>]>,
+    /// 所有页依次映射的区域，用于复制页。
+    pool: VirMem,
+    seqs: BTreeMap<SeqId, VirMem>,
+}
+
+impl PagedMem {
+    /// 以 `prop` 创建 `total` 个 `page_len` 字节的物理页，每个序列最多使用 `max_pages` 页。
+    ///
+    /// `page_len` 必须是 [`MemProp::granularity_minimum`] 的整数倍。
+    #[inline]
+    #[track_caller]
+    pub fn new(prop: MemProp, page_len: usize, total: usize, max_pages: usize) -> Self {
+        Self::try_new(prop, page_len, total, max_pages).unwrap()
+    }
+
+    pub fn try_new(
+        prop: MemProp,
+        page_len: usize,
+        total: usize,
+        max_pages: usize,
+    ) -> Result<Self, MapError> {
+        if page_len == 0 || !page_len.is_multiple_of(prop.try_granularity_minimum()?) {
+            return Err(invalid_value!("page_len is a multiple of granularity_minimum").into());
+        }
+        let pool_len = page_len
+            .checked_mul(total.max(1))
+            .ok_or_else(|| invalid_value!("page_len * total fits in usize"))?;
+        let pages = (0..total)
+            .map(|_| prop.try_create(page_len))
+            .collect::<Result<Box<_>, _>>()?;
+        let mut pool = VirMem::try_new(pool_len, 0)?;
+        for (i, page) in pages.iter().enumerate() {
+            pool.try_map(i * page_len, page.clone())?;
+        }
+        Ok(Self {
+            table: PageTable::new(total),
+            page_len,
+            max_pages,
+            pages,
+            pool,
+            seqs: BTreeMap::new(),
+        })
+    }
+
+    #[inline]
+    pub const fn page_len(&self) -> usize {
+        self.page_len
+    }
+
+    #[inline]
+    pub const fn table(&self) -> &PageTable {
+        &self.table
+    }
+
+    #[inline]
+    pub fn stats(&self) -> PageStats {
+        self.table.stats()
+    }
+
+    /// 页在所有页映射的区域中的存储。
+    pub fn page(&self, page: usize) -> &[DevByte] {
+        assert!(page < self.pages.len());
+        let ptr = self.pool.as_ptr() as usize + page * self.page_len;
+        unsafe { from_raw_parts(ptr as _, self.page_len) }
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn create_seq(&mut self) -> SeqId {
+        self.try_create_seq().unwrap()
+    }
+
+    /// 创建不含页的序列，保留 `max_pages` 页的虚地址。
+    pub fn try_create_seq(&mut self) -> Result<SeqId, Error> {
+        let mem = VirMem::try_new(self.seq_len()?, 0)?;
+        let seq = self.table.create_seq();
+        self.seqs.insert(seq, mem);
+        Ok(seq)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn fork(&mut self, seq: SeqId) -> SeqId {
+        self.try_fork(seq).unwrap()
+    }
+
+    /// 派生与 `seq` 共享所有页的序列。
+    pub fn try_fork(&mut self, seq: SeqId) -> Result<SeqId, MapError> {
+        let mut mem = VirMem::try_new(self.seq_len()?, 0)?;
+        for (i, &page) in self.table.pages(seq).iter().enumerate() {
+            mem.try_map(i * self.page_len, self.pages[page].clone())?;
+        }
+        let new = self.table.fork(seq);
+        self.seqs.insert(new, mem);
+        Ok(new)
+    }
+
+    /// 每个序列保留的虚地址长度。
+    fn seq_len(&self) -> Result<usize, Error> {
+        self.page_len
+            .checked_mul(self.max_pages.max(1))
+            .ok_or_else(|| invalid_value!("page_len * max_pages fits in usize"))
+    }
+
+    /// 移除序列，解除其所有映射。
+    pub fn remove_seq(&mut self, seq: SeqId) {
+        self.table.remove_seq(seq);
+        self.seqs.remove(&seq);
+    }
+
+    /// 序列的连续存储。
+    pub fn seq(&self, seq: SeqId) -> &[DevByte] {
+        let len = self.table.pages(seq).len() * self.page_len;
+        unsafe { from_raw_parts(self.seqs[&seq].as_ptr() as _, len) }
+    }
+
+    /// 序列的连续存储。写入前应当以 [`Self::copy_on_write`] 确认页不被共享。
+    pub fn seq_mut(&mut self, seq: SeqId) -> &mut [DevByte] {
+        let len = self.table.pages(seq).len() * self.page_len;
+        let mem = self.seqs.get_mut(&seq).unwrap();
+        unsafe { from_raw_parts_mut(mem.as_mut_ptr() as _, len) }
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn push_page(&mut self, seq: SeqId) -> Option<usize> {
+        self.try_push_page(seq).unwrap()
+    }
+
+    /// 为序列追加一页并映射到序列末尾，没有空闲页时返回 `None`。
+    ///
+    /// 序列已有 `max_pages` 页时返回 [`MapError::OutOfRange`]。
+    pub fn try_push_page(&mut self, seq: SeqId) -> Result<Option<usize>, MapError> {
+        let len = self.table.pages(seq).len();
+        if len >= self.max_pages {
+            return Err(MapError::OutOfRange);
+        }
+        let Some(page) = self.table.push_page(seq) else {
+            return Ok(None);
+        };
+        let mem = self.seqs.get_mut(&seq).unwrap();
+        mem.try_map(len * self.page_len, self.pages[page].clone())
+            .inspect_err(|_| self.table.truncate(seq, len))?;
+        Ok(Some(page))
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn truncate(&mut self, seq: SeqId, len: usize) {
+        self.try_truncate(seq, len).unwrap()
+    }
+
+    /// 将序列缩短到 `len` 页，解除多余页的映射。
+    ///
+    /// 解除映射失败时，序列保留仍然映射的页。
+    pub fn try_truncate(&mut self, seq: SeqId, len: usize) -> Result<(), MapError> {
+        let old = self.table.pages(seq).len();
+        let mem = self.seqs.get_mut(&seq).unwrap();
+        for i in (len..old).rev() {
+            mem.try_unmap(i * self.page_len)
+                .inspect_err(|_| self.table.truncate(seq, i + 1))?;
+        }
+        self.table.truncate(seq, len);
+        Ok(())
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn copy_on_write(&mut self, seq: SeqId, index: usize) -> Option<CopyOnWrite> {
+        self.try_copy_on_write(seq, index).unwrap()
+    }
+
+    /// 准备写入序列的第 `index` 页，需要在上下文中调用。
+    ///
+    /// 页被共享时复制到新页，并将新页映射到序列中原来的位置。没有空闲页时返回 `None`。
+    /// 失败时序列仍使用原来的共享页。
+    pub fn try_copy_on_write(
+        &mut self,
+        seq: SeqId,
+        index: usize,
+    ) -> Result<Option<CopyOnWrite>, MapError> {
+        let ans = self.table.copy_on_write(seq, index);
+        if let Some(CopyOnWrite::Copy { src, dst }) = ans {
+            self.replace_page(seq, index, src, dst)
+                .inspect_err(|_| self.table.undo_copy_on_write(seq, index, src, dst))?
+        }
+        Ok(ans)
+    }
+
+    /// 将 `src` 页复制到 `dst` 页，并在序列的第 `index` 页处以 `dst` 替换 `src`。
+    fn replace_page(
+        &mut self,
+        seq: SeqId,
+        index: usize,
+        src: usize,
+        dst: usize,
+    ) -> Result<(), MapError> {
+        let pool = self.pool.as_ptr() as CUdeviceptr;
+        let len = self.page_len;
+        let (src_, dst_) = unsafe {
+            (
+                from_raw_parts((pool + (src * len) as CUdeviceptr) as *const DevByte, len),
+                from_raw_parts_mut((pool + (dst * len) as CUdeviceptr) as *mut DevByte, len),
+            )
+        };
+        try_memcpy_d2d(dst_, src_)?;
+        let mem = self.seqs.get_mut(&seq).unwrap();
+        mem.try_unmap(index * len)?;
+        if let Err(e) = mem.try_map(index * len, self.pages[dst].clone()) {
+            // 恢复原来的映射，使序列与页表一致
+            mem.try_map(index * len, self.pages[src].clone())?;
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_table() {
+    let mut table = PageTable::new(4);
+    let a = table.create_seq();
+    assert_eq!(table.push_page(a), Some(0));
+    assert_eq!(table.push_page(a), Some(1));
+
+    // 派生的序列共享前缀
+    let b = table.fork(a);
+    assert_eq!(table.pages(b), [0, 1]);
+    assert_eq!(table.ref_count(1), 2);
+    assert_eq!(
+        table.stats(),
+        PageStats {
+            total: 4,
+            free: 2,
+            used: 2,
+            shared: 2
+        }
+    );
+
+    // 写入共享的页之前复制
+    assert_eq!(
+        table.copy_on_write(b, 1),
+        Some(CopyOnWrite::Copy { src: 1, dst: 2 })
+    );
+    assert_eq!(table.pages(b), [0, 2]);
+    assert_eq!(table.copy_on_write(a, 1), Some(CopyOnWrite::Unique(1)));
+    assert_eq!(table.push_page(b), Some(3));
+    assert_eq!(table.push_page(a), None);
+    assert_eq!(table.stats().shared, 1);
+
+    // 共享的页在所有序列移除后才释放
+    table.remove_seq(a);
+    assert_eq!(table.stats().free, 1);
+    assert_eq!(table.ref_count(0), 1);
+    table.truncate(b, 1);
+    assert_eq!(table.stats().free, 3);
+    table.remove_seq(b);
+    assert_eq!(table.stats().free, 4)
+}
+
+#[test]
+fn test_behavior() {
+    use crate::{Device, memcpy_d2h, memcpy_h2d};
+
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    let dev = Device::new(0);
+    let prop = dev.mem_prop();
+    let page_len = prop.granularity_minimum();
+    assert!(PagedMem::try_new(prop, page_len + 1, 4, 3).is_err());
+    assert!(PagedMem::try_new(prop, page_len, usize::MAX, 3).is_err());
+    let mut mem = PagedMem::new(prop, page_len, 4, 3);
+
+    dev.context().apply(|_| {
+        let a = mem.create_seq();
+        mem.push_page(a).unwrap();
+        mem.push_page(a).unwrap();
+        let host = (0..2 * page_len).map(|i| i as u8).collect::<Vec<_>>();
+        memcpy_h2d(mem.seq_mut(a), &host);
+
+        // 派生的序列映射相同的页
+        let b = mem.fork(a);
+        let mut host_ = vec![0u8; host.len()];
+        memcpy_d2h(&mut host_, mem.seq(b));
+        assert_eq!(host, host_);
+
+        // 写入前复制共享的页，不影响原序列
+        assert!(matches!(
+            mem.copy_on_write(b, 1),
+            Some(CopyOnWrite::Copy { .. })
+        ));
+        let zeros = vec![0u8; page_len];
+        memcpy_h2d(&mut mem.seq_mut(b)[page_len..], &zeros);
+        memcpy_d2h(&mut host_, mem.seq(a));
+        assert_eq!(host, host_);
+        memcpy_d2h(&mut host_, mem.seq(b));
+        assert_eq!(host_[..page_len], host[..page_len]);
+        assert_eq!(host_[page_len..], zeros);
+
+        assert_eq!(mem.stats().used, 3);
+        mem.truncate(a, 0);
+        mem.remove_seq(b);
+        assert_eq!(mem.stats().free, 4)
+    })
+}
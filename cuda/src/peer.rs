@@ -0,0 +1,206 @@
+use crate::{
+    Context, DevByte, Device, Error, Stream,
+    bindings::{
+        CUdevice_P2PAttribute::{self, *},
+        CUresult,
+    },
+};
+use context_spore::AsRaw;
+use std::ffi::c_int;
+
+impl Device {
+    #[inline]
+    #[track_caller]
+    pub fn can_access_peer(&self, peer: &Device) -> bool {
+        self.try_can_access_peer(peer).unwrap()
+    }
+
+    /// 设备能否直接访问 `peer` 上的存储。
+    pub fn try_can_access_peer(&self, peer: &Device) -> Result<bool, Error> {
+        let mut ans = 0;
+        try_driver!(cuDeviceCanAccessPeer(
+            &mut ans,
+            self.as_raw(),
+            peer.as_raw()
+        ))?;
+        Ok(ans != 0)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn p2p_performance_rank(&self, peer: &Device) -> c_int {
+        self.try_p2p_performance_rank(peer).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn p2p_access_supported(&self, peer: &Device) -> bool {
+        self.try_p2p_access_supported(peer).unwrap()
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn p2p_native_atomic_supported(&self, peer: &Device) -> bool {
+        self.try_p2p_native_atomic_supported(peer).unwrap()
+    }
+
+    /// 设备访问 `peer` 的相对性能，值越小性能越好。
+    #[inline]
+    pub fn try_p2p_performance_rank(&self, peer: &Device) -> Result<c_int, Error> {
+        self.try_get_p2p_attribute(peer, CU_DEVICE_P2P_ATTRIBUTE_PERFORMANCE_RANK)
+    }
+
+    #[inline]
+    pub fn try_p2p_access_supported(&self, peer: &Device) -> Result<bool, Error> {
+        self.try_get_p2p_attribute(peer, CU_DEVICE_P2P_ATTRIBUTE_ACCESS_SUPPORTED)
+            .map(|value| value != 0)
+    }
+
+    /// 设备能否对 `peer` 上的存储执行原子操作。
+    #[inline]
+    pub fn try_p2p_native_atomic_supported(&self, peer: &Device) -> Result<bool, Error> {
+        self.try_get_p2p_attribute(peer, CU_DEVICE_P2P_ATTRIBUTE_NATIVE_ATOMIC_SUPPORTED)
+            .map(|value| value != 0)
+    }
+
+    fn try_get_p2p_attribute(
+        &self,
+        peer: &Device,
+        attr: CUdevice_P2PAttribute,
+    ) -> Result<c_int, Error> {
+        let mut value = 0;
+        try_driver!(cuDeviceGetP2PAttribute(
+            &mut value,
+            attr,
+            self.as_raw(),
+            peer.as_raw()
+        ))?;
+        Ok(value)
+    }
+}
+
+impl Context {
+    #[inline]
+    #[track_caller]
+    pub fn enable_peer_access(&self, peer: &Context) {
+        self.try_enable_peer_access(peer).unwrap()
+    }
+
+    /// 允许这个上下文中的任务访问 `peer` 中分配的存储。已经允许时什么也不做。
+    pub fn try_enable_peer_access(&self, peer: &Context) -> Result<(), Error> {
+        self.try_apply(
+            |_| match try_driver!(cuCtxEnablePeerAccess(peer.as_raw(), 0)) {
+                Err(e) if e.code() == CUresult::CUDA_ERROR_PEER_ACCESS_ALREADY_ENABLED => Ok(()),
+                ans => ans,
+            },
+        )?
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn disable_peer_access(&self, peer: &Context) {
+        self.try_disable_peer_access(peer).unwrap()
+    }
+
+    /// 禁止这个上下文中的任务访问 `peer` 中分配的存储。未曾允许时什么也不做。
+    pub fn try_disable_peer_access(&self, peer: &Context) -> Result<(), Error> {
+        self.try_apply(
+            |_| match try_driver!(cuCtxDisablePeerAccess(peer.as_raw())) {
+                Err(e) if e.code() == CUresult::CUDA_ERROR_PEER_ACCESS_NOT_ENABLED => Ok(()),
+                ans => ans,
+            },
+        )?
+    }
+}
+
+impl Stream<'_> {
+    #[inline]
+    #[track_caller]
+    pub fn memcpy_peer(
+        &self,
+        dst: &mut [DevByte],
+        dst_ctx: &Context,
+        src: &[DevByte],
+        src_ctx: &Context,
+    ) -> &Self {
+        self.try_memcpy_peer(dst, dst_ctx, src, src_ctx).unwrap()
+    }
+
+    /// 在不同上下文的存储之间拷贝，`dst` 在 `dst_ctx` 中分配，`src` 在 `src_ctx` 中分配。
+    ///
+    /// 不需要允许对等访问，捕获时记录为拷贝节点。
+    pub fn try_memcpy_peer(
+        &self,
+        dst: &mut [DevByte],
+        dst_ctx: &Context,
+        src: &[DevByte],
+        src_ctx: &Context,
+    ) -> Result<&Self, Error> {
+        let len = size_of_val(src);
+        if len != size_of_val(dst) {
+            return Err(invalid_value!("dst and src have the same length"));
+        }
+        try_driver!(cuMemcpyPeerAsync(
+            dst.as_mut_ptr() as _,
+            dst_ctx.as_raw(),
+            src.as_ptr() as _,
+            src_ctx.as_raw(),
+            len,
+            self.as_raw()
+        ))?;
+        Ok(self)
+    }
+}
+
+#[test]
+fn test_behavior() {
+    use crate::{GraphNode, memcpy_d2h};
+
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    if Device::count() < 2 {
+        return;
+    }
+    let (dev0, dev1) = (Device::new(0), Device::new(1));
+    println!(
+        "can access peer: {}, performance rank: {}",
+        dev0.can_access_peer(&dev1),
+        dev0.p2p_performance_rank(&dev1)
+    );
+    let (ctx0, ctx1) = (dev0.context(), dev1.context());
+    if dev0.can_access_peer(&dev1) {
+        assert!(dev0.p2p_access_supported(&dev1));
+        // 重复允许和禁止不是错误
+        ctx0.enable_peer_access(&ctx1);
+        ctx0.enable_peer_access(&ctx1);
+        ctx0.disable_peer_access(&ctx1);
+        ctx0.disable_peer_access(&ctx1)
+    }
+
+    let host = (0..1024u32).collect::<Vec<_>>();
+    ctx1.apply(|c1| {
+        let src = c1.from_host(&host);
+        ctx0.apply(|c0| {
+            let mut dst = c0.malloc::<u32>(host.len());
+            let mut host_ = vec![0u32; host.len()];
+            c0.stream()
+                .memcpy_peer(&mut dst, &ctx0, &src, &ctx1)
+                .synchronize();
+            memcpy_d2h(&mut host_, &dst);
+            assert_eq!(host, host_);
+
+            // 捕获为拷贝节点
+            let mut dst = c0.malloc::<u32>(host.len());
+            let stream = c0.stream().capture();
+            stream.memcpy_peer(&mut dst, &ctx0, &src, &ctx1);
+            let graph = stream.end();
+            assert!(matches!(&*graph.nodes(), [GraphNode::Memcpy(_)]));
+            c0.stream()
+                .launch_graph(&c0.instantiate(&graph))
+                .synchronize();
+            memcpy_d2h(&mut host_, &dst);
+            assert_eq!(host, host_)
+        })
+    })
+}
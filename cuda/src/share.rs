@@ -0,0 +1,133 @@
+use std::{
+    io,
+    mem::MaybeUninit,
+    os::{
+        fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+        unix::net::UnixStream,
+    },
+    ptr::null_mut,
+};
+
+/// 通过 unix socket 发送文件描述符。
+///
+/// 导出的存储池和物理页以文件描述符表示，接收方通过 [`recv_fd`] 得到指向同一对象的文件描述符。
+pub fn send_fd(socket: &UnixStream, fd: BorrowedFd) -> io::Result<()> {
+    let mut byte = 0u8;
+    let mut iov = libc::iovec {
+        iov_base: (&raw mut byte).cast(),
+        iov_len: 1,
+    };
+    let mut control = Control::new();
+    let mut msg = control.msg(&mut iov);
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(size_of::<RawFd>() as _) as _;
+        libc::CMSG_DATA(cmsg)
+            .cast::<RawFd>()
+            .write_unaligned(fd.as_raw_fd());
+    }
+    msg.msg_controllen = Control::LEN as _;
+    match unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, 0) } {
+        1 => Ok(()),
+        n if n < 0 => Err(io::Error::last_os_error()),
+        _ => Err(io::ErrorKind::WriteZero.into()),
+    }
+}
+
+/// 从 unix socket 接收 [`send_fd`] 发送的文件描述符。
+pub fn recv_fd(socket: &UnixStream) -> io::Result<OwnedFd> {
+    let mut byte = 0u8;
+    let mut iov = libc::iovec {
+        iov_base: (&raw mut byte).cast(),
+        iov_len: 1,
+    };
+    let mut control = Control::new();
+    let mut msg = control.msg(&mut iov);
+    // 只有 linux 支持接收时原子地设置 close-on-exec，其他系统接收后再设置
+    #[cfg(target_os = "linux")]
+    let flags = libc::MSG_CMSG_CLOEXEC;
+    #[cfg(not(target_os = "linux"))]
+    let flags = 0;
+    match unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, flags) } {
+        n if n < 0 => return Err(io::Error::last_os_error()),
+        0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+        _ => {}
+    }
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        if cmsg.is_null()
+            || (*cmsg).cmsg_level != libc::SOL_SOCKET
+            || (*cmsg).cmsg_type != libc::SCM_RIGHTS
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "no file descriptor received",
+            ));
+        }
+        let fd = OwnedFd::from_raw_fd(libc::CMSG_DATA(cmsg).cast::<RawFd>().read_unaligned());
+        #[cfg(not(target_os = "linux"))]
+        if libc::fcntl(fd.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(fd)
+    }
+}
+
+/// 容纳一个文件描述符的控制消息缓冲区。
+#[repr(C)]
+struct Control {
+    _align: [libc::cmsghdr; 0],
+    buf: [MaybeUninit<u8>; 64],
+}
+
+impl Control {
+    const LEN: usize = unsafe { libc::CMSG_SPACE(size_of::<RawFd>() as _) } as _;
+
+    fn new() -> Self {
+        const { assert!(Self::LEN <= 64) }
+        Self {
+            _align: [],
+            buf: [MaybeUninit::new(0); 64],
+        }
+    }
+
+    fn msg(&mut self, iov: &mut libc::iovec) -> libc::msghdr {
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_name = null_mut();
+        msg.msg_iov = iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = self.buf.as_mut_ptr().cast();
+        msg.msg_controllen = Self::LEN as _;
+        msg
+    }
+}
+
+#[test]
+fn test_behavior() {
+    use std::{
+        fs::File,
+        io::{Read, Seek, Write},
+        os::fd::AsFd,
+    };
+
+    let (a, b) = UnixStream::pair().unwrap();
+    let path = std::env::temp_dir().join(format!("cuda-share-{}", std::process::id()));
+    let mut file = File::options()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    file.write_all(b"hello").unwrap();
+
+    send_fd(&a, file.as_fd()).unwrap();
+    let mut received = File::from(recv_fd(&b).unwrap());
+    let mut text = String::new();
+    received.rewind().unwrap();
+    received.read_to_string(&mut text).unwrap();
+    assert_eq!(text, "hello")
+}
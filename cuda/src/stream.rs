@@ -1,15 +1,97 @@
-use crate::{CurrentCtx, Dim3, KernelFn, bindings::CUstream};
+use crate::{
+    CurrentCtx, Dim3, Error, KernelFn,
+    bindings::{CUstream, CUstream_flags},
+};
 use context_spore::{AsRaw, impl_spore};
-use std::{ffi::c_void, marker::PhantomData, ptr::null_mut};
+use std::{
+    ffi::{c_int, c_uint, c_void},
+    marker::PhantomData,
+    ptr::null_mut,
+};
 
 impl_spore!(Stream and StreamSpore by (CurrentCtx, CUstream));
 
 impl CurrentCtx {
     #[inline]
-    pub fn stream(&self) -> Stream {
+    #[track_caller]
+    pub fn stream(&self) -> Stream<'_> {
+        self.try_stream().unwrap()
+    }
+
+    #[inline]
+    pub fn try_stream(&self) -> Result<Stream<'_>, Error> {
+        self.stream_builder().try_build()
+    }
+
+    #[inline]
+    pub fn stream_builder(&self) -> StreamBuilder<'_> {
+        StreamBuilder {
+            ctx: self,
+            flags: CUstream_flags::CU_STREAM_DEFAULT as _,
+            priority: None,
+        }
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn stream_priority_range(&self) -> (c_int, c_int) {
+        self.try_stream_priority_range().unwrap()
+    }
+
+    /// 返回 `(最低优先级, 最高优先级)`，数值越小优先级越高。
+    pub fn try_stream_priority_range(&self) -> Result<(c_int, c_int), Error> {
+        let mut least = 0;
+        let mut greatest = 0;
+        try_driver!(cuCtxGetStreamPriorityRange(&mut least, &mut greatest))?;
+        Ok((least, greatest))
+    }
+}
+
+/// 按指定的标志和优先级创建流。
+pub struct StreamBuilder<'ctx> {
+    ctx: &'ctx CurrentCtx,
+    flags: c_uint,
+    priority: Option<c_int>,
+}
+
+impl<'ctx> StreamBuilder<'ctx> {
+    /// 流上的任务不与空流同步。
+    pub fn non_blocking(mut self, non_blocking: bool) -> Self {
+        const FLAG: c_uint = CUstream_flags::CU_STREAM_NON_BLOCKING as _;
+        if non_blocking {
+            self.flags |= FLAG
+        } else {
+            self.flags &= !FLAG
+        }
+        self
+    }
+
+    /// 流的优先级，数值越小优先级越高，超出 [`CurrentCtx::stream_priority_range`] 的值会被截断。
+    pub fn priority(mut self, priority: c_int) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn build(self) -> Stream<'ctx> {
+        self.try_build().unwrap()
+    }
+
+    pub fn try_build(self) -> Result<Stream<'ctx>, Error> {
+        let Self {
+            ctx,
+            flags,
+            priority,
+        } = self;
         let mut stream = null_mut();
-        driver!(cuStreamCreate(&mut stream, 0));
-        Stream(unsafe { self.wrap_raw(stream) }, PhantomData)
+        match priority {
+            Some(priority) => {
+                try_driver!(cuStreamCreateWithPriority(&mut stream, flags, priority))?
+            }
+            None => try_driver!(cuStreamCreate(&mut stream, flags))?,
+        }
+        Ok(Stream(unsafe { ctx.wrap_raw(stream) }, PhantomData))
     }
 }
 
@@ -30,16 +112,27 @@ impl AsRaw for Stream<'_> {
 }
 
 impl Stream<'_> {
+    #[inline]
+    #[track_caller]
     pub fn launch(
         &self,
         f: &KernelFn,
         attrs: (impl Into<Dim3>, impl Into<Dim3>, usize),
         params: &[*const c_void],
     ) -> &Self {
+        self.try_launch(f, attrs, params).unwrap()
+    }
+
+    pub fn try_launch(
+        &self,
+        f: &KernelFn,
+        attrs: (impl Into<Dim3>, impl Into<Dim3>, usize),
+        params: &[*const c_void],
+    ) -> Result<&Self, Error> {
         let (grid, block, shared_mem) = attrs;
         let grid = grid.into();
         let block = block.into();
-        driver!(cuLaunchKernel(
+        try_driver!(cuLaunchKernel(
             f.as_raw(),
             grid.x,
             grid.y,
@@ -51,13 +144,89 @@ impl Stream<'_> {
             self.0.rss,
             params.as_ptr() as _,
             null_mut(),
-        ));
-        self
+        ))?;
+        Ok(self)
     }
 
     #[inline]
+    #[track_caller]
     pub fn synchronize(&self) -> &Self {
-        driver!(cuStreamSynchronize(self.0.rss));
-        self
+        self.try_synchronize().unwrap()
+    }
+
+    #[inline]
+    pub fn try_synchronize(&self) -> Result<&Self, Error> {
+        try_driver!(cuStreamSynchronize(self.0.rss))?;
+        Ok(self)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn flags(&self) -> c_uint {
+        self.try_flags().unwrap()
+    }
+
+    pub fn try_flags(&self) -> Result<c_uint, Error> {
+        let mut flags = 0;
+        try_driver!(cuStreamGetFlags(self.0.rss, &mut flags))?;
+        Ok(flags)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn is_non_blocking(&self) -> bool {
+        self.flags() & CUstream_flags::CU_STREAM_NON_BLOCKING as c_uint != 0
     }
+
+    #[inline]
+    #[track_caller]
+    pub fn priority(&self) -> c_int {
+        self.try_priority().unwrap()
+    }
+
+    pub fn try_priority(&self) -> Result<c_int, Error> {
+        let mut priority = 0;
+        try_driver!(cuStreamGetPriority(self.0.rss, &mut priority))?;
+        Ok(priority)
+    }
+
+    #[cfg(nvidia)]
+    #[inline]
+    #[track_caller]
+    pub fn id(&self) -> u64 {
+        self.try_id().unwrap()
+    }
+
+    /// 流在进程中的唯一标识。
+    #[cfg(nvidia)]
+    pub fn try_id(&self) -> Result<u64, Error> {
+        let mut id = 0;
+        try_driver!(cuStreamGetId(self.0.rss, &mut id))?;
+        Ok(id)
+    }
+}
+
+#[test]
+fn test_behavior() {
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    crate::Device::new(0).context().apply(|ctx| {
+        let (least, greatest) = ctx.stream_priority_range();
+        assert!(greatest <= least);
+
+        let stream = ctx.stream();
+        assert!(!stream.is_non_blocking());
+        assert_eq!(stream.priority(), least);
+
+        let high = ctx
+            .stream_builder()
+            .non_blocking(true)
+            .priority(greatest)
+            .build();
+        assert!(high.is_non_blocking());
+        assert_eq!(high.priority(), greatest);
+        #[cfg(nvidia)]
+        assert_ne!(stream.id(), high.id());
+    })
 }
@@ -0,0 +1,307 @@
+use crate::{
+    DevByte, Error, Stream,
+    bindings::{CUDA_MEMCPY2D, CUDA_MEMCPY3D, CUdeviceptr, CUmemorytype},
+};
+use context_spore::AsRaw;
+use std::{
+    marker::PhantomData,
+    ptr::{null, null_mut},
+};
+
+/// 二维或三维拷贝一侧的布局。
+#[derive(Clone, Copy)]
+struct Layout {
+    ty: CUmemorytype,
+    ptr: usize,
+    len: usize,
+    pitch: usize,
+    height: usize,
+    offset: (usize, usize, usize),
+}
+
+impl Layout {
+    fn new(ty: CUmemorytype, ptr: usize, len: usize, pitch: usize) -> Self {
+        Self {
+            ty,
+            ptr,
+            len,
+            pitch,
+            height: 0,
+            offset: (0, 0, 0),
+        }
+    }
+
+    /// 检查拷贝范围不超出存储，返回每层的行数。
+    fn check(&self, (width, height, depth): (usize, usize, usize)) -> Result<usize, Error> {
+        let overflow = || invalid_value!("strided view fits in usize");
+        let (x, y, z) = self.offset;
+        let rows_end = y.checked_add(height).ok_or_else(overflow)?;
+        let layer = if self.height == 0 {
+            rows_end
+        } else {
+            self.height
+        };
+        if width == 0 || height == 0 || depth == 0 {
+            return Ok(layer);
+        }
+        let row_end = x.checked_add(width).ok_or_else(overflow)?;
+        if self.pitch < row_end {
+            return Err(invalid_value!("row fits in pitch"));
+        }
+        if layer < rows_end {
+            return Err(invalid_value!("rows fit in layer"));
+        }
+        let end = z
+            .checked_add(depth - 1)
+            .and_then(|layers| layers.checked_mul(layer))
+            .and_then(|rows| rows.checked_add(rows_end - 1))
+            .and_then(|rows| rows.checked_mul(self.pitch))
+            .and_then(|bytes| bytes.checked_add(row_end))
+            .ok_or_else(overflow)?;
+        if end > self.len {
+            return Err(invalid_value!("strided view fits in its storage"));
+        }
+        Ok(layer)
+    }
+}
+
+macro_rules! strided_view {
+    ($name:ident, $slice:ty, $dev:ty, $as_ptr:ident) => {
+        impl<'a> $name<'a> {
+            /// 主机上的存储，行之间间隔 `pitch` 字节。
+            #[inline]
+            pub fn host<T: Copy>(slice: $slice, pitch: usize) -> Self {
+                let len = size_of_val(slice);
+                let ptr = slice.$as_ptr() as usize;
+                Self(
+                    Layout::new(CUmemorytype::CU_MEMORYTYPE_HOST, ptr, len, pitch),
+                    PhantomData,
+                )
+            }
+
+            /// 设备上的存储，行之间间隔 `pitch` 字节。视图的生命周期只约束主机存储。
+            #[inline]
+            pub fn device(slice: $dev, pitch: usize) -> Self {
+                let len = slice.len();
+                let ptr = slice.$as_ptr() as usize;
+                Self(
+                    Layout::new(CUmemorytype::CU_MEMORYTYPE_DEVICE, ptr, len, pitch),
+                    PhantomData,
+                )
+            }
+
+            /// 三维存储每层的行数，只拷贝一层时不需要指定。
+            #[inline]
+            pub fn height(mut self, height: usize) -> Self {
+                self.0.height = height;
+                self
+            }
+
+            /// 拷贝区域的起点，`x` 以字节计，`y` 以行计，`z` 以层计。
+            #[inline]
+            pub fn offset(mut self, x: usize, y: usize, z: usize) -> Self {
+                self.0.offset = (x, y, z);
+                self
+            }
+        }
+    };
+}
+
+/// 二维或三维拷贝的源存储。
+#[derive(Clone, Copy)]
+pub struct StridedView<'a>(Layout, PhantomData<&'a [u8]>);
+
+/// 二维或三维拷贝的目标存储。
+pub struct StridedViewMut<'a>(Layout, PhantomData<&'a mut [u8]>);
+
+strided_view!(StridedView, &'a [T], &[DevByte], as_ptr);
+strided_view!(StridedViewMut, &'a mut [T], &mut [DevByte], as_mut_ptr);
+
+impl Stream<'_> {
+    #[inline]
+    #[track_caller]
+    pub fn memcpy_2d(
+        &self,
+        dst: StridedViewMut,
+        src: StridedView,
+        extent: (usize, usize),
+    ) -> &Self {
+        self.try_memcpy_2d(dst, src, extent).unwrap()
+    }
+
+    /// 拷贝 `extent` 行数的矩形区域，宽度以字节计。
+    pub fn try_memcpy_2d(
+        &self,
+        dst: StridedViewMut,
+        src: StridedView,
+        (width, height): (usize, usize),
+    ) -> Result<&Self, Error> {
+        let (dst, src) = (dst.0, src.0);
+        dst.check((width, height, 1))?;
+        src.check((width, height, 1))?;
+        let params = CUDA_MEMCPY2D {
+            srcXInBytes: src.offset.0,
+            srcY: src.offset.1,
+            srcMemoryType: src.ty,
+            srcHost: host_ptr(&src),
+            srcDevice: dev_ptr(&src),
+            srcArray: null_mut(),
+            srcPitch: src.pitch,
+            dstXInBytes: dst.offset.0,
+            dstY: dst.offset.1,
+            dstMemoryType: dst.ty,
+            dstHost: host_ptr(&dst) as _,
+            dstDevice: dev_ptr(&dst),
+            dstArray: null_mut(),
+            dstPitch: dst.pitch,
+            WidthInBytes: width,
+            Height: height,
+        };
+        try_driver!(cuMemcpy2DAsync_v2(&params, self.as_raw()))?;
+        Ok(self)
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn memcpy_3d(
+        &self,
+        dst: StridedViewMut,
+        src: StridedView,
+        extent: (usize, usize, usize),
+    ) -> &Self {
+        self.try_memcpy_3d(dst, src, extent).unwrap()
+    }
+
+    /// 拷贝 `extent` 层数的长方体区域，宽度以字节计。
+    pub fn try_memcpy_3d(
+        &self,
+        dst: StridedViewMut,
+        src: StridedView,
+        extent: (usize, usize, usize),
+    ) -> Result<&Self, Error> {
+        let params = memcpy_3d_params(dst, src, extent)?;
+        try_driver!(cuMemcpy3DAsync_v2(&params, self.as_raw()))?;
+        Ok(self)
+    }
+}
+
+/// 检查拷贝范围并构造三维拷贝的参数。
+pub(crate) fn memcpy_3d_params(
+    dst: StridedViewMut,
+    src: StridedView,
+    extent: (usize, usize, usize),
+) -> Result<CUDA_MEMCPY3D, Error> {
+    let (dst, src) = (dst.0, src.0);
+    let dst_height = dst.check(extent)?;
+    let src_height = src.check(extent)?;
+    let (width, height, depth) = extent;
+    Ok(CUDA_MEMCPY3D {
+        srcXInBytes: src.offset.0,
+        srcY: src.offset.1,
+        srcZ: src.offset.2,
+        srcLOD: 0,
+        srcMemoryType: src.ty,
+        srcHost: host_ptr(&src),
+        srcDevice: dev_ptr(&src),
+        srcArray: null_mut(),
+        reserved0: null_mut(),
+        srcPitch: src.pitch,
+        srcHeight: src_height,
+        dstXInBytes: dst.offset.0,
+        dstY: dst.offset.1,
+        dstZ: dst.offset.2,
+        dstLOD: 0,
+        dstMemoryType: dst.ty,
+        dstHost: host_ptr(&dst) as _,
+        dstDevice: dev_ptr(&dst),
+        dstArray: null_mut(),
+        reserved1: null_mut(),
+        dstPitch: dst.pitch,
+        dstHeight: dst_height,
+        WidthInBytes: width,
+        Height: height,
+        Depth: depth,
+    })
+}
+
+fn host_ptr(layout: &Layout) -> *const std::ffi::c_void {
+    match layout.ty {
+        CUmemorytype::CU_MEMORYTYPE_HOST => layout.ptr as _,
+        _ => null(),
+    }
+}
+
+fn dev_ptr(layout: &Layout) -> CUdeviceptr {
+    match layout.ty {
+        CUmemorytype::CU_MEMORYTYPE_DEVICE => layout.ptr as _,
+        _ => 0,
+    }
+}
+
+#[test]
+fn test_behavior() {
+    use crate::{Device, memcpy_d2h};
+
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    Device::new(0).context().apply(|ctx| {
+        // 6 行 5 列的主机矩阵
+        const W: usize = 5;
+        const H: usize = 6;
+        let host = (0..(W * H) as u32).collect::<Vec<_>>();
+        let (mut mat, pitch) = ctx.malloc_pitch::<u32>(W, H);
+        assert!(pitch >= W * size_of::<u32>());
+        assert_eq!(mat.len(), pitch * H);
+
+        let stream = ctx.stream();
+        let row = W * size_of::<u32>();
+        stream.memcpy_2d(
+            StridedViewMut::device(&mut mat, pitch),
+            StridedView::host(&host, row),
+            (row, H),
+        );
+        // 取出第 1..4 行、第 2..5 列的子矩阵
+        let mut sub = vec![0u32; 3 * 3];
+        stream
+            .memcpy_2d(
+                StridedViewMut::host(&mut sub, 3 * size_of::<u32>()),
+                StridedView::device(&mat, pitch).offset(2 * size_of::<u32>(), 1, 0),
+                (3 * size_of::<u32>(), 3),
+            )
+            .synchronize();
+        assert_eq!(sub, [7, 8, 9, 12, 13, 14, 17, 18, 19]);
+
+        // 把矩阵看作 2 层、每层 3 行的张量，取出每层的第 1 行
+        let mut dev = ctx.malloc::<u32>(2 * W);
+        stream
+            .memcpy_3d(
+                StridedViewMut::device(&mut dev, row).height(1),
+                StridedView::device(&mat, pitch).height(3).offset(0, 1, 0),
+                (row, 1, 2),
+            )
+            .synchronize();
+        let mut slice = vec![0u32; 2 * W];
+        memcpy_d2h(&mut slice, &dev);
+        assert_eq!(slice, [5, 6, 7, 8, 9, 20, 21, 22, 23, 24]);
+
+        // 超出存储或溢出的范围返回错误而不是拷贝
+        use crate::bindings::CUresult::CUDA_ERROR_INVALID_VALUE;
+        let Err(err) = stream.try_memcpy_2d(
+            StridedViewMut::host(&mut sub, 3 * size_of::<u32>()),
+            StridedView::device(&mat, pitch).offset(0, H - 1, 0),
+            (3 * size_of::<u32>(), 3),
+        ) else {
+            panic!()
+        };
+        assert_eq!(err.code(), CUDA_ERROR_INVALID_VALUE);
+        let Err(err) = stream.try_memcpy_3d(
+            StridedViewMut::host(&mut sub, 3 * size_of::<u32>()),
+            StridedView::device(&mat, pitch).offset(0, 0, usize::MAX),
+            (3 * size_of::<u32>(), 3, 1),
+        ) else {
+            panic!()
+        };
+        assert_eq!(err.code(), CUDA_ERROR_INVALID_VALUE);
+    })
+}
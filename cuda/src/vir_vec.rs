@@ -0,0 +1,176 @@
+use crate::{DevSlice, Error, MapError, MemProp, VirMem};
+use std::{
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+/// 以 `T` 为元素、可以增长的设备数组。
+///
+/// 创建时保留足够大的虚地址区域，增长时按块映射物理页，缩小时解除映射。
+/// 增长不需要重新分配和拷贝，元素的地址保持不变。
+pub struct VirVec<T> {
+    mem: VirMem,
+    prop: MemProp,
+    /// 每次映射的物理页大小。
+    chunk: usize,
+    /// 已映射的字节数。
+    mapped: usize,
+    len: usize,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Copy> VirVec<T> {
+    /// 保留能容纳 `capacity` 个元素的虚地址区域，物理页以 `prop` 创建。
+    #[inline]
+    #[track_caller]
+    pub fn new(prop: MemProp, capacity: usize) -> Self {
+        Self::try_new(prop, capacity).unwrap()
+    }
+
+    pub fn try_new(prop: MemProp, capacity: usize) -> Result<Self, Error> {
+        assert_ne!(size_of::<T>(), 0);
+        let chunk = prop.try_granularity_recommended()?;
+        let len = capacity
+            .checked_mul(size_of::<T>())
+            .and_then(|bytes| bytes.div_ceil(chunk).max(1).checked_mul(chunk))
+            .ok_or_else(|| invalid_value!("capacity * size_of::<T>() fits in usize"))?;
+        Ok(Self {
+            mem: VirMem::try_new(len, 0)?,
+            prop,
+            chunk,
+            mapped: 0,
+            len: 0,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<T> VirVec<T> {
+    #[inline]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 保留的虚地址区域能容纳的元素数量。
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.mem.len() / size_of::<T>()
+    }
+
+    /// 已映射物理页的字节数。
+    #[inline]
+    pub const fn mapped_bytes(&self) -> usize {
+        self.mapped
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn resize(&mut self, len: usize) {
+        self.try_resize(len).unwrap()
+    }
+
+    /// 将数组长度改为 `len`，按需映射或解除映射物理页。
+    ///
+    /// 新增元素的值不确定，解除映射的物理页被释放。
+    /// `len` 超过容量时返回 [`MapError::OutOfRange`]，映射失败时已映射的物理页保留。
+    pub fn try_resize(&mut self, len: usize) -> Result<(), MapError> {
+        if len > self.capacity() {
+            return Err(MapError::OutOfRange);
+        }
+        let bytes = len * size_of::<T>();
+        while self.mapped < bytes {
+            let phy = self.prop.try_create(self.chunk)?;
+            self.mem.try_map(self.mapped, phy)?;
+            self.mapped += self.chunk
+        }
+        while self.mapped >= bytes + self.chunk {
+            self.mem.try_unmap(self.mapped - self.chunk)?;
+            self.mapped -= self.chunk
+        }
+        self.len = len;
+        Ok(())
+    }
+}
+
+impl<T: Copy> VirVec<T> {
+    #[inline]
+    #[track_caller]
+    pub fn extend_from_host(&mut self, src: &[T]) {
+        self.try_extend_from_host(src).unwrap()
+    }
+
+    /// 将主机上的元素追加到数组末尾，需要在上下文中调用。
+    pub fn try_extend_from_host(&mut self, src: &[T]) -> Result<(), MapError> {
+        let len = self.len;
+        let new_len = len.checked_add(src.len()).ok_or(MapError::OutOfRange)?;
+        self.try_resize(new_len)?;
+        self[len..].try_copy_from_host(src)?;
+        Ok(())
+    }
+}
+
+impl<T> Deref for VirVec<T> {
+    type Target = DevSlice<T>;
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        DevSlice::from_vir(&self.mem[..self.len * size_of::<T>()])
+    }
+}
+
+impl<T> DerefMut for VirVec<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        let len = self.len * size_of::<T>();
+        DevSlice::from_vir_mut(&mut self.mem[..len])
+    }
+}
+
+#[test]
+fn test_behavior() {
+    use crate::Device;
+
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    let dev = Device::new(0);
+    let prop = dev.mem_prop();
+    let chunk = prop.granularity_recommended();
+    let n = chunk / size_of::<u32>();
+
+    let mut vec = VirVec::<u32>::new(prop, 4 * n);
+    assert!(vec.is_empty());
+    assert_eq!(vec.capacity(), 4 * n);
+    assert_eq!(vec.mapped_bytes(), 0);
+
+    dev.context().apply(|_| {
+        // 增长到跨越两个块
+        let host = (0..n as u32 + 1).collect::<Vec<_>>();
+        vec.extend_from_host(&host);
+        assert_eq!(vec.len(), n + 1);
+        assert_eq!(vec.mapped_bytes(), 2 * chunk);
+        let ptr = vec.as_ptr();
+
+        // 继续增长，已有元素的地址和值不变
+        vec.extend_from_host(&host);
+        assert_eq!(vec.as_ptr(), ptr);
+        assert_eq!(vec.mapped_bytes(), 3 * chunk);
+        assert_eq!(vec[..n + 1].to_vec(), host);
+        assert_eq!(vec[n + 1..].to_vec(), host);
+
+        // 缩小时解除映射多余的块
+        vec.resize(n);
+        assert_eq!(vec.mapped_bytes(), chunk);
+        assert_eq!(vec.to_vec(), host[..n]);
+        vec.resize(0);
+        assert_eq!(vec.mapped_bytes(), 0);
+
+        // 超出容量时不改变数组
+        assert_eq!(vec.try_resize(4 * n + 1), Err(crate::MapError::OutOfRange));
+        assert_eq!(vec.mapped_bytes(), 0)
+    })
+}
@@ -1,5 +1,5 @@
 ﻿use crate::{
-    DevByte, Device,
+    DevByte, Device, Error,
     bindings::{
         CUdeviceptr, CUmemAccess_flags, CUmemAccessDesc, CUmemAllocationGranularity_flags,
         CUmemAllocationHandleType, CUmemAllocationProp, CUmemAllocationType,
@@ -9,6 +9,7 @@
 use context_spore::AsRaw;
 use std::{
     collections::BTreeMap,
+    fmt,
     ops::{Deref, DerefMut},
     ptr::null_mut,
     slice::{from_raw_parts, from_raw_parts_mut},
@@ -35,20 +36,49 @@ impl Device {
 }
 
 impl MemProp {
+    /// 物理页可以通过 POSIX 文件描述符导出到其他进程。
+    #[cfg(unix)]
     #[inline]
+    pub fn shareable(mut self, value: bool) -> Self {
+        self.0.requestedHandleTypes = if value {
+            CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_POSIX_FILE_DESCRIPTOR
+        } else {
+            CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_NONE
+        };
+        self
+    }
+
+    #[inline]
+    pub fn handle_type(&self) -> CUmemAllocationHandleType {
+        self.0.requestedHandleTypes
+    }
+
+    #[inline]
+    #[track_caller]
     pub fn granularity_minimum(&self) -> usize {
-        self.granularity(CUmemAllocationGranularity_flags::CU_MEM_ALLOC_GRANULARITY_MINIMUM)
+        self.try_granularity_minimum().unwrap()
     }
 
     #[inline]
+    #[track_caller]
     pub fn granularity_recommended(&self) -> usize {
-        self.granularity(CUmemAllocationGranularity_flags::CU_MEM_ALLOC_GRANULARITY_RECOMMENDED)
+        self.try_granularity_recommended().unwrap()
+    }
+
+    #[inline]
+    pub fn try_granularity_minimum(&self) -> Result<usize, Error> {
+        self.try_granularity(CUmemAllocationGranularity_flags::CU_MEM_ALLOC_GRANULARITY_MINIMUM)
     }
 
-    fn granularity(&self, type_: CUmemAllocationGranularity_flags) -> usize {
+    #[inline]
+    pub fn try_granularity_recommended(&self) -> Result<usize, Error> {
+        self.try_granularity(CUmemAllocationGranularity_flags::CU_MEM_ALLOC_GRANULARITY_RECOMMENDED)
+    }
+
+    fn try_granularity(&self, type_: CUmemAllocationGranularity_flags) -> Result<usize, Error> {
         let mut size = 0;
-        driver!(cuMemGetAllocationGranularity(&mut size, &self.0, type_));
-        size
+        try_driver!(cuMemGetAllocationGranularity(&mut size, &self.0, type_))?;
+        Ok(size)
     }
 }
 
@@ -63,14 +93,20 @@ pub struct VirMem {
 }
 
 impl VirMem {
+    #[inline]
+    #[track_caller]
     pub fn new(len: usize, min_addr: usize) -> Self {
+        Self::try_new(len, min_addr).unwrap()
+    }
+
+    pub fn try_new(len: usize, min_addr: usize) -> Result<Self, Error> {
         let mut ptr = 0;
-        driver!(cuMemAddressReserve(&mut ptr, len, 0, min_addr as _, 0));
-        Self {
+        try_driver!(cuMemAddressReserve(&mut ptr, len, 0, min_addr as _, 0))?;
+        Ok(Self {
             ptr,
             len,
             map: [(0, len.into())].into(),
-        }
+        })
     }
 }
 
@@ -108,14 +144,20 @@ pub struct PhyMem {
 }
 
 impl MemProp {
+    #[inline]
+    #[track_caller]
     pub fn create(&self, len: usize) -> Arc<PhyMem> {
+        self.try_create(len).unwrap()
+    }
+
+    pub fn try_create(&self, len: usize) -> Result<Arc<PhyMem>, Error> {
         let mut handle = 0;
-        driver!(cuMemCreate(&mut handle, len, &self.0, 0));
-        Arc::new(PhyMem {
+        try_driver!(cuMemCreate(&mut handle, len, &self.0, 0))?;
+        Ok(Arc::new(PhyMem {
             location: self.0.location,
             handle,
             len,
-        })
+        }))
     }
 }
 
@@ -146,11 +188,133 @@ impl PhyMem {
     }
 }
 
+#[cfg(unix)]
+impl PhyMem {
+    /// 导出物理页，物理页必须以 [`MemProp::shareable`] 创建。
+    #[inline]
+    #[track_caller]
+    pub fn export_fd(&self) -> std::os::fd::OwnedFd {
+        self.try_export_fd().unwrap()
+    }
+
+    pub fn try_export_fd(&self) -> Result<std::os::fd::OwnedFd, Error> {
+        use std::os::fd::FromRawFd;
+        let mut fd: std::ffi::c_int = -1;
+        try_driver!(cuMemExportToShareableHandle(
+            (&raw mut fd).cast(),
+            self.handle,
+            CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_POSIX_FILE_DESCRIPTOR,
+            0,
+        ))?;
+        Ok(unsafe { std::os::fd::OwnedFd::from_raw_fd(fd) })
+    }
+
+    /// # Safety
+    ///
+    /// 见 [`try_import_fd`](Self::try_import_fd)。
+    #[inline]
+    #[track_caller]
+    pub unsafe fn import_fd(fd: std::os::fd::BorrowedFd, len: usize) -> Arc<Self> {
+        unsafe { Self::try_import_fd(fd, len) }.unwrap()
+    }
+
+    /// 导入其他进程导出的 `len` 字节物理页，`fd` 仍由调用者持有。
+    ///
+    /// # Safety
+    ///
+    /// 驱动不能查询物理页的大小，`len` 必须等于导出的物理页的字节数，
+    /// 否则映射和访问导入的物理页会越界。
+    pub unsafe fn try_import_fd(
+        fd: std::os::fd::BorrowedFd,
+        len: usize,
+    ) -> Result<Arc<Self>, Error> {
+        use std::os::fd::AsRawFd;
+        let mut handle = 0;
+        try_driver!(cuMemImportFromShareableHandle(
+            &mut handle,
+            fd.as_raw_fd() as usize as _,
+            CUmemAllocationHandleType::CU_MEM_HANDLE_TYPE_POSIX_FILE_DESCRIPTOR,
+        ))?;
+        // 先包装句柄，以便查询失败时释放
+        let mut phy = PhyMem {
+            location: CUmemLocation {
+                type_: CUmemLocationType::CU_MEM_LOCATION_TYPE_DEVICE,
+                id: 0,
+            },
+            handle,
+            len,
+        };
+        let mut prop = std::mem::MaybeUninit::uninit();
+        try_driver!(cuMemGetAllocationPropertiesFromHandle(
+            prop.as_mut_ptr(),
+            handle
+        ))?;
+        phy.location = unsafe { prop.assume_init() }.location;
+        Ok(Arc::new(phy))
+    }
+}
+
 enum PhyRegion {
     Mapped(Arc<PhyMem>),
     Vacant(usize),
 }
 
+/// 虚地址区域中的一段。
+pub enum VirRegion<'a> {
+    /// 映射了物理页的段。
+    Mapped(&'a Arc<PhyMem>),
+    /// 空闲段的长度。
+    Vacant(usize),
+}
+
+impl VirRegion<'_> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Mapped(phy) => phy.len,
+            &Self::Vacant(len) => len,
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 映射或解除映射失败的原因。
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MapError {
+    /// 范围超出虚地址区域。
+    OutOfRange,
+    /// 范围内已有映射。
+    Occupied,
+    /// 偏移处没有映射的起点。
+    NotMapped,
+    /// 驱动调用失败。
+    Driver(Error),
+}
+
+impl From<Error> for MapError {
+    #[inline]
+    fn from(value: Error) -> Self {
+        Self::Driver(value)
+    }
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfRange => write!(f, "range is out of virtual memory"),
+            Self::Occupied => write!(f, "range is mapped"),
+            Self::NotMapped => write!(f, "offset is not mapped"),
+            Self::Driver(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
 impl From<Arc<PhyMem>> for PhyRegion {
     fn from(value: Arc<PhyMem>) -> Self {
         Self::Mapped(value)
@@ -164,61 +328,211 @@ impl From<usize> for PhyRegion {
 }
 
 impl VirMem {
+    /// 将物理页映射到 `offset`，物理页所在的设备可以读写。
+    #[inline]
+    #[track_caller]
     pub fn map(&mut self, offset: usize, phy: Arc<PhyMem>) -> &mut [DevByte] {
+        self.try_map(offset, phy).unwrap()
+    }
+
+    pub fn try_map(&mut self, offset: usize, phy: Arc<PhyMem>) -> Result<&mut [DevByte], MapError> {
+        let desc = CUmemAccessDesc {
+            location: phy.location,
+            flags: CUmemAccess_flags::CU_MEM_ACCESS_FLAGS_PROT_READWRITE,
+        };
+        self.map_(offset, phy, &[desc])
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn map_with_access(
+        &mut self,
+        offset: usize,
+        phy: Arc<PhyMem>,
+        access: &[(&Device, CUmemAccess_flags)],
+    ) -> &mut [DevByte] {
+        self.try_map_with_access(offset, phy, access).unwrap()
+    }
+
+    /// 将物理页映射到 `offset`，`access` 中的设备按指定的权限访问映射，其他设备不能访问。
+    pub fn try_map_with_access(
+        &mut self,
+        offset: usize,
+        phy: Arc<PhyMem>,
+        access: &[(&Device, CUmemAccess_flags)],
+    ) -> Result<&mut [DevByte], MapError> {
+        self.map_(offset, phy, &access_desc(access))
+    }
+
+    fn map_(
+        &mut self,
+        offset: usize,
+        phy: Arc<PhyMem>,
+        desc: &[CUmemAccessDesc],
+    ) -> Result<&mut [DevByte], MapError> {
         // 检查范围
-        assert!(offset <= self.len && offset + phy.len <= self.len);
+        let end = offset.checked_add(phy.len).ok_or(MapError::OutOfRange)?;
+        if end > self.len {
+            return Err(MapError::OutOfRange);
+        }
         // 查找所在区间
-        let (head, region) = self.map.range(..=offset).next_back().unwrap();
+        let (&head, region) = self.map.range(..=offset).next_back().unwrap();
         // 获取空闲段长度
         let len = match *region {
-            PhyRegion::Mapped(_) => panic!("mem is mapped"),
+            PhyRegion::Mapped(_) => return Err(MapError::Occupied),
             PhyRegion::Vacant(len) => len,
         };
-        assert!(phy.len <= len);
+        if end > head + len {
+            return Err(MapError::Occupied);
+        }
         // 映射
-        {
-            let ptr = self.ptr + offset as CUdeviceptr;
-            driver!(cuMemMap(ptr, phy.len, 0, phy.handle, 0));
-            let desc = CUmemAccessDesc {
-                location: phy.location,
-                flags: CUmemAccess_flags::CU_MEM_ACCESS_FLAGS_PROT_READWRITE,
-            };
-            driver!(cuMemSetAccess(ptr, phy.len, &desc, 1));
+        let ptr = self.ptr + offset as CUdeviceptr;
+        try_driver!(cuMemMap(ptr, phy.len, 0, phy.handle, 0))?;
+        if !desc.is_empty() {
+            try_driver!(cuMemSetAccess(ptr, phy.len, desc.as_ptr(), desc.len()))
+                .inspect_err(|_| driver!(cuMemUnmap(ptr, phy.len)))?
         }
         // 移除空闲段
-        let head = *head;
         self.map.remove(&head);
         // 插入映射段
         let phy_len = phy.len;
         self.map.insert(offset, phy.into());
         // 插入头尾空闲段
-        let head_len = offset - head;
-        let tail_len = len - head_len - phy_len;
-        if head_len > 0 {
-            self.map.insert(head, head_len.into());
+        if offset > head {
+            self.map.insert(head, (offset - head).into());
         }
-        if tail_len > 0 {
-            let tail = head + head_len + phy_len;
-            self.map.insert(tail, tail_len.into());
+        if head + len > end {
+            self.map.insert(end, (head + len - end).into());
         }
-        unsafe { std::slice::from_raw_parts_mut((self.ptr + offset as CUdeviceptr) as _, phy_len) }
+        Ok(unsafe { from_raw_parts_mut(ptr as _, phy_len) })
     }
 
+    #[inline]
+    #[track_caller]
     pub fn unmap(&mut self, offset: usize) -> Arc<PhyMem> {
-        let region = self.map.get_mut(&offset).expect("offset is not a boundary");
-        let len = match region {
-            PhyRegion::Mapped(phy_mem) => phy_mem.len,
-            PhyRegion::Vacant(_) => panic!("offset is not mapped"),
+        self.try_unmap(offset).unwrap()
+    }
+
+    /// 解除 `offset` 处的映射，空出的区间与相邻的空闲段合并。
+    pub fn try_unmap(&mut self, offset: usize) -> Result<Arc<PhyMem>, MapError> {
+        let len = match self.map.get(&offset) {
+            Some(PhyRegion::Mapped(phy)) => phy.len,
+            _ => return Err(MapError::NotMapped),
         };
-        let PhyRegion::Mapped(phy) = std::mem::replace(region, len.into()) else {
+        try_driver!(cuMemUnmap(self.ptr + offset as CUdeviceptr, len))?;
+        let Some(PhyRegion::Mapped(phy)) = self.map.remove(&offset) else {
             unreachable!()
         };
+        // 合并相邻的空闲段
+        let mut head = offset;
+        let mut len = len;
+        if let Some((&prev, &PhyRegion::Vacant(prev_len))) = self.map.range(..offset).next_back() {
+            self.map.remove(&prev);
+            head = prev;
+            len += prev_len
+        }
+        if let Some(&PhyRegion::Vacant(next_len)) = self.map.get(&(head + len)) {
+            self.map.remove(&(head + len));
+            len += next_len
+        }
+        self.map.insert(head, len.into());
+        Ok(phy)
+    }
+
+    /// 依次遍历区域中的映射段和空闲段，产生各段的偏移。
+    pub fn regions(&self) -> impl Iterator<Item = (usize, VirRegion<'_>)> {
+        self.map.iter().map(|(&offset, region)| {
+            let region = match region {
+                PhyRegion::Mapped(phy) => VirRegion::Mapped(phy),
+                &PhyRegion::Vacant(len) => VirRegion::Vacant(len),
+            };
+            (offset, region)
+        })
+    }
+
+    /// 查找能容纳 `len` 字节的空闲位置，其虚地址是 `align` 的整数倍。`align` 为 0 时返回 `None`。
+    pub fn find_vacant(&self, len: usize, align: usize) -> Option<usize> {
+        if align == 0 {
+            return None;
+        }
+        self.map.iter().find_map(|(&offset, region)| match *region {
+            PhyRegion::Vacant(vacant) => {
+                let ptr = self.ptr as usize;
+                let start = (ptr + offset).checked_next_multiple_of(align)? - ptr;
+                (start.checked_add(len)? <= offset + vacant).then_some(start)
+            }
+            PhyRegion::Mapped(_) => None,
+        })
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn access(&self, offset: usize, dev: &Device) -> CUmemAccess_flags {
+        self.try_access(offset, dev).unwrap()
+    }
+
+    /// 查询 `dev` 访问 `offset` 处映射的权限。
+    pub fn try_access(&self, offset: usize, dev: &Device) -> Result<CUmemAccess_flags, MapError> {
+        use CUmemAccess_flags::*;
+        if offset >= self.len {
+            return Err(MapError::OutOfRange);
+        }
+        let location = CUmemLocation {
+            type_: CUmemLocationType::CU_MEM_LOCATION_TYPE_DEVICE,
+            id: unsafe { dev.as_raw() },
+        };
+        let mut flags = 0;
+        try_driver!(cuMemGetAccess(
+            &mut flags,
+            &location,
+            self.ptr + offset as CUdeviceptr
+        ))?;
+        Ok(match flags {
+            f if f == CU_MEM_ACCESS_FLAGS_PROT_READWRITE as _ => CU_MEM_ACCESS_FLAGS_PROT_READWRITE,
+            f if f == CU_MEM_ACCESS_FLAGS_PROT_READ as _ => CU_MEM_ACCESS_FLAGS_PROT_READ,
+            _ => CU_MEM_ACCESS_FLAGS_PROT_NONE,
+        })
+    }
+
+    #[inline]
+    #[track_caller]
+    pub fn set_access(&self, offset: usize, access: &[(&Device, CUmemAccess_flags)]) {
+        self.try_set_access(offset, access).unwrap()
+    }
+
+    /// 修改映射在 `offset` 的物理页的访问权限，不需要解除映射，`access` 中未列出的设备权限不变。
+    pub fn try_set_access(
+        &self,
+        offset: usize,
+        access: &[(&Device, CUmemAccess_flags)],
+    ) -> Result<(), MapError> {
+        let len = match self.map.get(&offset) {
+            Some(PhyRegion::Mapped(phy)) => phy.len,
+            _ => return Err(MapError::NotMapped),
+        };
+        if access.is_empty() {
+            return Ok(());
+        }
+        let desc = access_desc(access);
         let ptr = self.ptr + offset as CUdeviceptr;
-        driver!(cuMemUnmap(ptr, phy.len));
-        phy
+        try_driver!(cuMemSetAccess(ptr, len, desc.as_ptr(), desc.len()))?;
+        Ok(())
     }
 }
 
+fn access_desc(access: &[(&Device, CUmemAccess_flags)]) -> Vec<CUmemAccessDesc> {
+    access
+        .iter()
+        .map(|&(dev, flags)| CUmemAccessDesc {
+            location: CUmemLocation {
+                type_: CUmemLocationType::CU_MEM_LOCATION_TYPE_DEVICE,
+                id: unsafe { dev.as_raw() },
+            },
+            flags,
+        })
+        .collect()
+}
+
 #[test]
 fn test_behavior() {
     use crate::{Device, memcpy_d2h, memcpy_h2d, virtual_mem::VirMem};
@@ -254,3 +568,131 @@ fn test_behavior() {
 
     assert_eq!(&*host, &*host_)
 }
+
+#[cfg(unix)]
+#[test]
+fn test_share() {
+    use crate::{Device, memcpy_d2h, memcpy_h2d, recv_fd, send_fd};
+    use std::os::{fd::AsFd, unix::net::UnixStream};
+
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    let dev = Device::new(0);
+    let prop = dev.mem_prop().shareable(true);
+    let len = prop.granularity_minimum();
+    let phy = prop.create(len);
+
+    // 通过 unix socket 将物理页发送给另一个进程
+    let (a, b) = UnixStream::pair().unwrap();
+    send_fd(&a, phy.export_fd().as_fd()).unwrap();
+    let imported = unsafe { PhyMem::import_fd(recv_fd(&b).unwrap().as_fd(), len) };
+    assert_eq!(imported.len(), len);
+
+    let mut src = VirMem::new(len, 0);
+    let mut dst = VirMem::new(len, 0);
+    let src = src.map(0, phy);
+    let dst = dst.map(0, imported);
+    let host = (0..len as u32 / 4).collect::<Vec<_>>();
+    let mut host_ = vec![0u32; host.len()];
+    dev.context().apply(|_| {
+        memcpy_h2d(src, &host);
+        memcpy_d2h(&mut host_, dst)
+    });
+    assert_eq!(host, host_)
+}
+
+#[test]
+fn test_access() {
+    use crate::{DevByte, Device, memcpy_d2h, memcpy_h2d};
+    use CUmemAccess_flags::*;
+
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    let dev = Device::new(0);
+    let prop = dev.mem_prop();
+    let len = prop.granularity_minimum();
+
+    let mut virmem = VirMem::new(2 * len, 0);
+    let mapped = virmem.map_with_access(
+        len,
+        prop.create(len),
+        &[(&dev, CU_MEM_ACCESS_FLAGS_PROT_READWRITE)],
+    );
+    let host = (0..len as u32 / 4).collect::<Vec<_>>();
+    dev.context().apply(|_| memcpy_h2d(mapped, &host));
+    assert_eq!(virmem.access(len, &dev), CU_MEM_ACCESS_FLAGS_PROT_READWRITE);
+
+    // 不解除映射，将映射改为只读
+    virmem.set_access(len, &[(&dev, CU_MEM_ACCESS_FLAGS_PROT_READ)]);
+    assert_eq!(virmem.access(len, &dev), CU_MEM_ACCESS_FLAGS_PROT_READ);
+    assert_eq!(
+        virmem.try_set_access(0, &[(&dev, CU_MEM_ACCESS_FLAGS_PROT_READ)]),
+        Err(MapError::NotMapped)
+    );
+    assert_eq!(virmem.try_access(2 * len, &dev), Err(MapError::OutOfRange));
+    let mapped = unsafe { from_raw_parts(virmem[len..].as_ptr().cast::<DevByte>(), len) };
+    let mut host_ = vec![0u32; host.len()];
+    dev.context().apply(|_| memcpy_d2h(&mut host_, mapped));
+    assert_eq!(host, host_);
+
+    // 未列出的设备不能访问
+    if Device::count() > 1 {
+        let other = Device::new(1);
+        assert_eq!(virmem.access(len, &other), CU_MEM_ACCESS_FLAGS_PROT_NONE);
+        virmem.set_access(len, &[(&other, CU_MEM_ACCESS_FLAGS_PROT_READ)]);
+        assert_eq!(virmem.access(len, &other), CU_MEM_ACCESS_FLAGS_PROT_READ);
+        assert_eq!(virmem.access(len, &dev), CU_MEM_ACCESS_FLAGS_PROT_READ)
+    }
+}
+
+#[test]
+fn test_regions() {
+    use crate::Device;
+
+    if let Err(crate::NoDevice) = crate::init() {
+        return;
+    }
+    let prop = Device::new(0).mem_prop();
+    let len = prop.granularity_minimum();
+    let mut virmem = VirMem::new(4 * len, 0);
+    let phy = prop.create(len);
+
+    virmem.map(len, phy.clone());
+    virmem.map(2 * len, prop.create(len));
+    let regions = virmem
+        .regions()
+        .map(|(offset, region)| (offset, region.len(), matches!(region, VirRegion::Mapped(_))))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        regions,
+        [
+            (0, len, false),
+            (len, len, true),
+            (2 * len, len, true),
+            (3 * len, len, false)
+        ]
+    );
+    assert_eq!(virmem.find_vacant(len, len), Some(0));
+    assert_eq!(virmem.find_vacant(2 * len, len), None);
+    assert_eq!(virmem.find_vacant(usize::MAX, len), None);
+    assert_eq!(virmem.find_vacant(len, 0), None);
+
+    // 误用返回错误而不是 panic
+    assert_eq!(
+        virmem.try_map(len, phy.clone()).err(),
+        Some(MapError::Occupied)
+    );
+    assert_eq!(
+        virmem.try_map(4 * len, phy.clone()).err(),
+        Some(MapError::OutOfRange)
+    );
+    assert_eq!(virmem.try_unmap(0).err(), Some(MapError::NotMapped));
+
+    // 解除映射后与相邻的空闲段合并
+    virmem.unmap(len);
+    virmem.unmap(2 * len);
+    assert_eq!(virmem.regions().count(), 1);
+    assert_eq!(virmem.find_vacant(4 * len, len), Some(0))
+}